@@ -0,0 +1,189 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//! How much a server costs while its clients are idle, and how fast it fans
+//! their messages out; for either core.
+//!
+//! Run as `cargo bench --bench fanout`. Another build, that speaks the same
+//! protocol, like one of an earlier commit, is measured alongside, if its
+//! path is given by `PRATA_BEFORE`.
+use std::{
+	env::var,
+	fs::read_to_string,
+	sync::{
+		Arc,
+		atomic::{AtomicUsize, Ordering},
+	},
+	thread::{sleep, spawn},
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use besked::Frame;
+
+use crate::common::{Peer, Server, UNLIMITED, percentile};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+#[path = "../tests/common/mod.rs"]
+mod common;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+const CLIENTS: usize = 100;
+/// How many messages every client sends.
+const MESSAGES: usize = 100;
+/// How many rounds of messages a client may send ahead of the slowest
+/// listener; so, that no outbox ever holds more than twice as many frames as
+/// there are other clients, and nobody is cut off.
+const AHEAD: usize = 1;
+/// How long the clients stay silent, while the cost of idling is measured.
+const IDLE: Duration = Duration::from_secs(3);
+/// How long the clients are given to hear everything.
+const WITHIN: Duration = Duration::from_secs(120);
+/// How many clock-ticks a second has, as `/proc` counts them.
+const TICKS: f64 = 100.0;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// What a run measured.
+struct Measured {
+	/// The share of a processor, that the server took while idling.
+	idle: Option<f64>,
+	/// How many messages were delivered a second.
+	throughput: f64,
+	/// How long a message took to reach another client; the median, and
+	/// the 99th percentile.
+	latency: (Duration, Duration),
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+fn main() {
+	let own = env!("CARGO_BIN_EXE_prata");
+	let mut runs = vec![("threads", own), ("events", own)];
+	let before = var("PRATA_BEFORE").ok();
+	if let Some(before) = &before {
+		runs.push(("threads (before)", before));
+	}
+
+	println!("{CLIENTS} clients, sending {MESSAGES} messages each:");
+	println!("{:<18}{:>10}{:>16}{:>14}{:>14}", "core", "idle CPU", "messages/s", "p50 latency", "p99 latency");
+	for (name, program) in runs {
+		let core = name
+			.split(' ')
+			.next()
+			.unwrap_or(name);
+		let measured = measure(program, core);
+		println!(
+			"{name:<18}{:>10}{:>16.0}{:>14}{:>14}",
+			measured
+				.idle
+				.map_or("n/a".into(), |idle| format!("{:.1}%", idle * 100.0)),
+			measured.throughput,
+			format!("{:.2?}", measured.latency.0),
+			format!("{:.2?}", measured.latency.1),
+		);
+	}
+}
+
+fn measure(program: &str, core: &str) -> Measured {
+	let server = Server::start_from(program, &[&UNLIMITED[..], &["--core", core]].concat());
+	let peers: Vec<Peer> = (0 .. CLIENTS)
+		.map(|index| Peer::join(&server, &format!("bench{index}")))
+		.collect();
+
+	// NOTE: whatever the joins set off is given a moment to settle first.
+	sleep(Duration::from_millis(500));
+	let before = cpu(server.get_process());
+	sleep(IDLE);
+	let idle = before
+		.zip(cpu(server.get_process()))
+		.map(|(before, after)| (after - before) / TICKS / IDLE.as_secs_f64());
+
+	let expected = MESSAGES * (CLIENTS - 1);
+	let heard: Arc<[AtomicUsize]> = (0 .. CLIENTS)
+		.map(|_| AtomicUsize::new(0))
+		.collect();
+	let started = Instant::now();
+	let mut senders = Vec::with_capacity(CLIENTS);
+	let mut listeners = Vec::with_capacity(CLIENTS);
+	for (index, mut peer) in peers
+		.into_iter()
+		.enumerate()
+	{
+		let mut writer = peer.writer();
+		let mut frame = peer.message("");
+		let everyone = heard.clone();
+		senders.push(spawn(move || {
+			for round in 0 .. MESSAGES {
+				let least = round.saturating_sub(AHEAD) * (CLIENTS - 1);
+				let until = Instant::now() + WITHIN;
+				while everyone
+					.iter()
+					.any(|heard| heard.load(Ordering::Relaxed) < least)
+				{
+					assert!(Instant::now() < until, "round {round} was never heard by everyone");
+					sleep(Duration::from_micros(100));
+				}
+				if let Frame::Message(message) = &mut frame {
+					message.set_content(now().to_string());
+				}
+				frame
+					.send(&mut writer)
+					.expect("a message could not be sent");
+			}
+		}));
+		let heard = heard.clone();
+		listeners.push(spawn(move || {
+			let mut latencies = Vec::with_capacity(expected);
+			peer.until(WITHIN, |frame| {
+				if let Frame::Message(message) = frame
+					&& let Ok(sent) = message
+						.get_content()
+						.parse::<u128>()
+				{
+					latencies.push(Duration::from_nanos(now().saturating_sub(sent) as u64));
+					heard[index].store(latencies.len(), Ordering::Relaxed);
+				}
+				latencies.len() == expected
+			});
+			latencies
+		}));
+	}
+	for sender in senders {
+		sender
+			.join()
+			.expect("a sender failed");
+	}
+	let mut latencies = Vec::with_capacity(expected * CLIENTS);
+	for listener in listeners {
+		latencies.extend(listener
+			.join()
+			.expect("a listener failed"));
+	}
+	let elapsed = started.elapsed();
+	assert_eq!(latencies.len(), expected * CLIENTS, "not every message reached everyone");
+
+	Measured {
+		idle,
+		throughput: latencies.len() as f64 / elapsed.as_secs_f64(),
+		latency: (percentile(&mut latencies, 0.5), percentile(&mut latencies, 0.99)),
+	}
+}
+
+/// How many clock-ticks the process has taken so far; none, where `/proc`
+/// doesn't tell.
+fn cpu(process: u32) -> Option<f64> {
+	let stat = read_to_string(format!("/proc/{process}/stat")).ok()?;
+	// NOTE: the name may hold spaces, but ends with the last parenthesis.
+	let (_, fields) = stat.rsplit_once(')')?;
+	let mut fields = fields.split_whitespace();
+	let user: f64 = fields
+		.nth(11)?
+		.parse()
+		.ok()?;
+	let system: f64 = fields
+		.next()?
+		.parse()
+		.ok()?;
+	Some(user + system)
+}
+
+/// The nanoseconds since the epoch; what every message carries, that it was
+/// sent at.
+fn now() -> u128 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_nanos()
+}
@@ -0,0 +1,26 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use chrono::{DateTime, Utc};
+
+use crate::{Moderate, Transcript};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// What whoever runs a server can have it do, through a [`Control`]-frame.
+///
+/// [`Control`]: crate::Frame::Control
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum Control {
+	/// Write a snapshot of the history, the registry and the mailboxes to the
+	/// given path; on the server's side.
+	Backup(Box<str>),
+	/// Write a transcript of the messages, that were written since and until
+	/// the given points in time, whichever are set, to the given path; on the
+	/// server's side, and in its local time.
+	Export(Box<str>, Transcript, Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+	/// Take the messages of a JSON Lines transcript at the given path into the
+	/// history, skipping those, that it holds already; on the server's side.
+	Import(Box<str>),
+	/// Moderate the users of the server, as an operator would; the only way
+	/// to grant, or revoke, the role of one.
+	Moderate(Moderate),
+}
@@ -0,0 +1,30 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// What became of a direct message.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum Delivery {
+	/// Handed to the recipient, who is online.
+	Delivered,
+	/// Kept by the server, until the recipient, who is registered, connects.
+	Queued,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Delivery {
+	#[inline(always)]
+	pub const fn as_byte(&self) -> u8 {
+		match self {
+			Self::Delivered => 0,
+			Self::Queued => 1,
+		}
+	}
+
+	#[inline(always)]
+	pub const fn from_byte(byte: u8) -> Option<Self> {
+		match byte {
+			0 => Some(Self::Delivered),
+			1 => Some(Self::Queued),
+			_ => None,
+		}
+	}
+}
@@ -3,16 +3,41 @@ use std::{io, str::Utf8Error};
 
 use thiserror::Error;
 
-use crate::{Message, Nickname};
+use crate::{Identifier, Measure, Message, Nickname};
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 #[derive(Debug, Error)]
 pub enum UsernameFromStrError {
-	#[error(r"`{0}` doesn't match `^@?[[:alnum:][:punct:]--[\{{\}}\(\)\[\]@]]{{1,31}}`")]
-	NoMatch(Box<str>),
-	#[error(r"`{0}` is `{1}` bytes long, but it shouldn't exceed `31`.")]
-	TooLong(Box<str>, usize),
+	#[error(r"`{0}` contains `{1:?}`, which is outside of the allowed character-classes.")]
+	NoMatch(Box<str>, char),
+	#[error(r"`{0}` contains the forbidden character `{1:?}`.")]
+	Forbidden(Box<str>, char),
+	#[error(r"`{0}` measures `{1}` {2}, but it shouldn't exceed `{3}`.")]
+	TooLong(Box<str>, usize, Measure, usize),
+	#[error("A nickname cannot be empty.")]
+	Empty,
+	#[error(r"`{0}` is too easily confused with the already taken `{1}`.")]
+	Confusable(Box<str>, Box<str>),
+
+	#[error(transparent)]
+	Nickname(#[from] NicknameError),
 }
 
+#[derive(Debug, Error)]
+#[error("`{0}` names no measure; expected one of `codepoints`, `graphemes` or `columns`.")]
+pub struct MeasureFromStrError(pub Box<str>);
+
+#[derive(Debug, Error)]
+#[error("`{0}` names no state; expected one of `online`, `away`, `busy` or `offline`.")]
+pub struct StateFromStrError(pub Box<str>);
+
+#[derive(Debug, Error)]
+#[error("`{0}` names no transcript; expected one of `text`, `markdown`, `html` or `jsonl`.")]
+pub struct TranscriptFromStrError(pub Box<str>);
+
+#[derive(Debug, Error)]
+#[error("Expected: {0}")]
+pub struct ModerateFromStrError(pub Box<str>);
+
 #[derive(Debug, Error)]
 pub enum MessageError {
 	#[error("The connection has been shutdown.")]
@@ -43,13 +68,25 @@ pub enum MessageError {
 	InvalidTimestamp,
 	#[error("A message's stream `{0:?}` isn't null-terminated.")]
 	NoNull(Box<[u8]>),
+
+	#[error("The frame-kind `{0:#04x}` is unknown.")]
+	UnknownKind(u8),
+	#[error("A frame ended before all of its fields were read.")]
+	Truncated,
+	#[error("`{0}` bytes exceed the limit of `{1}`.")]
+	TooLong(usize, usize),
+	#[error("A frame of kind `{0:#04x}` wasn't expected at this point.")]
+	Unexpected(u8),
+
+	#[error(transparent)]
+	Server(#[from] ServerError),
 }
 
 #[derive(Debug, Error)]
 pub enum NicknameError {
 	#[error(
 		"The text `{0}` (of codepoint-length `{1}`) is exceding the maximum amount `{}`.",
-		Nickname::MAX_GLYPH_COUNT
+		Nickname::MAX_CHAR_COUNT
 	)]
 	TooManyGlyphs(Box<str>, usize),
 
@@ -71,7 +108,77 @@ pub enum UserError {
 	Nickname(#[from] NicknameError),
 	#[error(transparent)]
 	Identifier(#[from] IdentifierError),
+	#[error(transparent)]
+	Policy(#[from] UsernameFromStrError),
+	#[error(transparent)]
+	Profile(#[from] ProfileError),
 
 	#[error("The, from the header extracted, length `{0}` mismatches the actual `{1}`")]
 	LengthMismatch(usize, usize),
 }
+
+#[derive(Debug, Error)]
+pub enum ProfileError {
+	#[error("The {0} is `{1}` codepoints long, but it shouldn't exceed `{2}`.")]
+	TooLong(&'static str, usize, usize),
+}
+
+/// Faults, which a server reports back to one of its clients.
+#[derive(Clone)]
+#[derive(Debug, Error)]
+pub enum ServerError {
+	#[error("The nickname was refused: {0}")]
+	Nickname(Box<str>),
+	#[error("The profile was refused: {0}")]
+	Profile(Box<str>),
+	#[error("No user is known by the identifier `{0}`.")]
+	Unknown(Identifier),
+	#[error("The server didn't expect that transaction.")]
+	Unexpected,
+	#[error("The connection was cut off, for falling too far behind.")]
+	TooSlow,
+	#[error("Too much is sent too quickly; wait `{0}`ms before sending more.")]
+	SlowDown(u32),
+	#[error("Sending is muted for flooding; `{0}`s are left.")]
+	Muted(u32),
+	#[error("The connection was cut off, for flooding.")]
+	Flooding,
+	#[error("The connection was cut off, for a frame longer than `{0}` bytes.")]
+	TooLong(u32),
+	#[error("No more than `{0}` connections are allowed from the same address.")]
+	Crowded(u32),
+	#[error("The message could not be kept, and was not sent.")]
+	Unsaved,
+	#[error("The history could not be read.")]
+	History,
+	#[error("The history could not be searched.")]
+	Search,
+	#[error("`{0}` is neither online, nor registered to have messages kept.")]
+	Unregistered(Box<str>),
+	#[error("No more messages are kept for `{0}`.")]
+	Inbox(Box<str>),
+	#[error("The nickname is registered under another secret.")]
+	Secret,
+	#[error("The nickname could not be registered.")]
+	Registry,
+	#[error("The control token is wrong, or the server takes no control.")]
+	Token,
+	#[error("The backup failed: {0}")]
+	Backup(Box<str>),
+	#[error("The export failed: {0}")]
+	Export(Box<str>),
+	#[error("The import failed: {0}")]
+	Import(Box<str>),
+	#[error("Only operators may moderate.")]
+	Operator,
+	#[error("The moderation failed: {0}")]
+	Moderation(Box<str>),
+	#[error("You were kicked: {0}")]
+	Kicked(Box<str>),
+	#[error("You are banned: {0}")]
+	Banned(Box<str>),
+	#[error("Sending is muted by an operator for `{0}`s more: {1}")]
+	Silenced(u32, Box<str>),
+	#[error("No connections are accepted from your address.")]
+	Denied,
+}
@@ -0,0 +1,957 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	io::{self, ErrorKind, Read, Write},
+	str::{FromStr, from_utf8},
+	time::Duration,
+};
+
+use chrono::DateTime;
+
+use crate::{
+	Control, Delivery, HistoryQuery, Identifier, Message, Moderate, Nickname, Presence, Profile, Retention, SearchQuery,
+	State, Target, Transcript,
+	errors::{MessageError, ServerError, UserError},
+	policy::{CharClasses, Measure, NicknamePolicy},
+};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Every transaction, that travels between a server and its clients.
+///
+/// On the wire, a frame is made up of its kind, the big-endian length of its
+/// body and the body itself: `[kind: u8][length: u32][body]`.
+#[derive(Debug)]
+pub enum Frame {
+	/// The rules, by which the server judges nicknames.
+	///
+	/// Always the first transaction sent by a server.
+	Policy(NicknamePolicy),
+	/// The nickname that a client would like to use, alongside its profile,
+	/// and the secret it was registered under; empty, if it is not.
+	///
+	/// Always the first transaction sent by a client.
+	Join(Nickname, Profile, Box<str>),
+	/// The acceptance of a [`Join`], carrying the assigned identifier, the
+	/// canonical form of the requested nickname, and the longest body the
+	/// server accepts.
+	///
+	/// [`Join`]: Self::Join
+	Welcome(Identifier, Nickname, u32),
+	/// A message, preceded by its sequence on the wire.
+	Message(Message),
+	Error(ServerError),
+
+	/// The sequence that the server assigned to the oldest message of a
+	/// client, that has not yet been acknowledged.
+	///
+	/// Acknowledgements are sent in the same order as the messages arrived.
+	Ack(u64),
+	/// The refusal of the oldest message of a client, that has not yet been
+	/// acknowledged, and why; sent in place of its [`Ack`], so that every
+	/// message is answered by exactly one of either.
+	///
+	/// [`Ack`]: Self::Ack
+	Refused(ServerError),
+	/// The last sequence that a user has read.
+	///
+	/// Sent by a client about itself, in which case the identifier is
+	/// ignored, and broadcast by the server to everyone.
+	Read(Identifier, u64),
+
+	/// A user's profile.
+	///
+	/// Sent by a client to update its own profile, in which case the
+	/// identifier is ignored, and by the server to answer a [`ProfileQuery`].
+	///
+	/// [`ProfileQuery`]: Self::ProfileQuery
+	Profile(Identifier, Profile),
+	/// Ask the server for the profile of the user with the given identifier.
+	ProfileQuery(Identifier),
+
+	/// A change in someone's availability.
+	///
+	/// Sent by a client to announce its own, in which case the identifier and
+	/// nickname are ignored, and broadcast by the server to everyone.
+	Presence(Presence),
+	/// The presence of everyone connected; sent by the server after its
+	/// [`Welcome`].
+	///
+	/// [`Welcome`]: Self::Welcome
+	Roster(Vec<Presence>),
+
+	/// A sign of someone typing; relayed by the server, but never stored.
+	///
+	/// Sent by a client, in which case the identifier and nickname are
+	/// ignored, and passed on by the server to everyone else.
+	Typing(Identifier, Nickname),
+
+	/// A sign of life, to be answered with a [`Pong`].
+	///
+	/// [`Pong`]: Self::Pong
+	Ping,
+	Pong,
+
+	/// The server is about to close every connection, for an optional
+	/// reason; nothing follows.
+	Closing(String),
+
+	/// Ask the server for a page of its history.
+	History(HistoryQuery),
+	/// A message from the history, laid out like a [`Message`]; never mixed up
+	/// with one, that is sent just now.
+	///
+	/// [`Message`]: Self::Message
+	Backfill(Message),
+	/// Ends a page of [`Backfill`]s, telling how many of them were sent, and
+	/// after which sequence the next page starts; zero, if there is none.
+	///
+	/// [`Backfill`]: Self::Backfill
+	Page(u32, u64),
+
+	/// How long, and how much of, its history the server keeps; sent after
+	/// its [`Welcome`].
+	///
+	/// [`Welcome`]: Self::Welcome
+	Retention(Retention),
+
+	/// Search the server's history.
+	Search(SearchQuery),
+	/// A message, that a [`Search`] found; laid out like a [`Message`].
+	///
+	/// [`Search`]: Self::Search
+	/// [`Message`]: Self::Message
+	Hit(Message),
+	/// Ends the [`Hit`]s of a search, telling how many were sent, and how many
+	/// there are in all; the latest ones are sent first.
+	///
+	/// [`Hit`]: Self::Hit
+	Found(u32, u32),
+
+	/// Register the nickname in use under the given secret, which has to be
+	/// sent along in every later [`Join`] with it.
+	///
+	/// [`Join`]: Self::Join
+	Register(Box<str>),
+	/// The answer to a [`Register`].
+	///
+	/// [`Register`]: Self::Register
+	Registered(Nickname),
+	/// A message for the user with the given nickname alone; laid out like a
+	/// [`Message`], but without a sequence.
+	///
+	/// Sent by a client, in which case the author is ignored, and passed on
+	/// by the server to the recipient, right away or once they connect.
+	///
+	/// [`Message`]: Self::Message
+	Direct(Nickname, Message),
+	/// What became of a [`Direct`] message for the user with the given
+	/// nickname.
+	///
+	/// [`Direct`]: Self::Direct
+	Delivery(Nickname, Delivery),
+
+	/// Ask the server for the head of its history's hash-chain.
+	HeadQuery,
+	/// The sequence of the latest message, and the hash of its entry, which
+	/// every entry before it is chained into; an answer to a [`HeadQuery`].
+	///
+	/// [`HeadQuery`]: Self::HeadQuery
+	Head(u64, [u8; 32]),
+	/// A message as the server keeps it, after the hash of the entry before
+	/// it; laid out like a [`Message`], but never sent.
+	///
+	/// One, that was imported, is kept under a kind of its own, so that the
+	/// layout stays the same.
+	///
+	/// [`Message`]: Self::Message
+	Chained([u8; 32], Message),
+
+	/// Have the server do something, given its control token; sent instead
+	/// of a [`Join`].
+	///
+	/// [`Join`]: Self::Join
+	Control(Box<str>, Control),
+	/// What a [`Control`] did; the last frame of its connection.
+	///
+	/// [`Control`]: Self::Control
+	Done(Box<str>),
+
+	/// Have the server moderate its users; only heeded from an operator, and
+	/// answered with a [`Done`].
+	///
+	/// [`Done`]: Self::Done
+	Moderate(Moderate),
+}
+
+/// Serialiser for the body of a [`Frame`].
+struct Body(Vec<u8>);
+
+/// Deserialiser for the body of a [`Frame`].
+struct Fields<'a>(&'a [u8]);
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+mod kind {
+	pub const POLICY: u8 = 0x00;
+	pub const JOIN: u8 = 0x01;
+	pub const WELCOME: u8 = 0x02;
+	pub const MESSAGE: u8 = 0x03;
+	pub const ERROR: u8 = 0x04;
+	pub const PROFILE: u8 = 0x05;
+	pub const PROFILE_QUERY: u8 = 0x06;
+	pub const PRESENCE: u8 = 0x07;
+	pub const ROSTER: u8 = 0x08;
+	pub const TYPING: u8 = 0x09;
+	pub const ACK: u8 = 0x0A;
+	pub const READ: u8 = 0x0B;
+	pub const PING: u8 = 0x0C;
+	pub const PONG: u8 = 0x0D;
+	pub const CLOSING: u8 = 0x0E;
+	pub const HISTORY: u8 = 0x0F;
+	pub const BACKFILL: u8 = 0x10;
+	pub const PAGE: u8 = 0x11;
+	pub const RETENTION: u8 = 0x12;
+	pub const SEARCH: u8 = 0x13;
+	pub const HIT: u8 = 0x14;
+	pub const FOUND: u8 = 0x15;
+	pub const REGISTER: u8 = 0x16;
+	pub const REGISTERED: u8 = 0x17;
+	pub const DIRECT: u8 = 0x18;
+	pub const DELIVERY: u8 = 0x19;
+	pub const HEAD_QUERY: u8 = 0x1A;
+	pub const HEAD: u8 = 0x1B;
+	pub const CHAINED: u8 = 0x1C;
+	pub const CONTROL: u8 = 0x1D;
+	pub const DONE: u8 = 0x1E;
+	pub const IMPORTED: u8 = 0x1F;
+	pub const MODERATE: u8 = 0x20;
+	pub const REFUSED: u8 = 0x21;
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Frame {
+	/// The size of the kind and the length.
+	pub const HEAD_LENGTH: usize = size_of::<u8>() + size_of::<u32>();
+	/// How many heartbeats may go by in silence, before the other party is
+	/// taken to be gone.
+	pub const MISSED_HEARTBEATS: u32 = 3;
+	/// The usual time between two pings.
+	pub const HEARTBEAT: Duration = Duration::from_secs(15);
+	/// The longest body read by default; a server may advertise its own in its
+	/// [`Welcome`].
+	///
+	/// [`Welcome`]: Self::Welcome
+	pub const MAX_LENGTH: u32 = 1 << 20;
+
+	pub const fn kind(&self) -> u8 {
+		match self {
+			Self::Policy(_) => kind::POLICY,
+			Self::Join(..) => kind::JOIN,
+			Self::Welcome(..) => kind::WELCOME,
+			Self::Message(_) => kind::MESSAGE,
+			Self::Error(_) => kind::ERROR,
+			Self::Profile(..) => kind::PROFILE,
+			Self::ProfileQuery(_) => kind::PROFILE_QUERY,
+			Self::Presence(_) => kind::PRESENCE,
+			Self::Roster(_) => kind::ROSTER,
+			Self::Typing(..) => kind::TYPING,
+			Self::Ack(_) => kind::ACK,
+			Self::Refused(_) => kind::REFUSED,
+			Self::Read(..) => kind::READ,
+			Self::Ping => kind::PING,
+			Self::Pong => kind::PONG,
+			Self::Closing(_) => kind::CLOSING,
+			Self::History(_) => kind::HISTORY,
+			Self::Backfill(_) => kind::BACKFILL,
+			Self::Page(..) => kind::PAGE,
+			Self::Retention(_) => kind::RETENTION,
+			Self::Search(_) => kind::SEARCH,
+			Self::Hit(_) => kind::HIT,
+			Self::Found(..) => kind::FOUND,
+			Self::Register(_) => kind::REGISTER,
+			Self::Registered(_) => kind::REGISTERED,
+			Self::Direct(..) => kind::DIRECT,
+			Self::Delivery(..) => kind::DELIVERY,
+			Self::HeadQuery => kind::HEAD_QUERY,
+			Self::Head(..) => kind::HEAD,
+			Self::Chained(_, message) if message.get_imported() => kind::IMPORTED,
+			Self::Chained(..) => kind::CHAINED,
+			Self::Control(..) => kind::CONTROL,
+			Self::Done(_) => kind::DONE,
+			Self::Moderate(_) => kind::MODERATE,
+		}
+	}
+
+	pub fn send(&self, to: &mut impl Write) -> Result<(), MessageError> { self.send_limited(to, <u32>::MAX) }
+
+	/// Send the frame, unless its body is longer than `limit`.
+	pub fn send_limited(&self, to: &mut impl Write, limit: u32) -> Result<(), MessageError> {
+		let bytes = self.to_bytes()?;
+		let length = bytes.len() - Self::HEAD_LENGTH;
+		if length > limit as usize {
+			Err(MessageError::TooLong(length, limit as usize))?
+		}
+		to.write_all(&bytes)
+			.and_then(|_| to.flush())
+			.map_err(|error| classify(error, false))
+	}
+
+	#[inline(always)]
+	pub fn recv(from: &mut impl Read) -> Result<Self, MessageError> { Self::recv_limited(from, Self::MAX_LENGTH) }
+
+	/// Receive a frame, whose body may not be longer than `limit`.
+	///
+	/// The length is checked before the body is read, so that an oversized
+	/// frame never takes up any memory.
+	pub fn recv_limited(from: &mut impl Read, limit: u32) -> Result<Self, MessageError> {
+		let mut head = [0; Self::HEAD_LENGTH];
+		// NOTE: only an end between two frames is a clean one.
+		from.read_exact(&mut head[.. 1])
+			.map_err(|error| classify(error, true))?;
+		from.read_exact(&mut head[1 ..])
+			.map_err(|error| classify(error, false))?;
+		let [kind, length @ ..] = head;
+		let length = <u32>::from_be_bytes(length);
+		if length > limit {
+			Err(MessageError::TooLong(length as usize, limit as usize))?
+		}
+
+		let mut body = vec![0; length as usize];
+		from.read_exact(&mut body)
+			.map_err(|error| classify(error, false))?;
+
+		Self::from_body(kind, &body)
+	}
+
+	/// Serialise the whole frame, including its head.
+	pub fn to_bytes(&self) -> Result<Vec<u8>, MessageError> {
+		let mut body = Body(Vec::from([0; Self::HEAD_LENGTH]));
+		match self {
+			Self::Policy(policy) => {
+				body.u8(policy
+					.get_allowed()
+					.bits());
+				body.u8((policy.get_normalise() as u8)
+					| (policy.get_strip_invisible() as u8) << 1
+					| (policy.get_reject_confusables() as u8) << 2);
+				body.u8(policy.get_measure() as u8);
+				body.u8(policy.get_budget());
+				body.str(&policy
+					.get_forbidden()
+					.iter()
+					.collect::<String>());
+			},
+			Self::Join(nickname, profile, secret) => {
+				body.nickname(nickname);
+				body.profile(profile);
+				body.str(secret);
+			},
+			Self::Welcome(identifier, nickname, limit) => {
+				body.identifier(identifier);
+				body.nickname(nickname);
+				body.u32(*limit);
+			},
+			Self::Message(message) => {
+				body.u64(message.get_sequence());
+				message.send(&mut body.0)?
+			},
+			Self::Backfill(message) | Self::Hit(message) => {
+				body.u64(message.get_sequence());
+				body.u8(message.get_imported() as u8);
+				message.send(&mut body.0)?
+			},
+			Self::Error(error) | Self::Refused(error) => body.error(error),
+			Self::Profile(identifier, profile) => {
+				body.identifier(identifier);
+				body.profile(profile);
+			},
+			Self::ProfileQuery(identifier) => body.identifier(identifier),
+			Self::Presence(presence) => body.presence(presence),
+			Self::Roster(roster) => {
+				body.u32(roster.len() as u32);
+				for presence in roster {
+					body.presence(presence);
+				}
+			},
+			Self::Typing(identifier, nickname) => {
+				body.identifier(identifier);
+				body.nickname(nickname);
+			},
+			Self::Ack(sequence) => body.u64(*sequence),
+			Self::Ping | Self::Pong | Self::HeadQuery => (),
+			Self::Closing(reason) => body.str(reason),
+			Self::Read(identifier, sequence) => {
+				body.identifier(identifier);
+				body.u64(*sequence);
+			},
+			Self::History(query) => {
+				body.u8(query.as_byte());
+				match query {
+					HistoryQuery::Last(amount) => body.u32(*amount),
+					HistoryQuery::After(sequence) => body.u64(*sequence),
+					HistoryQuery::Since(time) => body.u64(time.timestamp() as u64),
+				}
+			},
+			Self::Page(count, next) => {
+				body.u32(*count);
+				body.u64(*next);
+			},
+			Self::Retention(retention) => {
+				body.u64(retention
+					.get_age()
+					.as_secs());
+				body.u64(retention.get_count());
+				body.u64(retention.get_bytes());
+			},
+			Self::Search(query) => {
+				body.str(query.get_text());
+				body.str(query.get_nickname());
+				let (since, until) = (query.get_since(), query.get_until());
+				body.u8((query.get_case_sensitive() as u8) | (since.is_some() as u8) << 1 | (until.is_some() as u8) << 2);
+				for time in [since, until]
+					.into_iter()
+					.flatten()
+				{
+					body.u64(time.timestamp() as u64);
+				}
+			},
+			Self::Found(sent, total) => {
+				body.u32(*sent);
+				body.u32(*total);
+			},
+			Self::Register(secret) => body.str(secret),
+			Self::Registered(nickname) => body.nickname(nickname),
+			Self::Direct(nickname, message) => {
+				body.nickname(nickname);
+				message.send(&mut body.0)?
+			},
+			Self::Delivery(nickname, delivery) => {
+				body.nickname(nickname);
+				body.u8(delivery.as_byte());
+			},
+			Self::Head(sequence, hash) => {
+				body.u64(*sequence);
+				body.0
+					.extend_from_slice(hash);
+			},
+			Self::Chained(previous, message) => {
+				body.0
+					.extend_from_slice(previous);
+				body.u64(message.get_sequence());
+				message.send(&mut body.0)?
+			},
+			Self::Control(token, control) => {
+				body.str(token);
+				match control {
+					Control::Backup(path) => {
+						body.u8(0);
+						body.str(path);
+					},
+					Control::Export(path, transcript, since, until) => {
+						body.u8(1);
+						body.str(path);
+						body.u8(transcript.as_byte());
+						body.u8((since.is_some() as u8) | (until.is_some() as u8) << 1);
+						for time in [since, until]
+							.into_iter()
+							.flatten()
+						{
+							body.u64(time.timestamp() as u64);
+						}
+					},
+					Control::Import(path) => {
+						body.u8(2);
+						body.str(path);
+					},
+					Control::Moderate(moderate) => {
+						body.u8(3);
+						body.moderate(moderate);
+					},
+				}
+			},
+			Self::Done(report) => body.str(report),
+			Self::Moderate(moderate) => body.moderate(moderate),
+		}
+
+		let mut bytes = body.0;
+		let length = (bytes.len() - Self::HEAD_LENGTH) as u32;
+		bytes[0] = self.kind();
+		bytes[1 .. Self::HEAD_LENGTH].copy_from_slice(&length.to_be_bytes());
+		Ok(bytes)
+	}
+
+	/// Deserialise the body of a frame of the given kind.
+	pub fn from_body(kind: u8, body: impl AsRef<[u8]>) -> Result<Self, MessageError> {
+		let mut fields = Fields(body.as_ref());
+		let frame = match kind {
+			kind::POLICY => {
+				let allowed = CharClasses::from_bits(fields.u8()?);
+				let flags = fields.u8()?;
+				let measure = fields.u8()?;
+				let Some(measure) = Measure::from_byte(measure) else {
+					Err(MessageError::UnknownKind(measure))?
+				};
+				let budget = fields.u8()?;
+				Self::Policy(
+					NicknamePolicy::new()
+						.allowed(allowed)
+						.measure(measure)
+						.budget(budget)
+						.normalise(flags & 0b001 != 0)
+						.strip_invisible(flags & 0b010 != 0)
+						.reject_confusables(flags & 0b100 != 0)
+						.forbidden(fields.str()?.chars()),
+				)
+			},
+			kind::JOIN => Self::Join(fields.nickname()?, fields.profile()?, fields.str()?.into()),
+			kind::WELCOME => Self::Welcome(fields.identifier()?, fields.nickname()?, fields.u32()?),
+			kind::MESSAGE => {
+				let sequence = fields.u64()?;
+				Self::Message(Message::from_bytes(fields.rest())?.sequence(sequence))
+			},
+			kind::BACKFILL => {
+				let sequence = fields.u64()?;
+				let imported = fields.u8()? != 0;
+				Self::Backfill(
+					Message::from_bytes(fields.rest())?
+						.sequence(sequence)
+						.imported(imported),
+				)
+			},
+			kind::HIT => {
+				let sequence = fields.u64()?;
+				let imported = fields.u8()? != 0;
+				Self::Hit(
+					Message::from_bytes(fields.rest())?
+						.sequence(sequence)
+						.imported(imported),
+				)
+			},
+			kind::ERROR => Self::Error(fields.error()?),
+			kind::REFUSED => Self::Refused(fields.error()?),
+			kind::PROFILE => Self::Profile(fields.identifier()?, fields.profile()?),
+			kind::PROFILE_QUERY => Self::ProfileQuery(fields.identifier()?),
+			kind::PRESENCE => Self::Presence(fields.presence()?),
+			kind::ROSTER => {
+				let count = fields.u32()? as usize;
+				// NOTE: every presence takes up more than a byte; a count, that cannot fit, is not allocated for.
+				if count > fields.0.len() {
+					Err(MessageError::Truncated)?
+				}
+				Self::Roster((0 .. count)
+					.map(|_| fields.presence())
+					.collect::<Result<_, _>>()?)
+			},
+			kind::TYPING => Self::Typing(fields.identifier()?, fields.nickname()?),
+			kind::ACK => Self::Ack(fields.u64()?),
+			kind::READ => Self::Read(fields.identifier()?, fields.u64()?),
+			kind::PING => Self::Ping,
+			kind::PONG => Self::Pong,
+			kind::CLOSING => Self::Closing(fields.str()?.into()),
+			kind::HISTORY => Self::History(match fields.u8()? {
+				0 => HistoryQuery::Last(fields.u32()?),
+				1 => HistoryQuery::After(fields.u64()?),
+				2 => HistoryQuery::Since(match DateTime::from_timestamp(fields.u64()? as i64, 0) {
+					Some(time) => time,
+					None => Err(MessageError::InvalidTimestamp)?,
+				}),
+				other => Err(MessageError::UnknownKind(other))?,
+			}),
+			kind::PAGE => Self::Page(fields.u32()?, fields.u64()?),
+			kind::SEARCH => {
+				let text = fields.str()?;
+				let nickname = fields.str()?;
+				let flags = fields.u8()?;
+				let mut time = |present: bool| -> Result<_, MessageError> {
+					if !present {
+						return Ok(None)
+					}
+					match DateTime::from_timestamp(fields.u64()? as i64, 0) {
+						Some(time) => Ok(Some(time)),
+						None => Err(MessageError::InvalidTimestamp),
+					}
+				};
+				let since = time(flags & 0b010 != 0)?;
+				let until = time(flags & 0b100 != 0)?;
+				Self::Search(
+					SearchQuery::new()
+						.text(text)
+						.nickname(nickname)
+						.case_sensitive(flags & 0b001 != 0)
+						.since(since)
+						.until(until),
+				)
+			},
+			kind::FOUND => Self::Found(fields.u32()?, fields.u32()?),
+			kind::REGISTER => Self::Register(fields.str()?.into()),
+			kind::REGISTERED => Self::Registered(fields.nickname()?),
+			kind::DIRECT => {
+				let nickname = fields.nickname()?;
+				Self::Direct(nickname, Message::from_bytes(fields.rest())?)
+			},
+			kind::DELIVERY => {
+				let nickname = fields.nickname()?;
+				let delivery = fields.u8()?;
+				let Some(delivery) = Delivery::from_byte(delivery) else { Err(MessageError::UnknownKind(delivery))? };
+				Self::Delivery(nickname, delivery)
+			},
+			kind::HEAD_QUERY => Self::HeadQuery,
+			kind::HEAD => Self::Head(fields.u64()?, fields.hash()?),
+			kind::CHAINED | kind::IMPORTED => {
+				let previous = fields.hash()?;
+				let sequence = fields.u64()?;
+				Self::Chained(
+					previous,
+					Message::from_bytes(fields.rest())?
+						.sequence(sequence)
+						.imported(kind == kind::IMPORTED),
+				)
+			},
+			kind::CONTROL => {
+				let token = fields.str()?.into();
+				Self::Control(token, match fields.u8()? {
+					0 => Control::Backup(fields.str()?.into()),
+					1 => {
+						let path = fields.str()?.into();
+						let transcript = fields.u8()?;
+						let Some(transcript) = Transcript::from_byte(transcript) else {
+							Err(MessageError::UnknownKind(transcript))?
+						};
+						let flags = fields.u8()?;
+						let mut time = |present: bool| -> Result<_, MessageError> {
+							if !present {
+								return Ok(None)
+							}
+							match DateTime::from_timestamp(fields.u64()? as i64, 0) {
+								Some(time) => Ok(Some(time)),
+								None => Err(MessageError::InvalidTimestamp),
+							}
+						};
+						let since = time(flags & 0b01 != 0)?;
+						let until = time(flags & 0b10 != 0)?;
+						Control::Export(path, transcript, since, until)
+					},
+					2 => Control::Import(fields.str()?.into()),
+					3 => Control::Moderate(fields.moderate()?),
+					other => Err(MessageError::UnknownKind(other))?,
+				})
+			},
+			kind::DONE => Self::Done(fields.str()?.into()),
+			kind::MODERATE => Self::Moderate(fields.moderate()?),
+			kind::RETENTION => Self::Retention(
+				Retention::new()
+					.age(Duration::from_secs(fields.u64()?))
+					.count(fields.u64()?)
+					.bytes(fields.u64()?),
+			),
+			other => Err(MessageError::UnknownKind(other))?,
+		};
+		Ok(frame)
+	}
+}
+
+/// Tell apart the ways, in which a connection can end.
+fn classify(error: io::Error, clean: bool) -> MessageError {
+	match error.kind() {
+		ErrorKind::UnexpectedEof if clean => MessageError::ConnectionClosed,
+		ErrorKind::UnexpectedEof | ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::BrokenPipe => {
+			MessageError::ConnectionInterrupted
+		},
+		// NOTE: a read-timeout shows up as either, depending on the platform.
+		ErrorKind::WouldBlock | ErrorKind::TimedOut => MessageError::Timeout,
+		_ => MessageError::IO(error),
+	}
+}
+
+impl Body {
+	#[inline(always)]
+	fn u8(&mut self, byte: u8) { self.0.push(byte) }
+	#[inline(always)]
+	fn u32(&mut self, int: u32) {
+		self.0
+			.extend_from_slice(&int.to_be_bytes())
+	}
+	#[inline(always)]
+	fn u64(&mut self, int: u64) {
+		self.0
+			.extend_from_slice(&int.to_be_bytes())
+	}
+
+	/// Texts are prefixed by their byte-length as an `u32`; the limit of a
+	/// frame bounds them well before.
+	fn str(&mut self, text: &str) {
+		self.u32(text.len() as u32);
+		self.0
+			.extend_from_slice(text.as_bytes());
+	}
+
+	/// Nicknames are prefixed by their byte-length as an `u8`.
+	fn nickname(&mut self, nickname: &Nickname) {
+		self.u8(nickname.len() as u8);
+		self.0
+			.extend_from_slice(nickname.as_bytes());
+	}
+
+	#[inline(always)]
+	fn identifier(&mut self, identifier: &Identifier) { self.u32(identifier.0) }
+
+	fn profile(&mut self, profile: &Profile) {
+		self.str(profile.get_display_name());
+		self.str(profile.get_status());
+		self.str(profile.get_pronouns());
+		self.str(profile.get_timezone());
+		self.str(profile.get_bio());
+	}
+
+	fn presence(&mut self, presence: &Presence) {
+		self.identifier(presence.get_identifier());
+		self.nickname(presence.get_nickname());
+		self.u8(presence.get_state() as u8);
+		self.str(presence.get_status());
+	}
+
+	fn error(&mut self, error: &ServerError) {
+		match error {
+			ServerError::Nickname(reason) => {
+				self.u8(0);
+				self.str(reason);
+			},
+			ServerError::Unexpected => self.u8(1),
+			ServerError::Profile(reason) => {
+				self.u8(2);
+				self.str(reason);
+			},
+			ServerError::Unknown(identifier) => {
+				self.u8(3);
+				self.identifier(identifier);
+			},
+			ServerError::TooSlow => self.u8(4),
+			ServerError::SlowDown(milliseconds) => {
+				self.u8(5);
+				self.u32(*milliseconds);
+			},
+			ServerError::Muted(seconds) => {
+				self.u8(6);
+				self.u32(*seconds);
+			},
+			ServerError::Flooding => self.u8(7),
+			ServerError::TooLong(limit) => {
+				self.u8(8);
+				self.u32(*limit);
+			},
+			ServerError::Crowded(limit) => {
+				self.u8(9);
+				self.u32(*limit);
+			},
+			ServerError::Unsaved => self.u8(10),
+			ServerError::History => self.u8(11),
+			ServerError::Search => self.u8(12),
+			ServerError::Unregistered(nickname) => {
+				self.u8(13);
+				self.str(nickname);
+			},
+			ServerError::Inbox(nickname) => {
+				self.u8(14);
+				self.str(nickname);
+			},
+			ServerError::Secret => self.u8(15),
+			ServerError::Registry => self.u8(16),
+			ServerError::Token => self.u8(17),
+			ServerError::Backup(reason) => {
+				self.u8(18);
+				self.str(reason);
+			},
+			ServerError::Export(reason) => {
+				self.u8(19);
+				self.str(reason);
+			},
+			ServerError::Import(reason) => {
+				self.u8(20);
+				self.str(reason);
+			},
+			ServerError::Operator => self.u8(21),
+			ServerError::Moderation(reason) => {
+				self.u8(22);
+				self.str(reason);
+			},
+			ServerError::Kicked(reason) => {
+				self.u8(23);
+				self.str(reason);
+			},
+			ServerError::Banned(reason) => {
+				self.u8(24);
+				self.str(reason);
+			},
+			ServerError::Silenced(seconds, reason) => {
+				self.u8(25);
+				self.u32(*seconds);
+				self.str(reason);
+			},
+			ServerError::Denied => self.u8(26),
+		}
+	}
+
+	/// Targets are written as they are read; durations in seconds.
+	fn moderate(&mut self, moderate: &Moderate) {
+		match moderate {
+			Moderate::List => self.u8(0),
+			Moderate::Kick(target, reason) => {
+				self.u8(1);
+				self.str(&target.to_string());
+				self.str(reason);
+			},
+			Moderate::Mute(target, duration, reason) => {
+				self.u8(2);
+				self.str(&target.to_string());
+				self.u64(duration.as_secs());
+				self.str(reason);
+			},
+			Moderate::Unmute(target) => {
+				self.u8(3);
+				self.str(&target.to_string());
+			},
+			Moderate::Ban(target, reason) => {
+				self.u8(4);
+				self.str(&target.to_string());
+				self.str(reason);
+			},
+			Moderate::Unban(target) => {
+				self.u8(5);
+				self.str(&target.to_string());
+			},
+			Moderate::Bans => self.u8(6),
+			Moderate::Grant(nickname) => {
+				self.u8(7);
+				self.nickname(nickname);
+			},
+			Moderate::Revoke(nickname) => {
+				self.u8(8);
+				self.nickname(nickname);
+			},
+		}
+	}
+}
+
+impl<'a> Fields<'a> {
+	fn take(&mut self, amount: usize) -> Result<&'a [u8], MessageError> {
+		if self.0.len() < amount {
+			Err(MessageError::Truncated)?
+		}
+		let (taken, rest) = self.0.split_at(amount);
+		self.0 = rest;
+		Ok(taken)
+	}
+
+	#[inline(always)]
+	fn rest(&mut self) -> &'a [u8] { std::mem::take(&mut self.0) }
+
+	#[inline(always)]
+	fn u8(&mut self) -> Result<u8, MessageError> { Ok(self.take(1)?[0]) }
+	#[inline(always)]
+	fn u32(&mut self) -> Result<u32, MessageError> {
+		Ok(<u32>::from_be_bytes(unsafe {
+			self.take(4)?
+				.try_into()
+				.unwrap_unchecked()
+		}))
+	}
+	#[inline(always)]
+	fn u64(&mut self) -> Result<u64, MessageError> {
+		Ok(<u64>::from_be_bytes(unsafe {
+			self.take(8)?
+				.try_into()
+				.unwrap_unchecked()
+		}))
+	}
+
+	#[inline(always)]
+	fn hash(&mut self) -> Result<[u8; 32], MessageError> {
+		Ok(unsafe {
+			self.take(32)?
+				.try_into()
+				.unwrap_unchecked()
+		})
+	}
+
+	fn str(&mut self) -> Result<&'a str, MessageError> {
+		let length = self.u32()? as usize;
+		Ok(from_utf8(self.take(length)?)?)
+	}
+
+	fn nickname(&mut self) -> Result<Nickname, MessageError> {
+		let length = self.u8()? as usize;
+		let text = from_utf8(self.take(length)?)?;
+		Nickname::from_str(text).map_err(|error| MessageError::User(UserError::Nickname(error)))
+	}
+
+	#[inline(always)]
+	fn identifier(&mut self) -> Result<Identifier, MessageError> { Ok(Identifier(self.u32()?)) }
+
+	fn profile(&mut self) -> Result<Profile, MessageError> {
+		Ok(Profile::new()
+			.display_name(self.str()?)
+			.status(self.str()?)
+			.pronouns(self.str()?)
+			.timezone(self.str()?)
+			.bio(self.str()?))
+	}
+
+	fn presence(&mut self) -> Result<Presence, MessageError> {
+		let identifier = self.identifier()?;
+		let nickname = self.nickname()?;
+		let state = self.u8()?;
+		let Some(state) = State::from_byte(state) else {
+			Err(MessageError::UnknownKind(state))?
+		};
+		Ok(Presence::new(identifier, nickname, state).status(self.str()?))
+	}
+
+	fn target(&mut self) -> Result<Target, MessageError> {
+		self.str()?
+			.parse()
+			.map_err(|error| MessageError::User(UserError::Nickname(error)))
+	}
+
+	fn error(&mut self) -> Result<ServerError, MessageError> {
+		Ok(match self.u8()? {
+			0 => ServerError::Nickname(self.str()?.into()),
+			1 => ServerError::Unexpected,
+			2 => ServerError::Profile(self.str()?.into()),
+			3 => ServerError::Unknown(self.identifier()?),
+			4 => ServerError::TooSlow,
+			5 => ServerError::SlowDown(self.u32()?),
+			6 => ServerError::Muted(self.u32()?),
+			7 => ServerError::Flooding,
+			8 => ServerError::TooLong(self.u32()?),
+			9 => ServerError::Crowded(self.u32()?),
+			10 => ServerError::Unsaved,
+			11 => ServerError::History,
+			12 => ServerError::Search,
+			13 => ServerError::Unregistered(self.str()?.into()),
+			14 => ServerError::Inbox(self.str()?.into()),
+			15 => ServerError::Secret,
+			16 => ServerError::Registry,
+			17 => ServerError::Token,
+			18 => ServerError::Backup(self.str()?.into()),
+			19 => ServerError::Export(self.str()?.into()),
+			20 => ServerError::Import(self.str()?.into()),
+			21 => ServerError::Operator,
+			22 => ServerError::Moderation(self.str()?.into()),
+			23 => ServerError::Kicked(self.str()?.into()),
+			24 => ServerError::Banned(self.str()?.into()),
+			25 => ServerError::Silenced(self.u32()?, self.str()?.into()),
+			26 => ServerError::Denied,
+			other => Err(MessageError::UnknownKind(other))?,
+		})
+	}
+
+	fn moderate(&mut self) -> Result<Moderate, MessageError> {
+		Ok(match self.u8()? {
+			0 => Moderate::List,
+			1 => Moderate::Kick(self.target()?, self.str()?.into()),
+			2 => Moderate::Mute(self.target()?, Duration::from_secs(self.u64()?), self.str()?.into()),
+			3 => Moderate::Unmute(self.target()?),
+			4 => Moderate::Ban(self.target()?, self.str()?.into()),
+			5 => Moderate::Unban(self.target()?),
+			6 => Moderate::Bans,
+			7 => Moderate::Grant(self.nickname()?),
+			8 => Moderate::Revoke(self.nickname()?),
+			other => Err(MessageError::UnknownKind(other))?,
+		})
+	}
+}
@@ -0,0 +1,31 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use chrono::{DateTime, Utc};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Which part of the server's history a client asks for.
+///
+/// The answer comes in pages; whatever does not fit into one is left to be
+/// asked for again, [`After`] the last sequence sent.
+///
+/// [`After`]: Self::After
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum HistoryQuery {
+	/// The given amount of the latest messages.
+	Last(u32),
+	/// Every message after the given sequence.
+	After(u64),
+	/// Every message written at, or after, the given time.
+	Since(DateTime<Utc>),
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl HistoryQuery {
+	#[inline(always)]
+	pub const fn as_byte(&self) -> u8 {
+		match self {
+			Self::Last(_) => 0,
+			Self::After(_) => 1,
+			Self::Since(_) => 2,
+		}
+	}
+}
@@ -1,15 +1,41 @@
 //! Functionality relating to server-client communications.
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-use std::{fmt::Debug, slice::from_raw_parts};
-
+use std::{
+	fmt::{self, Debug, Display, Formatter},
+	num::ParseIntError,
+	str::FromStr,
+};
+
+pub use control::Control;
+pub use delivery::Delivery;
+pub use frame::Frame;
+pub use history::HistoryQuery;
 pub use message::Message;
+pub use moderation::{Moderate, Target};
 pub use nickname::Nickname;
 use parking_lot::Mutex;
+pub use policy::{CharClasses, Measure, NicknamePolicy};
+pub use presence::{Presence, State};
+pub use profile::Profile;
+pub use retention::Retention;
+pub use search::SearchQuery;
+pub use transcript::Transcript;
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+mod control;
+mod delivery;
 pub mod errors;
+mod frame;
+mod history;
 mod message;
+mod moderation;
 mod nickname;
+mod policy;
+mod presence;
+mod profile;
+mod retention;
+mod search;
+mod transcript;
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 static CURRENT_IDENTIFIER: Mutex<u32> = Mutex::new(1);
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -128,22 +154,23 @@ impl Identifier {
 	#[inline(always)]
 	pub const fn is_unset(&self) -> bool { self.0 == 0 }
 
-	#[inline(always)]
-	pub const fn as_bytes(&self) -> &[u8] {
-		let origin = (&raw const self.0).cast::<u8>();
+	/// Make sure, that this identifier is never handed out again, nor any
+	/// before it; as for the authors of a history, that was read back in.
+	pub fn reserve(self) {
+		let mut identifier = CURRENT_IDENTIFIER.lock();
+		*identifier = (*identifier).max(self.0.saturating_add(1));
+	}
+
+	/// The big-endian bytes of the identifier, without the zero-bytes that
+	/// its header-component accounts for.
+	pub fn to_bytes(self) -> Box<[u8]> {
+		let be = self.0.to_be_bytes();
 
 		let lead =
-			self.0.leading_zeros();
+			self.0.leading_zeros() as usize;
 		let trail =
-			self.0.trailing_zeros();
-		let (offset, zeroes) = if lead >= trail {
-			let lead = lead as usize;
-			(unsafe { origin.add(lead) }, lead)
-		} else {
-			(origin, trail as usize)
-		};
-
-		unsafe { from_raw_parts(offset, size_of::<u32>() - zeroes) }
+			self.0.trailing_zeros() as usize;
+		if lead >= trail { be[lead / 8 ..].into() } else { be[.. 4 - trail / 8].into() }
 	}
 }
 
@@ -195,6 +222,32 @@ impl FromHeader for Identifier {
 	}
 }
 
+impl Display for Identifier {
+	#[inline(always)]
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { Display::fmt(&self.0, f) }
+}
+
+impl FromStr for Identifier {
+	type Err = ParseIntError;
+
+	#[inline(always)]
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		s.trim_start_matches('#')
+			.parse()
+			.map(Self)
+	}
+}
+
+impl From<u32> for Identifier {
+	#[inline(always)]
+	fn from(value: u32) -> Self { Self(value) }
+}
+
+impl From<Identifier> for u32 {
+	#[inline(always)]
+	fn from(value: Identifier) -> Self { value.0 }
+}
+
 impl Default for Identifier {
 	fn default() -> Self {
 		let mut identifier = CURRENT_IDENTIFIER.lock();
@@ -6,13 +6,19 @@ use std::{
 
 use chrono::{DateTime, Local, Utc};
 
-use crate::{Header, HeaderComponent, Identifier, Nickname, errors};
+use crate::{Frame, Header, HeaderComponent, Identifier, Nickname, errors};
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+#[derive(Clone)]
 #[derive(Debug)]
 pub struct Message {
 	// [202411162045+0100] NOTE(by: @OST-Gh): added in conversion to a byte stream.
 	// [202411190014+0100] NOTE(by: @OST-Gh): shouldn't be read, just for serialisation.
 	written_on: DateTime<Utc>,
+	/// Assigned by the server, zero until then; not part of the byte stream.
+	sequence: u64,
+	/// Set by the server, for what was taken from a transcript; not part of
+	/// the byte stream either.
+	imported: bool,
 
 	identifier: Identifier,
 	nickname: Nickname,
@@ -22,13 +28,15 @@ pub struct Message {
 }
 
 impl Message {
-	pub const MAX_LENGTH: usize = <usize>::MAX;
+	pub const MAX_LENGTH: usize = Frame::MAX_LENGTH as usize;
 	pub const MIN_LENGTH: usize =
 		size_of::<Header>() + size_of::<i64>() + Identifier::MIN_LENGTH + Nickname::MIN_LENGTH + size_of::<u8>();
 
 	pub fn empty() -> Self {
 		Self {
 			written_on: DateTime::UNIX_EPOCH,
+			sequence: 0,
+			imported: false,
 			identifier: Identifier::empty(),
 			nickname: Nickname::default(),
 			content: String::with_capacity(8),
@@ -46,6 +54,8 @@ impl Message {
 		}
 		Self {
 			written_on: DateTime::UNIX_EPOCH,
+			sequence: 0,
+			imported: false,
 			identifier: Identifier::empty(),
 			nickname: Nickname::default(),
 			content,
@@ -71,6 +81,8 @@ impl Message {
 	pub fn with_identifier(identifier: Identifier) -> Self {
 		Self {
 			written_on: DateTime::UNIX_EPOCH,
+			sequence: 0,
+			imported: false,
 			identifier,
 			nickname: Nickname::default(),
 			content: String::with_capacity(8),
@@ -90,6 +102,8 @@ impl Message {
 	pub fn with_nickname(nickname: Nickname) -> Self {
 		Self {
 			written_on: DateTime::UNIX_EPOCH,
+			sequence: 0,
+			imported: false,
 			identifier: Identifier::empty(),
 			nickname,
 			content: String::with_capacity(8),
@@ -107,28 +121,60 @@ impl Message {
 		self
 	}
 
+	/// The position of the message in the server's log, starting at one.
+	#[inline(always)]
+	pub const fn get_sequence(&self) -> u64 { self.sequence }
+	#[inline(always)]
+	pub fn set_sequence(&mut self, sequence: u64) { self.sequence = sequence; }
+	#[inline(always)]
+	pub fn sequence(mut self, sequence: u64) -> Self {
+		self.set_sequence(sequence);
+		self
+	}
+
+	/// Whether the message was taken into the history from a transcript,
+	/// rather than sent to the server.
+	#[inline(always)]
+	pub const fn get_imported(&self) -> bool { self.imported }
+	#[inline(always)]
+	pub fn set_imported(&mut self, imported: bool) { self.imported = imported; }
+	#[inline(always)]
+	pub fn imported(mut self, imported: bool) -> Self {
+		self.set_imported(imported);
+		self
+	}
+
 	#[inline(always)]
 	pub const fn get_utc(&self) -> DateTime<Utc> { self.written_on }
 	#[inline(always)]
+	pub fn set_utc(&mut self, written_on: DateTime<Utc>) { self.written_on = written_on; }
+	#[inline(always)]
+	pub fn utc(mut self, written_on: DateTime<Utc>) -> Self {
+		self.set_utc(written_on);
+		self
+	}
+	#[inline(always)]
 	pub fn get_local(&self) -> DateTime<Local> {
 		self.get_utc()
 			.with_timezone(&Local)
 	}
 
 	pub fn send(&self, to: &mut impl Write) -> Result<(), errors::MessageError> {
-		let send_timestamp = Utc::now().timestamp();
+		// NOTE: a message, that has been written on already, keeps its time; as when it is replayed.
+		let send_timestamp = if self.written_on == DateTime::UNIX_EPOCH { Utc::now() } else { self.written_on }.timestamp();
 		let identifier = self.get_identifier();
 		to.write_all(&[self.as_header_component()])?;
 		to.write_all(&send_timestamp.to_be_bytes())?;
 		if identifier.is_unset() {
 			Err(errors::MessageError::NoIdentifier)?
 		} else {
-			to.write_all(identifier.as_bytes())?;
+			to.write_all(&identifier.to_bytes())?;
 		}
-		to.write_all(
-			self.get_nickname()
-				.as_bytes(),
-		)?;
+		let nickname = self.get_nickname();
+		if let Some(count) = nickname.count_byte() {
+			to.write_all(&[count])?;
+		}
+		to.write_all(nickname.as_bytes())?;
 		to.write_all(
 			self.get_content()
 				.as_bytes(),
@@ -143,9 +189,16 @@ impl Message {
 		Self::recv_buf(from, &mut buf)
 	}
 
+	/// Read up to and including the terminating `\0`, but never more than
+	/// [`MAX_LENGTH`] bytes.
+	///
+	/// [`MAX_LENGTH`]: Self::MAX_LENGTH
 	pub fn recv_buf(from: &mut impl Read, buf: &mut Vec<u8>) -> Result<Self, errors::MessageError> {
-		let mut r = BufReader::new(from);
-		r.read_until(b'\0', buf)?;
+		let mut r = BufReader::new(from).take(Self::MAX_LENGTH as u64);
+		let length = r.read_until(b'\0', buf)?;
+		if length == Self::MAX_LENGTH && buf.last() != Some(&b'\0') {
+			Err(errors::MessageError::TooLong(length, Self::MAX_LENGTH))?
+		}
 		Self::from_bytes(buf)
 	}
 
@@ -212,15 +265,34 @@ impl Message {
 		}
 		let identifier = Identifier(<u32>::from_be_bytes(identifier_buffer));
 
+		let glyph_count = match header.get_bits::<Nickname>(None) {
+			// NOTE: a nickname of exactly 32 codepoints is sent without a count, as it always was.
+			Nickname::ESCAPE => match bytes
+				.as_slice()
+				.first()
+				.and_then(|byte| Nickname::counted(*byte))
+			{
+				Some(count) if count > Nickname::ESCAPE as usize + 1 => {
+					bytes.next();
+					count
+				},
+				Some(count) => Err(errors::UserError::LengthMismatch(count, Nickname::ESCAPE as usize + 1))?,
+				None => Nickname::ESCAPE as usize + 1,
+			},
+			count => count as usize + 1,
+		};
+
 		let mut rest = from_utf8(bytes.as_slice())?.chars();
 
 		let mut nickname = Nickname::default();
 		nickname.extend(rest
 			.by_ref()
-			.take(header.get_bits::<Nickname>(None) as usize + 1));
+			.take(glyph_count));
 
 		Ok(Self {
 			written_on,
+			sequence: 0,
+			imported: false,
 			identifier,
 			nickname,
 			content: rest.collect(),
@@ -258,3 +330,56 @@ impl HeaderComponent for Message {
 			.as_header_component()
 	}
 }
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sent(glyph_count: usize) -> (Message, Vec<u8>) {
+		// NOTE: a time, none of whose bytes is that of the nickname's first.
+		let message = Message::empty()
+			.utc(DateTime::from_timestamp(1_700_000_000, 0).unwrap())
+			.identifier(Identifier::from(7))
+			.nickname(
+				"é".repeat(glyph_count)
+					.parse()
+					.expect("the nickname is valid"),
+			)
+			.content("hello");
+		let mut bytes = Vec::new();
+		message
+			.send(&mut bytes)
+			.expect("the message can be sent");
+		(message, bytes)
+	}
+
+	#[test]
+	fn counts_only_above_32() {
+		let lengths = [31, 32, 33, 63].map(|glyph_count| sent(glyph_count).1.len());
+		// NOTE: every codepoint takes two bytes.
+		assert_eq!(lengths[1] - lengths[0], 2);
+		assert_eq!(lengths[2] - lengths[1], 2 + 1);
+		assert_eq!(lengths[3] - lengths[2], 2 * 30);
+	}
+
+	#[test]
+	fn round_trips_every_count() {
+		for glyph_count in [1, 31, 32, 33, 40, Nickname::MAX_CHAR_COUNT] {
+			let (message, bytes) = sent(glyph_count);
+			let received = Message::from_bytes(&bytes).expect("the message can be read back");
+			assert_eq!(received.get_nickname(), message.get_nickname(), "with {glyph_count} codepoints");
+			assert_eq!(received.get_content(), message.get_content(), "with {glyph_count} codepoints");
+		}
+	}
+
+	#[test]
+	fn refuses_a_needless_count() {
+		let (_, mut bytes) = sent(32);
+		let at = bytes
+			.iter()
+			.position(|byte| *byte == "é".as_bytes()[0])
+			.expect("the nickname is sent");
+		bytes.insert(at, Nickname::COUNTED | 32);
+		assert!(Message::from_bytes(&bytes).is_err());
+	}
+}
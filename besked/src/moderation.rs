@@ -0,0 +1,212 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	fmt::{self, Display, Formatter},
+	net::IpAddr,
+	str::{FromStr, SplitWhitespace},
+	time::Duration,
+};
+
+use crate::{
+	Identifier, Nickname,
+	errors::{ModerateFromStrError, NicknameError},
+};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Whom a moderation is aimed at.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum Target {
+	/// Whoever goes by the nickname; regardless of case.
+	Nickname(Nickname),
+	/// Whoever is connected under the identifier.
+	Identifier(Identifier),
+	/// Whoever connects from the address.
+	Address(IpAddr),
+}
+
+/// What an operator can have the server do about its users; either from a
+/// client, or through a [`Control`]-frame.
+///
+/// [`Control`]: crate::Frame::Control
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum Moderate {
+	/// List whoever is connected, with their identifier, nickname and
+	/// address.
+	List,
+	/// Cut off whoever is aimed at, for the given reason.
+	Kick(Target, Box<str>),
+	/// Refuse whatever those aimed at send, for the given time.
+	Mute(Target, Duration, Box<str>),
+	Unmute(Target),
+	/// Cut off whoever is aimed at, and refuse them from now on, for the given
+	/// reason.
+	Ban(Target, Box<str>),
+	Unban(Target),
+	/// List every ban.
+	Bans,
+	/// Make a registered nickname an operator; only through the control
+	/// channel.
+	Grant(Nickname),
+	/// Take the role of an operator away again; only through the control
+	/// channel.
+	Revoke(Nickname),
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// What is told, when a moderation cannot be read.
+const USAGE: &str = "who | kick <target> [reason] | mute <target> <duration> [reason] | unmute <target> | ban <target> [reason] | unban <target> | bans | op <nickname> | deop <nickname>";
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Moderate {
+	/// The word, that the moderation is read by.
+	pub const fn as_word(&self) -> &'static str {
+		match self {
+			Self::List => "who",
+			Self::Kick(..) => "kick",
+			Self::Mute(..) => "mute",
+			Self::Unmute(_) => "unmute",
+			Self::Ban(..) => "ban",
+			Self::Unban(_) => "unban",
+			Self::Bans => "bans",
+			Self::Grant(_) => "op",
+			Self::Revoke(_) => "deop",
+		}
+	}
+}
+
+impl Display for Target {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Nickname(nickname) => write!(f, "{nickname:#}"),
+			Self::Identifier(identifier) => write!(f, "#{identifier}"),
+			Self::Address(address) => Display::fmt(address, f),
+		}
+	}
+}
+
+impl Display for Moderate {
+	/// Write the moderation, as it is read; durations in seconds.
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(self.as_word())?;
+		let reason = match self {
+			Self::List | Self::Bans => return Ok(()),
+			Self::Kick(target, reason) | Self::Ban(target, reason) => {
+				write!(f, " {target}")?;
+				reason
+			},
+			Self::Mute(target, duration, reason) => {
+				write!(f, " {target} {}s", duration.as_secs())?;
+				reason
+			},
+			Self::Unmute(target) | Self::Unban(target) => return write!(f, " {target}"),
+			Self::Grant(nickname) | Self::Revoke(nickname) => return write!(f, " {nickname:#}"),
+		};
+		if reason.is_empty() { Ok(()) } else { write!(f, " {reason}") }
+	}
+}
+
+impl FromStr for Target {
+	type Err = NicknameError;
+
+	/// Read `#<identifier>`, an address, or a nickname; one, that could be read
+	/// as either of the former, is told apart by a leading `@`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if let Some(identifier) = s.strip_prefix('#')
+			&& let Ok(identifier) = identifier.parse()
+		{
+			Ok(Self::Identifier(identifier))
+		} else if let Ok(address) = s.parse() {
+			Ok(Self::Address(address))
+		} else {
+			Ok(Self::Nickname(s
+				.strip_prefix('@')
+				.unwrap_or(s)
+				.parse()?))
+		}
+	}
+}
+
+impl FromStr for Moderate {
+	type Err = ModerateFromStrError;
+
+	/// Read a moderation as words; whatever follows the target, and a
+	/// duration, like `30s`, `10m`, `2h` or `1d`, is the reason.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut words = s.split_whitespace();
+		let moderate = match words
+			.next()
+			.ok_or_else(usage)?
+		{
+			"who" | "list" => Self::List,
+			"kick" => Self::Kick(target(&mut words)?, reason(&mut words)),
+			"mute" => Self::Mute(
+				target(&mut words)?,
+				words
+					.next()
+					.and_then(duration)
+					.ok_or_else(usage)?,
+				reason(&mut words),
+			),
+			"unmute" => Self::Unmute(target(&mut words)?),
+			"ban" => Self::Ban(target(&mut words)?, reason(&mut words)),
+			"unban" => Self::Unban(target(&mut words)?),
+			"bans" => Self::Bans,
+			"op" => Self::Grant(nickname(&mut words)?),
+			"deop" => Self::Revoke(nickname(&mut words)?),
+			_ => Err(usage())?,
+		};
+		if words
+			.next()
+			.is_some()
+		{
+			Err(usage())?
+		}
+		Ok(moderate)
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+#[inline(always)]
+fn usage() -> ModerateFromStrError { ModerateFromStrError(USAGE.into()) }
+
+fn target(words: &mut SplitWhitespace<'_>) -> Result<Target, ModerateFromStrError> {
+	let word = words
+		.next()
+		.ok_or_else(usage)?;
+	word.parse()
+		.map_err(|error| ModerateFromStrError(format!("`{word}` is no target: {error}").into()))
+}
+
+fn nickname(words: &mut SplitWhitespace<'_>) -> Result<Nickname, ModerateFromStrError> {
+	let word = words
+		.next()
+		.ok_or_else(usage)?;
+	word.trim_start_matches('@')
+		.parse()
+		.map_err(|error| ModerateFromStrError(format!("`{word}` is no nickname: {error}").into()))
+}
+
+/// The rest of the words, as they were meant.
+fn reason(words: &mut SplitWhitespace<'_>) -> Box<str> {
+	words
+		.collect::<Vec<_>>()
+		.join(" ")
+		.into()
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Read an amount of seconds, minutes, hours or days, like `10m`.
+fn duration(text: &str) -> Option<Duration> {
+	let (amount, unit) = text.split_at_checked(text.len().checked_sub(1)?)?;
+	let size = match unit {
+		"s" => 1,
+		"m" => 60,
+		"h" => 3600,
+		"d" => 86400,
+		_ => None?,
+	};
+	let amount: u64 = amount
+		.parse()
+		.ok()
+		.filter(|amount| *amount != 0)?;
+	Some(Duration::from_secs(amount.checked_mul(size)?))
+}
@@ -2,17 +2,28 @@
 use std::{
 	alloc::{Layout, alloc, dealloc, realloc},
 	fmt::{self, Debug, Display, Formatter},
+	hash::{Hash, Hasher},
 	slice::from_raw_parts,
 	str::{FromStr, from_utf8, from_utf8_unchecked},
 };
 
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
 use crate::{FromHeader, Header, HeaderComponent, errors::NicknameError};
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 /// A buffer for UTF-8 encoded usernicknames.
 ///
-/// Each nickname cannot be [`MAX_GLYPH_COUNT`] codepoints long.
+/// Each nickname cannot be longer than [`MAX_CHAR_COUNT`] codepoints.
+/// How long a nickname appears to a user is better described by its
+/// [`grapheme_count`] or [`display_width`], which a [`NicknamePolicy`] can
+/// limit to [`MAX_GLYPH_COUNT`].
 ///
+/// [`MAX_CHAR_COUNT`]: Nickname::MAX_CHAR_COUNT
 /// [`MAX_GLYPH_COUNT`]: Nickname::MAX_GLYPH_COUNT
+/// [`grapheme_count`]: Nickname::grapheme_count
+/// [`display_width`]: Nickname::display_width
+/// [`NicknamePolicy`]: crate::NicknamePolicy
 pub struct Nickname {
 	buffer: *mut u8,
 	length: u8,
@@ -53,22 +64,70 @@ fn get_bearings(origin: *mut u8, offset: usize) -> (usize, usize, *mut u8) {
 }
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 impl Nickname {
-	pub const MAX_LENGTH: usize = 4 * Self::MAX_GLYPH_COUNT;
+	pub const MAX_LENGTH: usize = 4 * Self::MAX_CHAR_COUNT;
 	pub const MIN_LENGTH: usize = 1;
 
+	/// The default length-budget of a nickname.
 	pub const MAX_GLYPH_COUNT: usize = Self::POSSEBILITIES as usize;
+	/// The hard limit on codepoints; chosen so that [`MAX_LENGTH`] still fits
+	/// into an `u8`.
+	///
+	/// [`MAX_LENGTH`]: Self::MAX_LENGTH
+	pub const MAX_CHAR_COUNT: usize = 63;
+
+	/// The header-component of a nickname of at least 32 codepoints; for
+	/// more than that, a [`COUNTED`] byte with their amount follows it.
+	///
+	/// [`COUNTED`]: Self::COUNTED
+	pub const ESCAPE: u8 = Self::MASK;
+	/// What marks the byte, that holds the amount of codepoints after an
+	/// [`ESCAPE`]; being that of a UTF-8 continuation-byte, it is never
+	/// mistaken for the start of the nickname, as sent before it existed.
+	///
+	/// [`ESCAPE`]: Self::ESCAPE
+	pub const COUNTED: u8 = 0b1000_0000;
 
 	const DEFAULT_CAPACITY: u8 = 8;
 
 	#[inline(always)]
 	pub const fn capacity(&self) -> usize { self.allocated as usize }
 	#[inline(always)]
-	pub const fn available_bytes(&self) -> usize { self.len() - self.capacity() }
+	pub const fn available_bytes(&self) -> usize { self.capacity() - self.len() }
 	#[inline(always)]
 	pub const fn len(&self) -> usize { self.length as usize }
 	#[inline(always)]
 	pub const fn glyph_count(&self) -> usize { self.glyph_count as usize }
 
+	/// The byte, that follows an [`ESCAPE`]d header with the amount of
+	/// codepoints; [`None`], if the header holds the amount by itself.
+	///
+	/// [`ESCAPE`]: Self::ESCAPE
+	#[inline(always)]
+	pub fn count_byte(&self) -> Option<u8> {
+		(self.glyph_count() > Self::ESCAPE as usize + 1).then(|| Self::COUNTED | self.glyph_count() as u8)
+	}
+	/// The amount of codepoints a byte after an [`ESCAPE`]d header holds;
+	/// [`None`], if it isn't [`COUNTED`], but already part of the nickname.
+	///
+	/// [`ESCAPE`]: Self::ESCAPE
+	/// [`COUNTED`]: Self::COUNTED
+	#[inline(always)]
+	pub const fn counted(byte: u8) -> Option<usize> {
+		if byte & 0b1100_0000 == Self::COUNTED { Some((byte & !Self::COUNTED) as usize) } else { None }
+	}
+
+	/// The amount of extended grapheme-clusters, i.e. user-perceived
+	/// characters.
+	#[inline]
+	pub fn grapheme_count(&self) -> usize {
+		self.as_str()
+			.graphemes(true)
+			.count()
+	}
+	/// The amount of columns, that the nickname takes up in a terminal.
+	#[inline]
+	pub fn display_width(&self) -> usize { self.as_str().width() }
+
 	#[inline(always)]
 	pub const fn is_empty(&self) -> bool { self.len() == 0 }
 
@@ -77,7 +136,7 @@ impl Nickname {
 		if capacity > max_length {
 			None?
 		}
-		Some(unsafe { Self::with_capacity_unchecked(max_length.min(capacity << 2)) })
+		Some(unsafe { Self::with_capacity_unchecked(max_length.min(capacity.saturating_mul(4))) })
 	}
 
 	/// Allocate a buffer with no checks.
@@ -144,7 +203,7 @@ impl Nickname {
 	#[inline]
 	pub fn reserve(&mut self, additional: u8) {
 		let additional = additional as usize;
-		if self.len() < self.capacity() {
+		if self.len() + additional <= self.capacity() {
 			return;
 		}
 		self.realloc_if_not(false, Self::MAX_LENGTH.min((self.capacity() + additional) << 1));
@@ -152,7 +211,7 @@ impl Nickname {
 
 	#[inline(always)]
 	fn realloc_if_not(&mut self, cond: bool, new_capacity: usize) {
-		if new_capacity >= Self::MAX_LENGTH || cond {
+		if new_capacity > Self::MAX_LENGTH || new_capacity == 0 || new_capacity == self.capacity() || cond {
 			return;
 		}
 
@@ -163,6 +222,7 @@ impl Nickname {
 				new_capacity,
 			);
 		}
+		self.allocated = new_capacity as u8;
 	}
 
 	pub fn shrink_to(&mut self, new_capacity: u8) {
@@ -179,11 +239,11 @@ impl Nickname {
 		let len = glyph.len_utf8();
 		let new_len = len + self.len();
 
-		if new_len > Self::MAX_LENGTH || self.glyph_count() >= Self::MAX_GLYPH_COUNT {
+		if new_len > Self::MAX_LENGTH || self.glyph_count() >= Self::MAX_CHAR_COUNT {
 			return false;
 		}
 		if new_len > self.capacity() {
-			self.reserve(new_len as u8);
+			self.reserve(len as u8);
 		}
 
 		let mut utf8 = [0; 4];
@@ -225,11 +285,11 @@ impl Nickname {
 		let len = glyph.len_utf8();
 		let new_len = len + self.len();
 
-		if new_len > Self::MAX_LENGTH || self.glyph_count() >= Self::MAX_GLYPH_COUNT || index_hint >= self.len() {
+		if new_len > Self::MAX_LENGTH || self.glyph_count() >= Self::MAX_CHAR_COUNT || index_hint >= self.len() {
 			return false;
 		}
 		if new_len > self.capacity() {
-			self.reserve(new_len as u8);
+			self.reserve(len as u8);
 		}
 
 		let (_, index, overwrite) = get_bearings(self.as_mut_ptr(), index_hint);
@@ -331,7 +391,7 @@ impl Extend<char> for Nickname {
 	{
 		for c in iter
 			.into_iter()
-			.take(Self::MAX_GLYPH_COUNT - self.glyph_count())
+			.take(Self::MAX_CHAR_COUNT - self.glyph_count())
 		{
 			self.push(c);
 		}
@@ -346,7 +406,7 @@ impl FromIterator<char> for Nickname {
 		let iter = iter.into_iter();
 		let (min, max_opt) = iter.size_hint();
 		let mut instance =
-			unsafe { Self::with_capacity_unchecked((max_opt.unwrap_or(min)).min(Self::MAX_GLYPH_COUNT) as u8) };
+			unsafe { Self::with_capacity_unchecked((max_opt.unwrap_or(min)).min(Self::MAX_CHAR_COUNT) as u8) };
 		instance.extend(iter);
 		instance
 	}
@@ -360,7 +420,8 @@ impl HeaderComponent for Nickname {
 		if self.is_empty() {
 			panic!("Cannot serialise an empty `{}`", stringify!(Nickname));
 		}
-		self.glyph_count() as u8 - 1
+		// NOTE: counts above 32, which don't fit, are escaped and follow the identifier instead.
+		(self.glyph_count() as u8 - 1).min(Self::ESCAPE)
 	}
 }
 
@@ -368,14 +429,19 @@ impl FromHeader for Nickname {
 	type Error = NicknameError;
 
 	fn from_header(header: &Header, buf: impl AsRef<[u8]>) -> Result<Self, Self::Error> {
-		let take = header.get_bits::<Self>(None) as usize + 1;
+		let take = header.get_bits::<Self>(None);
 
 		let text = from_utf8(buf.as_ref())?;
 		let glyph_count = text
 			.chars()
 			.count();
 
-		if glyph_count != take {
+		let mismatch = if take == Self::ESCAPE {
+			glyph_count <= Self::ESCAPE as usize || glyph_count > Self::MAX_CHAR_COUNT
+		} else {
+			glyph_count != take as usize + 1
+		};
+		if mismatch {
 			Err(Self::Error::TooManyGlyphs(text.into(), glyph_count))?;
 		}
 
@@ -398,6 +464,8 @@ impl Debug for Nickname {
 		if f.alternate() {
 			f.debug_struct(stringify!(UserNickname))
 				.field("codepoint_amount", &self.glyph_count())
+				.field("grapheme_amount", &self.grapheme_count())
+				.field("display_width", &self.display_width())
 				.field("length", &self.len())
 				.field("capacity", &self.capacity())
 				.field("buffer_addr", &self.as_ptr())
@@ -422,7 +490,7 @@ impl FromStr for Nickname {
 		let glyph_count = s
 			.chars()
 			.count();
-		if glyph_count > Self::MAX_GLYPH_COUNT {
+		if glyph_count > Self::MAX_CHAR_COUNT {
 			Err(Self::Err::TooManyGlyphs(s.into(), glyph_count))?
 		}
 
@@ -458,3 +526,31 @@ impl Default for Nickname {
 	#[inline(always)]
 	fn default() -> Self { unsafe { Self::with_capacity(Self::DEFAULT_CAPACITY).unwrap_unchecked() } }
 }
+
+impl Clone for Nickname {
+	#[inline(always)]
+	fn clone(&self) -> Self {
+		if self.is_empty() {
+			return Self::default();
+		}
+		unsafe { Self::from_str_unchecked(self.as_str(), self.glyph_count) }
+	}
+}
+
+impl PartialEq for Nickname {
+	#[inline(always)]
+	fn eq(&self, other: &Self) -> bool { self.as_bytes() == other.as_bytes() }
+}
+
+impl Eq for Nickname {}
+
+impl Hash for Nickname {
+	#[inline(always)]
+	fn hash<H>(&self, state: &mut H)
+	where
+		H: Hasher,
+	{
+		self.as_bytes()
+			.hash(state)
+	}
+}
@@ -0,0 +1,464 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	fmt::{self, Debug, Display, Formatter},
+	ops::BitOr,
+	str::FromStr,
+};
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_security::confusable_detection::skeleton;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+	Nickname,
+	errors::{MeasureFromStrError, UsernameFromStrError},
+};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A set of character-classes, which a [`Nickname`] may be made up of.
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+#[derive(Eq, PartialEq)]
+#[derive(Hash)]
+pub struct CharClasses(u8);
+
+/// The unit, in which the length of a [`Nickname`] is measured.
+#[repr(u8)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Eq, PartialEq)]
+#[derive(Hash)]
+pub enum Measure {
+	/// Unicode scalar-values, as counted by [`str::chars`].
+	#[default]
+	Codepoints,
+	/// Extended grapheme-clusters, i.e. user-perceived characters.
+	Graphemes,
+	/// Columns in a terminal.
+	Columns,
+}
+
+/// The rules, that a [`Nickname`] has to adhere to, before it is accepted.
+///
+/// A server sends its policy to every client upon connecting, so that both
+/// ends enforce the very same rules.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub struct NicknamePolicy {
+	allowed: CharClasses,
+	forbidden: Box<[char]>,
+
+	measure: Measure,
+	budget: u8,
+
+	normalise: bool,
+	strip_invisible: bool,
+	reject_confusables: bool,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Bidirectional-formatting characters.
+const BIDI_CONTROLS: [char; 12] = [
+	'\u{061C}', '\u{200E}', '\u{200F}', '\u{202A}', '\u{202B}', '\u{202C}', '\u{202D}', '\u{202E}', '\u{2066}', '\u{2067}',
+	'\u{2068}', '\u{2069}',
+];
+/// Characters without any width, which never have a legitimate use in a name.
+const ZERO_WIDTH: [char; 4] = ['\u{200B}', '\u{2060}', '\u{FEFF}', '\u{180E}'];
+/// Zero-width (non-)joiners; needed by emoji-sequences and some scripts.
+const JOINERS: [char; 2] = ['\u{200C}', '\u{200D}'];
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+fn is_invisible(glyph: char) -> bool { BIDI_CONTROLS.contains(&glyph) || ZERO_WIDTH.contains(&glyph) }
+
+fn confusable_skeleton(text: &str) -> String { skeleton(text).collect() }
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl CharClasses {
+	pub const NONE: Self = Self(0b00000);
+	pub const LETTERS: Self = Self(0b00001);
+	pub const DIGITS: Self = Self(0b00010);
+	pub const PUNCTUATION: Self = Self(0b00100);
+	pub const SYMBOLS: Self = Self(0b01000);
+	pub const SPACES: Self = Self(0b10000);
+
+	pub const ALL: Self = Self(0b11111);
+
+	#[inline(always)]
+	pub const fn from_bits(bits: u8) -> Self { Self(bits & Self::ALL.0) }
+	#[inline(always)]
+	pub const fn bits(&self) -> u8 { self.0 }
+
+	#[inline(always)]
+	pub const fn union(self, other: Self) -> Self { Self(self.0 | other.0) }
+	#[inline(always)]
+	pub const fn contains(&self, other: Self) -> bool { self.0 & other.0 == other.0 }
+
+	/// Classify a single `char`.
+	///
+	/// Control-characters fall into no class at all.
+	pub fn of(glyph: char) -> Self {
+		if glyph.is_alphabetic() {
+			Self::LETTERS
+		} else if glyph.is_numeric() {
+			Self::DIGITS
+		} else if glyph.is_ascii_punctuation() {
+			Self::PUNCTUATION
+		} else if glyph == ' ' {
+			Self::SPACES
+		} else if glyph.is_control() || glyph.is_whitespace() {
+			Self::NONE
+		} else {
+			Self::SYMBOLS
+		}
+	}
+}
+
+impl BitOr for CharClasses {
+	type Output = Self;
+
+	#[inline(always)]
+	fn bitor(self, rhs: Self) -> Self::Output { self.union(rhs) }
+}
+
+impl Default for CharClasses {
+	#[inline(always)]
+	fn default() -> Self { Self::LETTERS | Self::DIGITS | Self::PUNCTUATION }
+}
+
+impl Debug for CharClasses {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		let mut set = f.debug_set();
+		for (class, name) in [
+			(Self::LETTERS, "letters"),
+			(Self::DIGITS, "digits"),
+			(Self::PUNCTUATION, "punctuation"),
+			(Self::SYMBOLS, "symbols"),
+			(Self::SPACES, "spaces"),
+		] {
+			if self.contains(class) {
+				set.entry(&name);
+			}
+		}
+		set.finish()
+	}
+}
+
+impl Measure {
+	/// Measure some text.
+	pub fn of(&self, text: impl AsRef<str>) -> usize {
+		let text = text.as_ref();
+		match self {
+			Self::Codepoints => text
+				.chars()
+				.count(),
+			Self::Graphemes => text
+				.graphemes(true)
+				.count(),
+			Self::Columns => text.width(),
+		}
+	}
+
+	#[inline(always)]
+	pub const fn from_byte(byte: u8) -> Option<Self> {
+		match byte {
+			0 => Some(Self::Codepoints),
+			1 => Some(Self::Graphemes),
+			2 => Some(Self::Columns),
+			_ => None,
+		}
+	}
+}
+
+impl FromStr for Measure {
+	type Err = MeasureFromStrError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s
+			.to_lowercase()
+			.as_str()
+		{
+			"codepoints" | "chars" => Ok(Self::Codepoints),
+			"graphemes" => Ok(Self::Graphemes),
+			"columns" | "width" => Ok(Self::Columns),
+			_ => Err(MeasureFromStrError(s.into())),
+		}
+	}
+}
+
+impl Display for Measure {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Codepoints => "codepoints",
+			Self::Graphemes => "graphemes",
+			Self::Columns => "columns",
+		})
+	}
+}
+
+impl NicknamePolicy {
+	#[inline(always)]
+	pub fn new() -> Self { Self::default() }
+
+	#[inline(always)]
+	pub const fn get_allowed(&self) -> CharClasses { self.allowed }
+	#[inline(always)]
+	pub fn set_allowed(&mut self, allowed: CharClasses) { self.allowed = allowed; }
+	#[inline(always)]
+	pub fn allowed(mut self, allowed: CharClasses) -> Self {
+		self.set_allowed(allowed);
+		self
+	}
+
+	#[inline(always)]
+	pub fn get_forbidden(&self) -> &[char] { &self.forbidden }
+	#[inline(always)]
+	pub fn set_forbidden(&mut self, forbidden: impl IntoIterator<Item = char>) {
+		self.forbidden = forbidden
+			.into_iter()
+			.collect();
+	}
+	#[inline(always)]
+	pub fn forbidden(mut self, forbidden: impl IntoIterator<Item = char>) -> Self {
+		self.set_forbidden(forbidden);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_measure(&self) -> Measure { self.measure }
+	#[inline(always)]
+	pub fn set_measure(&mut self, measure: Measure) { self.measure = measure; }
+	#[inline(always)]
+	pub fn measure(mut self, measure: Measure) -> Self {
+		self.set_measure(measure);
+		self
+	}
+
+	/// The maximum length of a nickname, as measured by [`get_measure`].
+	///
+	/// [`get_measure`]: Self::get_measure
+	#[inline(always)]
+	pub const fn get_budget(&self) -> u8 { self.budget }
+	#[inline(always)]
+	pub fn set_budget(&mut self, budget: u8) { self.budget = budget; }
+	#[inline(always)]
+	pub fn budget(mut self, budget: u8) -> Self {
+		self.set_budget(budget);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_normalise(&self) -> bool { self.normalise }
+	#[inline(always)]
+	pub fn set_normalise(&mut self, normalise: bool) { self.normalise = normalise; }
+	#[inline(always)]
+	pub fn normalise(mut self, normalise: bool) -> Self {
+		self.set_normalise(normalise);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_strip_invisible(&self) -> bool { self.strip_invisible }
+	#[inline(always)]
+	pub fn set_strip_invisible(&mut self, strip_invisible: bool) { self.strip_invisible = strip_invisible; }
+	#[inline(always)]
+	pub fn strip_invisible(mut self, strip_invisible: bool) -> Self {
+		self.set_strip_invisible(strip_invisible);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_reject_confusables(&self) -> bool { self.reject_confusables }
+	#[inline(always)]
+	pub fn set_reject_confusables(&mut self, reject_confusables: bool) { self.reject_confusables = reject_confusables; }
+	#[inline(always)]
+	pub fn reject_confusables(mut self, reject_confusables: bool) -> Self {
+		self.set_reject_confusables(reject_confusables);
+		self
+	}
+
+	/// Bring some raw user-input into its canonical form.
+	///
+	/// A leading `@` is dropped, as it only marks the text as a nickname.
+	pub fn canonicalise(&self, raw: impl AsRef<str>) -> String {
+		let raw = raw.as_ref();
+		let raw = raw
+			.trim()
+			.strip_prefix('@')
+			.unwrap_or(raw.trim());
+
+		let mut text: String = if self.normalise {
+			raw.nfc()
+				.collect()
+		} else {
+			raw.into()
+		};
+
+		if self.strip_invisible {
+			let glyphs: Vec<char> = text
+				.chars()
+				.filter(|glyph| !is_invisible(*glyph))
+				.collect();
+			// NOTE:
+			// 	Joiners are only kept in between two non-A.S.C.I.I. characters, because
+			// 	that is the only place where emoji-sequences and scripts need them.
+			text = glyphs
+				.iter()
+				.enumerate()
+				.filter(|(i, glyph)| {
+					!JOINERS.contains(glyph)
+						|| *i > 0 && glyphs
+							.get(i + 1)
+							.is_some_and(|next| !next.is_ascii() && !glyphs[i - 1].is_ascii())
+				})
+				.map(|(_, glyph)| glyph)
+				.collect();
+		}
+
+		String::from(text.trim())
+	}
+
+	/// Check already canonical text against the policy.
+	pub fn validate(&self, text: impl AsRef<str>) -> Result<(), UsernameFromStrError> {
+		let text = text.as_ref();
+		if text.is_empty() {
+			Err(UsernameFromStrError::Empty)?
+		}
+		for glyph in text.chars() {
+			if self.forbidden
+				.contains(&glyph)
+			{
+				Err(UsernameFromStrError::Forbidden(text.into(), glyph))?
+			}
+			if JOINERS.contains(&glyph) {
+				continue;
+			}
+			let class = CharClasses::of(glyph);
+			if class == CharClasses::NONE || !self.allowed.contains(class) {
+				Err(UsernameFromStrError::NoMatch(text.into(), glyph))?
+			}
+		}
+		let length = self.measure.of(text);
+		if length > self.budget as usize {
+			Err(UsernameFromStrError::TooLong(text.into(), length, self.measure, self.budget as usize))?
+		}
+		Ok(())
+	}
+
+	/// Canonicalise and validate raw user-input.
+	///
+	/// Regardless of the budget, a nickname can never exceed
+	/// [`Nickname::MAX_CHAR_COUNT`] codepoints.
+	pub fn apply(&self, raw: impl AsRef<str>) -> Result<Nickname, UsernameFromStrError> {
+		let text = self.canonicalise(raw);
+		self.validate(&text)?;
+		Ok(Nickname::from_str(&text)?)
+	}
+
+	/// Whether two nicknames could be mistaken for one another.
+	///
+	/// Always `false`, if the policy doesn't reject confusables.
+	pub fn is_confusable(&self, left: impl AsRef<str>, right: impl AsRef<str>) -> bool {
+		if !self.reject_confusables {
+			return false;
+		}
+		let (left, right) = (left.as_ref(), right.as_ref());
+		confusable_skeleton(left) == confusable_skeleton(right)
+			|| confusable_skeleton(&left.to_lowercase()) == confusable_skeleton(&right.to_lowercase())
+	}
+
+	/// [`apply`] the policy and make sure, that the result isn't confusable
+	/// with any of the already `taken` nicknames.
+	///
+	/// [`apply`]: Self::apply
+	pub fn admit<'a>(
+		&self,
+		raw: impl AsRef<str>,
+		taken: impl IntoIterator<Item = &'a Nickname>,
+	) -> Result<Nickname, UsernameFromStrError> {
+		let nickname = self.apply(raw)?;
+		for other in taken {
+			if self.is_confusable(&nickname, other) {
+				Err(UsernameFromStrError::Confusable(nickname.as_str().into(), other.as_str().into()))?
+			}
+		}
+		Ok(nickname)
+	}
+}
+
+impl Default for NicknamePolicy {
+	fn default() -> Self {
+		Self {
+			allowed: CharClasses::default(),
+			forbidden: Box::from(['{', '}', '(', ')', '[', ']', '@']),
+			measure: Measure::default(),
+			budget: Nickname::MAX_GLYPH_COUNT as u8,
+			normalise: true,
+			strip_invisible: true,
+			reject_confusables: true,
+		}
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn normalises_to_nfc() {
+		let policy = NicknamePolicy::new();
+		assert_eq!(policy.canonicalise("e\u{0301}ve"), "\u{00E9}ve");
+		assert_eq!(policy.canonicalise(" @\u{00E9}ve "), "\u{00E9}ve");
+		assert_eq!(
+			policy
+				.apply("e\u{0301}ve")
+				.expect("a normalised nickname is valid")
+				.as_str(),
+			"\u{00E9}ve"
+		);
+
+		let policy = policy.normalise(false);
+		assert_eq!(policy.canonicalise("e\u{0301}ve"), "e\u{0301}ve");
+	}
+
+	#[test]
+	fn strips_bidi_controls_and_zero_width() {
+		let policy = NicknamePolicy::new();
+		assert_eq!(policy.canonicalise("ad\u{202E}nim"), "adnim");
+		assert_eq!(policy.canonicalise("\u{2067}ad\u{2069}min\u{200E}"), "admin");
+		assert_eq!(policy.canonicalise("ad\u{200B}mi\u{FEFF}n\u{2060}"), "admin");
+		// NOTE: joiners between A.S.C.I.I. characters go, those in an emoji-sequence stay.
+		assert_eq!(policy.canonicalise("ad\u{200D}min"), "admin");
+		assert_eq!(policy.canonicalise("\u{200C}admin"), "admin");
+		assert_eq!(policy.canonicalise("\u{1F469}\u{200D}\u{1F4BB}"), "\u{1F469}\u{200D}\u{1F4BB}");
+
+		let policy = policy.strip_invisible(false);
+		assert_eq!(policy.canonicalise("ad\u{202E}nim"), "ad\u{202E}nim");
+		assert!(policy
+			.apply("ad\u{202E}nim")
+			.is_err());
+	}
+
+	#[test]
+	fn detects_confusables() {
+		let policy = NicknamePolicy::new();
+		// NOTE: with a cyrillic `а` in place of the latin `a`.
+		assert!(policy.is_confusable("paypal", "p\u{0430}ypal"));
+		assert!(policy.is_confusable("Alice", "alice"));
+		assert!(policy.is_confusable("rn", "m"));
+		assert!(!policy.is_confusable("alice", "bob"));
+
+		let taken = [Nickname::from_str("paypal").expect("the nickname is valid")];
+		assert!(matches!(
+			policy.admit("p\u{0430}ypal", &taken),
+			Err(UsernameFromStrError::Confusable(..))
+		));
+		assert!(policy
+			.admit("alice", &taken)
+			.is_ok());
+
+		let policy = policy.reject_confusables(false);
+		assert!(!policy.is_confusable("paypal", "p\u{0430}ypal"));
+		assert!(policy
+			.admit("p\u{0430}ypal", &taken)
+			.is_ok());
+	}
+}
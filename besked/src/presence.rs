@@ -0,0 +1,130 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	fmt::{self, Display, Formatter},
+	str::FromStr,
+};
+
+use crate::{Identifier, Nickname, errors::StateFromStrError};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Whether, and how, a user is available.
+#[repr(u8)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Eq, PartialEq, PartialOrd, Ord)]
+#[derive(Hash)]
+pub enum State {
+	#[default]
+	Online,
+	Away,
+	Busy,
+	Offline,
+}
+
+/// The availability of a single user, as announced by the server.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub struct Presence {
+	identifier: Identifier,
+	nickname: Nickname,
+
+	state: State,
+	/// Empty, if unset.
+	status: String,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl State {
+	#[inline(always)]
+	pub const fn from_byte(byte: u8) -> Option<Self> {
+		match byte {
+			0 => Some(Self::Online),
+			1 => Some(Self::Away),
+			2 => Some(Self::Busy),
+			3 => Some(Self::Offline),
+			_ => None,
+		}
+	}
+}
+
+impl Display for State {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Online => "online",
+			Self::Away => "away",
+			Self::Busy => "busy",
+			Self::Offline => "offline",
+		})
+	}
+}
+
+impl FromStr for State {
+	type Err = StateFromStrError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s
+			.to_lowercase()
+			.as_str()
+		{
+			"online" | "back" => Ok(Self::Online),
+			"away" => Ok(Self::Away),
+			"busy" => Ok(Self::Busy),
+			"offline" => Ok(Self::Offline),
+			_ => Err(StateFromStrError(s.into())),
+		}
+	}
+}
+
+impl Presence {
+	#[inline(always)]
+	pub fn new(identifier: Identifier, nickname: Nickname, state: State) -> Self {
+		Self {
+			identifier,
+			nickname,
+			state,
+			status: String::new(),
+		}
+	}
+
+	#[inline(always)]
+	pub const fn get_identifier(&self) -> &Identifier { &self.identifier }
+	#[inline(always)]
+	pub const fn get_nickname(&self) -> &Nickname { &self.nickname }
+
+	#[inline(always)]
+	pub const fn get_state(&self) -> State { self.state }
+	#[inline(always)]
+	pub fn set_state(&mut self, state: State) { self.state = state; }
+	#[inline(always)]
+	pub fn state(mut self, state: State) -> Self {
+		self.set_state(state);
+		self
+	}
+
+	#[inline(always)]
+	pub fn get_status(&self) -> &str { &self.status }
+	#[inline(always)]
+	pub fn set_status(&mut self, status: impl AsRef<str>) {
+		self.status
+			.clear();
+		self.status
+			.push_str(status
+				.as_ref()
+				.trim());
+	}
+	#[inline(always)]
+	pub fn status(mut self, status: impl AsRef<str>) -> Self {
+		self.set_status(status);
+		self
+	}
+}
+
+impl Display for Presence {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{:#} is {}", self.nickname, self.state)?;
+		if !self.status.is_empty() {
+			write!(f, " ({})", self.status)?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,66 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use crate::errors::ProfileError;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Everything a user tells about themselves, besides their [`Nickname`].
+///
+/// Every field is optional and left empty by default.
+///
+/// [`Nickname`]: crate::Nickname
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Eq, PartialEq)]
+pub struct Profile {
+	display_name: String,
+	status: String,
+	pronouns: String,
+	timezone: String,
+	bio: String,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+macro_rules! accessors {
+	($($field: ident, $get: ident, $set: ident, $max: ident = $limit: literal);+ $(;)?) => {
+		impl Profile {$(
+			#[doc = concat!("The maximum amount of codepoints in the `", stringify!($field), "`.")]
+			pub const $max: usize = $limit;
+
+			#[inline(always)]
+			pub fn $get(&self) -> &str { &self.$field }
+			#[inline(always)]
+			pub fn $set(&mut self, $field: impl AsRef<str>) {
+				self.$field.clear();
+				self.$field.push_str($field.as_ref().trim());
+			}
+			#[inline(always)]
+			pub fn $field(mut self, $field: impl AsRef<str>) -> Self {
+				self.$set($field);
+				self
+			}
+		)+
+
+			/// Check every field against its maximum length.
+			pub fn validate(&self) -> Result<(), ProfileError> {
+				$(
+					let length = self.$field.chars().count();
+					if length > Self::$max {
+						Err(ProfileError::TooLong(stringify!($field), length, Self::$max))?
+					}
+				)+
+				Ok(())
+			}
+		}
+	};
+}
+
+accessors! {
+	display_name,	get_display_name,	set_display_name,	MAX_DISPLAY_NAME_LENGTH	= 64;
+	status,		get_status,		set_status,		MAX_STATUS_LENGTH	= 128;
+	pronouns,	get_pronouns,		set_pronouns,		MAX_PRONOUNS_LENGTH	= 32;
+	timezone,	get_timezone,		set_timezone,		MAX_TIMEZONE_LENGTH	= 64;
+	bio,		get_bio,		set_bio,		MAX_BIO_LENGTH		= 512;
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Profile {
+	#[inline(always)]
+	pub fn new() -> Self { Self::default() }
+}
@@ -0,0 +1,99 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	fmt::{self, Display, Formatter},
+	time::Duration,
+};
+
+use crate::Message;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How long, and how much of, its history a server keeps.
+///
+/// Whatever falls outside of any of the limits expires; the latest message
+/// never does, so that sequences carry on where they left off.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Eq, PartialEq)]
+pub struct Retention {
+	/// How old a message may get; forever, if zero.
+	age: Duration,
+	/// How many messages are kept; any amount, if zero.
+	count: u64,
+	/// How many bytes of nicknames and contents are kept; any amount, if
+	/// zero.
+	bytes: u64,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Retention {
+	#[inline(always)]
+	pub fn new() -> Self { Self::default() }
+
+	#[inline(always)]
+	pub const fn get_age(&self) -> Duration { self.age }
+	#[inline(always)]
+	pub fn set_age(&mut self, age: Duration) { self.age = age; }
+	#[inline(always)]
+	pub fn age(mut self, age: Duration) -> Self {
+		self.set_age(age);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_count(&self) -> u64 { self.count }
+	#[inline(always)]
+	pub fn set_count(&mut self, count: u64) { self.count = count; }
+	#[inline(always)]
+	pub fn count(mut self, count: u64) -> Self {
+		self.set_count(count);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_bytes(&self) -> u64 { self.bytes }
+	#[inline(always)]
+	pub fn set_bytes(&mut self, bytes: u64) { self.bytes = bytes; }
+	#[inline(always)]
+	pub fn bytes(mut self, bytes: u64) -> Self {
+		self.set_bytes(bytes);
+		self
+	}
+
+	/// Whether nothing ever expires.
+	#[inline(always)]
+	pub const fn is_forever(&self) -> bool { self.age.is_zero() && self.count == 0 && self.bytes == 0 }
+
+	/// How much a message counts towards the limit of bytes.
+	#[inline(always)]
+	pub fn measure(message: &Message) -> u64 {
+		(message
+			.get_nickname()
+			.len()
+			+ message
+				.get_content()
+				.len()) as u64
+	}
+}
+
+impl Display for Retention {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		if self.is_forever() {
+			return f.write_str("forever")
+		}
+		let mut limits = Vec::with_capacity(3);
+		if !self.age.is_zero() {
+			let seconds = self.age.as_secs();
+			let (amount, unit) = [(86400, 'd'), (3600, 'h'), (60, 'm')]
+				.into_iter()
+				.find(|(size, _)| seconds.is_multiple_of(*size))
+				.map_or((seconds, 's'), |(size, unit)| (seconds / size, unit));
+			limits.push(format!("for {amount}{unit}"));
+		}
+		if self.count != 0 {
+			limits.push(format!("the latest {} messages", self.count));
+		}
+		if self.bytes != 0 {
+			limits.push(format!("up to {} bytes", self.bytes));
+		}
+		f.write_str(&limits.join(", "))
+	}
+}
@@ -0,0 +1,115 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use chrono::{DateTime, Utc};
+
+use crate::Message;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// What to look for in the server's history.
+///
+/// A message is a hit, if its content holds the text, and it was written by
+/// the given nickname, within the given window of time; whichever of them are
+/// set.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Eq, PartialEq)]
+pub struct SearchQuery {
+	/// Empty, if any content will do.
+	text: String,
+	/// Empty, if anyone will do; never case-sensitive.
+	nickname: String,
+	case_sensitive: bool,
+	since: Option<DateTime<Utc>>,
+	until: Option<DateTime<Utc>>,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl SearchQuery {
+	#[inline(always)]
+	pub fn new() -> Self { Self::default() }
+
+	#[inline(always)]
+	pub fn get_text(&self) -> &str { &self.text }
+	#[inline(always)]
+	pub fn set_text(&mut self, text: impl Into<String>) { self.text = text.into(); }
+	#[inline(always)]
+	pub fn text(mut self, text: impl Into<String>) -> Self {
+		self.set_text(text);
+		self
+	}
+
+	#[inline(always)]
+	pub fn get_nickname(&self) -> &str { &self.nickname }
+	#[inline(always)]
+	pub fn set_nickname(&mut self, nickname: impl Into<String>) { self.nickname = nickname.into(); }
+	#[inline(always)]
+	pub fn nickname(mut self, nickname: impl Into<String>) -> Self {
+		self.set_nickname(nickname);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_case_sensitive(&self) -> bool { self.case_sensitive }
+	#[inline(always)]
+	pub fn set_case_sensitive(&mut self, case_sensitive: bool) { self.case_sensitive = case_sensitive; }
+	#[inline(always)]
+	pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+		self.set_case_sensitive(case_sensitive);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_since(&self) -> Option<DateTime<Utc>> { self.since }
+	#[inline(always)]
+	pub fn set_since(&mut self, since: Option<DateTime<Utc>>) { self.since = since; }
+	#[inline(always)]
+	pub fn since(mut self, since: Option<DateTime<Utc>>) -> Self {
+		self.set_since(since);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_until(&self) -> Option<DateTime<Utc>> { self.until }
+	#[inline(always)]
+	pub fn set_until(&mut self, until: Option<DateTime<Utc>>) { self.until = until; }
+	#[inline(always)]
+	pub fn until(mut self, until: Option<DateTime<Utc>>) -> Self {
+		self.set_until(until);
+		self
+	}
+
+	/// Whether the given message is a hit.
+	pub fn matches(&self, message: &Message) -> bool {
+		let written_on = message.get_utc();
+		if self
+			.since
+			.is_some_and(|since| written_on < since)
+			|| self
+				.until
+				.is_some_and(|until| written_on >= until)
+		{
+			return false
+		}
+		if !(self
+			.nickname
+			.is_empty()
+			|| message
+				.get_nickname()
+				.as_str()
+				.to_lowercase()
+				== self
+					.nickname
+					.to_lowercase())
+		{
+			return false
+		}
+		let content = message.get_content();
+		if self.case_sensitive {
+			content.contains(&self.text)
+		} else {
+			content
+				.to_lowercase()
+				.contains(&self
+					.text
+					.to_lowercase())
+		}
+	}
+}
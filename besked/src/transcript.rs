@@ -0,0 +1,61 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::str::FromStr;
+
+use crate::errors::TranscriptFromStrError;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The forms, that a transcript of the history can be exported in.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Eq, PartialEq)]
+pub enum Transcript {
+	/// A line for every message.
+	#[default]
+	Text,
+	/// A list-item for every message.
+	Markdown,
+	/// A standalone page, with a list-item for every message.
+	Html,
+	/// A JSON-object on a line of its own for every message.
+	JsonLines,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Transcript {
+	#[inline(always)]
+	pub const fn as_byte(&self) -> u8 {
+		match self {
+			Self::Text => 0,
+			Self::Markdown => 1,
+			Self::Html => 2,
+			Self::JsonLines => 3,
+		}
+	}
+
+	#[inline(always)]
+	pub const fn from_byte(byte: u8) -> Option<Self> {
+		match byte {
+			0 => Some(Self::Text),
+			1 => Some(Self::Markdown),
+			2 => Some(Self::Html),
+			3 => Some(Self::JsonLines),
+			_ => None,
+		}
+	}
+}
+
+impl FromStr for Transcript {
+	type Err = TranscriptFromStrError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s
+			.to_lowercase()
+			.as_str()
+		{
+			"text" | "txt" => Ok(Self::Text),
+			"markdown" | "md" => Ok(Self::Markdown),
+			"html" | "htm" => Ok(Self::Html),
+			"jsonl" | "json-lines" | "ndjson" => Ok(Self::JsonLines),
+			_ => Err(TranscriptFromStrError(s.into())),
+		}
+	}
+}
@@ -1,37 +1,71 @@
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 use std::{
-	io::{BufRead, BufReader, ErrorKind, Read},
-	net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream},
-	thread::JoinHandle,
-	time::Duration,
+	io::{self, BufReader, ErrorKind},
+	net::{Ipv4Addr, Shutdown, SocketAddr, SocketAddrV4, TcpStream},
+	sync::{Arc, Weak},
+	thread::{JoinHandle, sleep, spawn},
+	time::{Duration, Instant},
 };
 
+use besked::{
+	Frame, HistoryQuery, Identifier, Message, Moderate, Nickname, Presence, Profile, SearchQuery, State,
+	errors::{MessageError, ProfileError, ServerError, UsernameFromStrError},
+};
+use crossbeam_channel::{Receiver, unbounded};
 use either::{Either, Left, Right};
+use parking_lot::Mutex;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use thiserror::Error;
 
-use besked::{Identifier, Message,  Nickname};
-
-use crate::proto::{spaces::Private, MessageLog};
+use crate::spaces::Private;
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 const TRIES: usize = 16;
+/// The least amount of time between two typing-notifications.
+pub const TYPING_INTERVAL: Duration = Duration::from_secs(3);
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+#[derive(Debug, Error)]
+pub enum JoinError {
+	#[error("The server refused to let us join: {0}")]
+	Refused(ServerError),
+
+	#[error("{0}")]
+	Nickname(#[from] UsernameFromStrError),
+	#[error("{0}")]
+	Profile(#[from] ProfileError),
+	#[error("{0}")]
+	Message(#[from] MessageError),
+	#[error("{0}")]
+	IO(#[from] io::Error),
+}
+
 /// Singelton struct for client-mode startup.
 pub struct Client {
-	stream: BufReader<TcpStream>,
-	log: MessageLog,
-
-	message_handler: JoinHandle<()>,
+	stream: Arc<Mutex<TcpStream>>,
+	activity: Arc<Mutex<Activity>>,
+	/// When the last typing-notification was sent.
+	typed: Option<Instant>,
+	/// The last sequence, that has been marked as read.
+	read: u64,
 
-	buffer: Vec<u8>,
+	message_handler: JoinHandle<Result<(), MessageError>>,
+	incoming: Receiver<Frame>,
 
 	nick: Nickname,
 	id: Identifier,
+	profile: Profile,
+	/// The longest body the server accepts.
+	max_frame: u32,
 }
 
-pub struct ClientBuilder {
-	stream: Option<BufReader<TcpStream>>,
-	buffer: Vec<u8>,
-	log: Message,
+/// What the client last announced about itself, and when its user was last
+/// seen doing something.
+struct Activity {
+	last: Instant,
+	state: State,
+	status: String,
+	/// Whether the current state was set by the idle-timeout, rather than by
+	/// the user.
+	auto: bool,
 }
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 /// Partition the iterator based on whether a connection attempt succeeds or
@@ -108,26 +142,338 @@ pub fn find_from(self_addr: Ipv4Addr, port: impl Into<u16>) -> Vec<TcpStream> {
 
 	Vec::new()
 }
+
+/// Send a [`Frame::Presence`] and remember what was announced.
+fn announce(
+	stream: &Mutex<TcpStream>,
+	activity: &mut Activity,
+	id: Identifier,
+	nick: &Nickname,
+	state: State,
+	status: &str,
+) -> Result<(), MessageError> {
+	Frame::Presence(Presence::new(id, nick.clone(), state).status(status)).send(&mut *stream.lock())?;
+	activity.state = state;
+	activity
+		.status
+		.clear();
+	activity
+		.status
+		.push_str(status);
+	Ok(())
+}
+
+/// Mark the client as away, once it has been idle for long enough; stops when
+/// the client is dropped.
+fn idle_watch(stream: Weak<Mutex<TcpStream>>, activity: Arc<Mutex<Activity>>, id: Identifier, nick: Nickname, idle: Duration) {
+	loop {
+		let elapsed = activity
+			.lock()
+			.last
+			.elapsed();
+		sleep(idle
+			.saturating_sub(elapsed)
+			.max(Duration::from_secs(1)));
+
+		let Some(stream) = stream.upgrade() else { return };
+		let mut activity = activity.lock();
+		if activity.state != State::Online || activity.last.elapsed() < idle {
+			continue
+		}
+		let status = std::mem::take(&mut activity.status);
+		let Ok(_) = announce(&stream, &mut activity, id, &nick, State::Away, &status) else { return };
+		activity.auto = true;
+	}
+}
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-impl TryFrom<TcpStream> for Client {
-	type Error = ();
+impl Client {
+	/// Join the server on the other end of the stream under a nickname, and
+	/// the secret it was registered under, if it was.
+	///
+	/// The nickname is judged by the server's policy, before it is even sent.
+	pub fn join(
+		mut stream: TcpStream,
+		nickname: impl AsRef<str>,
+		secret: impl AsRef<str>,
+		profile: Profile,
+	) -> Result<Self, JoinError> {
+		profile.validate()?;
 
-	fn try_from(value: TcpStream) -> Result<Self, Self::Error> {
-		let mut buffer = Vec::with_capacity(Message::MIN_LENGTH);
+		let mut reader = BufReader::new(stream.try_clone()?);
 
-		let mut stream = BufReader::new(value);
+		let policy = match Frame::recv(&mut reader)? {
+			Frame::Policy(policy) => policy,
+			Frame::Error(error) => Err(JoinError::Refused(error))?,
+			other => Err(MessageError::Unexpected(other.kind()))?,
+		};
+		Frame::Join(policy.apply(nickname)?, profile.clone(), secret.as_ref().into()).send(&mut stream)?;
 
-		let msg = Message::recv(&mut stream)?;
+		let (id, nick, max_frame) = match Frame::recv(&mut reader)? {
+			Frame::Welcome(id, nick, max_frame) => (id, nick, max_frame),
+			Frame::Error(error) => Err(JoinError::Refused(error))?,
+			other => Err(MessageError::Unexpected(other.kind()))?,
+		};
 
-		let msg = Message::from_bytes(buffer.as_slice())?;
-		let ident = *msg.get_identifier();
+		let stream = Arc::new(Mutex::new(stream));
+		let writer = Arc::downgrade(&stream);
 
-		Self {
+		let (sender, incoming) = unbounded();
+		let message_handler = spawn(move || -> Result<(), MessageError> {
+			loop {
+				// NOTE: whatever the server takes, it may relay.
+				let frame = Frame::recv_limited(&mut reader, max_frame.max(Frame::MAX_LENGTH))?;
+				if let Frame::Ping = frame {
+					let Some(writer) = writer.upgrade() else { return Ok(()) };
+					Frame::Pong.send(&mut *writer.lock())?;
+					continue
+				}
+				// NOTE: a page, that is not the last, asks for the next one; one page at a time.
+				if let Frame::Page(_, next @ 1 ..) = frame {
+					let Some(writer) = writer.upgrade() else { return Ok(()) };
+					Frame::History(HistoryQuery::After(next)).send(&mut *writer.lock())?;
+				}
+				// NOTE: a server, that says it closes, leaves nothing to read afterwards.
+				let closing = matches!(frame, Frame::Closing(_));
+				let Ok(_) = sender.send(frame) else { return Ok(()) };
+				if closing {
+					return Ok(())
+				}
+			}
+		});
+
+		let activity = Arc::new(Mutex::new(Activity {
+			last: Instant::now(),
+			state: State::Online,
+			status: String::new(),
+			auto: false,
+		}));
+
+		Ok(Self {
 			stream,
-			handler,
-			buffer,
-			log,
-			nick: 
+			activity,
+			typed: None,
+			read: 0,
+			message_handler,
+			incoming,
+			nick,
+			id,
+			profile,
+			max_frame,
+		})
+	}
+
+	/// Send a message, unless it is longer than the server accepts.
+	pub fn send(&mut self, content: impl AsRef<str>) -> Result<(), MessageError> {
+		let frame = Frame::Message(
+			Message::with_identifier(self.id)
+				.nickname(self.nick.clone())
+				.content(content),
+		);
+		self.touch()?;
+		self.typed = None;
+		frame.send_limited(&mut *self.stream.lock(), self.max_frame)
+	}
+
+	/// Send a message to the user with the given nickname alone.
+	///
+	/// What became of it arrives as [`Frame::Delivery`] through [`incoming`].
+	///
+	/// [`incoming`]: Self::incoming
+	pub fn send_to(&mut self, to: Nickname, content: impl AsRef<str>) -> Result<(), MessageError> {
+		let frame = Frame::Direct(
+			to,
+			Message::with_identifier(self.id)
+				.nickname(self.nick.clone())
+				.content(content),
+		);
+		self.touch()?;
+		frame.send_limited(&mut *self.stream.lock(), self.max_frame)
+	}
+
+	/// Register the nickname in use under a secret, which has to be given to
+	/// join with it from then on.
+	///
+	/// The answer arrives as [`Frame::Registered`] through [`incoming`].
+	///
+	/// [`incoming`]: Self::incoming
+	#[inline(always)]
+	pub fn register(&mut self, secret: impl AsRef<str>) -> Result<(), MessageError> {
+		Frame::Register(secret.as_ref().into()).send(&mut *self.stream.lock())
+	}
+
+	/// Announce a new state and status to everyone.
+	pub fn set_presence(&mut self, state: State, status: impl AsRef<str>) -> Result<(), MessageError> {
+		let mut activity = self.activity.lock();
+		activity.last = Instant::now();
+		activity.auto = false;
+		announce(&self.stream, &mut activity, self.id, &self.nick, state, status.as_ref())
+	}
+
+	/// Note that the user did something, coming back if they were away only
+	/// because of being idle.
+	pub fn touch(&mut self) -> Result<(), MessageError> {
+		let mut activity = self.activity.lock();
+		activity.last = Instant::now();
+		if !activity.auto {
+			return Ok(())
+		}
+		activity.auto = false;
+		let status = std::mem::take(&mut activity.status);
+		announce(&self.stream, &mut activity, self.id, &self.nick, State::Online, &status)
+	}
+
+	/// Let everyone know that the user is typing.
+	///
+	/// Does nothing if the last notification is more recent than the
+	/// [`TYPING_INTERVAL`], so it may be called on every keystroke.
+	pub fn typing(&mut self) -> Result<(), MessageError> {
+		self.touch()?;
+		if self
+			.typed
+			.is_some_and(|typed| typed.elapsed() < TYPING_INTERVAL)
+		{
+			return Ok(())
+		}
+		self.typed = Some(Instant::now());
+		Frame::Typing(self.id, self.nick.clone()).send(&mut *self.stream.lock())
+	}
+
+	/// Go away on one's own, after no activity for the given duration.
+	///
+	/// Only an online client goes away; a busy one stays busy.
+	pub fn away_after(&self, idle: Duration) {
+		let stream = Arc::downgrade(&self.stream);
+		let activity = self.activity.clone();
+		let (id, nick) = (self.id, self.nick.clone());
+		spawn(move || idle_watch(stream, activity, id, nick, idle));
+	}
+
+	/// Tell everyone, that every message up to the given sequence has been
+	/// read.
+	///
+	/// Does nothing if a later sequence has already been marked.
+	pub fn mark_read(&mut self, sequence: u64) -> Result<(), MessageError> {
+		if sequence <= self.read {
+			return Ok(())
+		}
+		self.read = sequence;
+		Frame::Read(self.id, sequence).send(&mut *self.stream.lock())
+	}
+
+	#[inline(always)]
+	pub const fn get_identifier(&self) -> Identifier { self.id }
+	#[inline(always)]
+	pub const fn get_nickname(&self) -> &Nickname { &self.nick }
+
+	/// Ping the server once per `interval`, and give up on it after
+	/// [`Frame::MISSED_HEARTBEATS`] intervals of silence.
+	pub fn heartbeat(&self, interval: Duration) -> io::Result<()> {
+		self.stream
+			.lock()
+			.set_read_timeout(Some(interval * Frame::MISSED_HEARTBEATS))?;
+		let stream = Arc::downgrade(&self.stream);
+		spawn(move || {
+			loop {
+				sleep(interval);
+				let Some(stream) = stream.upgrade() else { return };
+				let Ok(_) = Frame::Ping.send(&mut *stream.lock()) else { return };
+			}
+		});
+		Ok(())
+	}
+
+	/// Replace the own profile.
+	pub fn set_profile(&mut self, profile: Profile) -> Result<(), JoinError> {
+		profile.validate()?;
+		Frame::Profile(self.id, profile.clone()).send(&mut *self.stream.lock())?;
+		self.profile = profile;
+		Ok(())
+	}
+	#[inline(always)]
+	pub const fn get_profile(&self) -> &Profile { &self.profile }
+
+	/// Ask the server for someone's profile.
+	///
+	/// The answer arrives as [`Frame::Profile`] through [`incoming`].
+	///
+	/// [`incoming`]: Self::incoming
+	#[inline(always)]
+	pub fn query_profile(&mut self, of: Identifier) -> Result<(), MessageError> {
+		Frame::ProfileQuery(of).send(&mut *self.stream.lock())
+	}
+
+	/// Ask the server for a part of its history.
+	///
+	/// The messages arrive as [`Frame::Backfill`]s through [`incoming`], a
+	/// [`Frame::Page`] after each page of them; every page after the first is
+	/// asked for on its own.
+	///
+	/// [`incoming`]: Self::incoming
+	#[inline(always)]
+	pub fn history(&mut self, query: HistoryQuery) -> Result<(), MessageError> {
+		Frame::History(query).send(&mut *self.stream.lock())
+	}
+
+	/// Search the history of the server.
+	///
+	/// The latest hits arrive as [`Frame::Hit`]s through [`incoming`], and a
+	/// [`Frame::Found`] after them.
+	///
+	/// [`incoming`]: Self::incoming
+	#[inline(always)]
+	pub fn search(&mut self, query: SearchQuery) -> Result<(), MessageError> {
+		Frame::Search(query).send(&mut *self.stream.lock())
+	}
+
+	/// Ask the server for the head of its history's hash-chain; which can be
+	/// held against a later one, or against `verify-log`.
+	///
+	/// The answer arrives as [`Frame::Head`] through [`incoming`].
+	///
+	/// [`incoming`]: Self::incoming
+	#[inline(always)]
+	pub fn head(&mut self) -> Result<(), MessageError> { Frame::HeadQuery.send(&mut *self.stream.lock()) }
+
+	/// Have the server moderate its users; which it only heeds from an
+	/// operator.
+	///
+	/// The answer arrives as [`Frame::Done`], or [`Frame::Error`], through
+	/// [`incoming`].
+	///
+	/// [`incoming`]: Self::incoming
+	#[inline(always)]
+	pub fn moderate(&mut self, moderate: Moderate) -> Result<(), MessageError> {
+		Frame::Moderate(moderate).send(&mut *self.stream.lock())
+	}
+
+	/// Every frame that has been received from the server.
+	#[inline(always)]
+	pub const fn incoming(&self) -> &Receiver<Frame> { &self.incoming }
+
+	/// Whether the server is still being listened to.
+	#[inline(always)]
+	pub fn is_connected(&self) -> bool {
+		!self.message_handler
+			.is_finished()
+	}
+
+	/// Leave the server, telling why the connection ended, if it did so on
+	/// its own.
+	pub fn close(self) -> Result<(), MessageError> {
+		if self.is_connected() {
+			let _ = self
+				.stream
+				.lock()
+				.shutdown(Shutdown::Both);
+			return Ok(())
+		}
+		match self
+			.message_handler
+			.join()
+		{
+			Ok(outcome) => outcome,
+			// NOTE: the reader doesn't panic; if it did, there is nothing more to tell.
+			Err(_) => Err(MessageError::ConnectionInterrupted),
 		}
 	}
 }
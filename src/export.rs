@@ -0,0 +1,216 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	collections::BTreeMap,
+	fmt::Write as _,
+	fs::{File, rename},
+	io::{self, Write},
+	path::{Path, PathBuf},
+};
+
+use besked::{Frame, HistoryQuery, Message, Transcript};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::json;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How the time of a message is shown, in the local time-zone.
+const TIME: &str = "%Y-%m-%d %H:%M:%S";
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A transcript, that a client puts together from the pages of history, that
+/// it asked for.
+pub struct Export {
+	transcript: Transcript,
+	path: PathBuf,
+	since: Option<DateTime<Utc>>,
+	until: Option<DateTime<Utc>>,
+	/// By their sequence, so that a message sent twice is kept once.
+	messages: BTreeMap<u64, Message>,
+}
+
+/// What became of a frame, that was offered to an [`Export`].
+pub enum Progress {
+	/// The frame is no part of the history.
+	Unrelated,
+	/// The frame was taken; more are to come.
+	Pending,
+	/// The last page came.
+	Done,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Export {
+	/// An export of the messages written at, or after, `since`, and before
+	/// `until`, to the given path.
+	pub fn new(transcript: Transcript, path: PathBuf, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Self {
+		Self {
+			transcript,
+			path,
+			since,
+			until,
+			messages: BTreeMap::new(),
+		}
+	}
+
+	/// What to ask the server for; the pages after the first are asked for
+	/// by the client on its own.
+	pub fn query(&self) -> HistoryQuery {
+		match self.since {
+			Some(since) => HistoryQuery::Since(since),
+			None => HistoryQuery::After(0),
+		}
+	}
+
+	/// Take a frame from the server.
+	pub fn take(&mut self, frame: &Frame) -> Progress {
+		match frame {
+			Frame::Backfill(message) => {
+				// NOTE: the pages go on to the latest message; whatever is past the range is dropped.
+				if self
+					.until
+					.is_none_or(|until| message.get_utc() < until)
+					&& self
+						.since
+						.is_none_or(|since| message.get_utc() >= since)
+				{
+					self.messages
+						.insert(message.get_sequence(), message.clone());
+				}
+				Progress::Pending
+			},
+			Frame::Page(_, 0) => Progress::Done,
+			Frame::Page(..) => Progress::Pending,
+			_ => Progress::Unrelated,
+		}
+	}
+
+	/// Write what was taken; tells what was done, either way.
+	pub fn finish(self) -> String {
+		let messages: Vec<Message> = self
+			.messages
+			.into_values()
+			.collect();
+		match write(&self.path, self.transcript, &messages) {
+			Ok(()) => format!("Exported {} messages to `{}`.", messages.len(), self.path.display()),
+			Err(error) => format!("The export to `{}` failed: {error}", self.path.display()),
+		}
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Render the given messages, oldest first, as a transcript.
+pub fn render(transcript: Transcript, messages: &[Message]) -> String {
+	let mut text = String::new();
+	match transcript {
+		Transcript::Text => {
+			for message in messages {
+				let _ = writeln!(
+					text,
+					"[{}] #{} {:#}: {}",
+					message
+						.get_local()
+						.format(TIME),
+					message.get_identifier(),
+					message.get_nickname(),
+					message.get_content()
+				);
+			}
+		},
+		Transcript::Markdown => {
+			for message in messages {
+				let _ = writeln!(
+					text,
+					"- `{}` #{} **{}**: {}",
+					message
+						.get_local()
+						.format(TIME),
+					message.get_identifier(),
+					escape_markdown(&format!("{:#}", message.get_nickname())),
+					escape_markdown(message.get_content())
+				);
+			}
+		},
+		Transcript::Html => {
+			text.push_str(concat!(
+				"<!DOCTYPE html>\n",
+				"<html>\n",
+				"<head>\n",
+				"<meta charset=\"utf-8\">\n",
+				"<title>Transcript</title>\n",
+				"<style>body { font-family: sans-serif; } time, .id { color: gray; } li { list-style: none; }</style>\n",
+				"</head>\n",
+				"<body>\n",
+				"<ol>\n",
+			));
+			for message in messages {
+				let local = message.get_local();
+				let _ = writeln!(
+					text,
+					"<li id=\"m{}\"><time datetime=\"{}\">{}</time> <span class=\"id\">#{}</span> <b>{}</b>: {}</li>",
+					message.get_sequence(),
+					local.to_rfc3339_opts(SecondsFormat::Secs, false),
+					local.format(TIME),
+					message.get_identifier(),
+					escape_html(&format!("{:#}", message.get_nickname())),
+					escape_html(message.get_content())
+				);
+			}
+			text.push_str("</ol>\n</body>\n</html>\n");
+		},
+		Transcript::JsonLines => {
+			for message in messages {
+				let _ = writeln!(
+					text,
+					"{}",
+					json!({
+						"sequence": message.get_sequence(),
+						"written_on": message
+							.get_local()
+							.to_rfc3339_opts(SecondsFormat::Secs, false),
+						"identifier": u32::from(*message.get_identifier()),
+						"nickname": message
+							.get_nickname()
+							.as_str(),
+						"content": message.get_content(),
+						"imported": message.get_imported(),
+					})
+				);
+			}
+		},
+	}
+	text
+}
+
+/// Render the given messages as a transcript of its own, which then takes the
+/// place of whatever was at the given path.
+pub fn write(path: &Path, transcript: Transcript, messages: &[Message]) -> io::Result<()> {
+	let saving = path.with_extension("saving");
+	let mut file = File::create(&saving)?;
+	file.write_all(
+		render(transcript, messages)
+			.as_bytes(),
+	)?;
+	file.sync_all()?;
+	rename(&saving, path)
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+fn escape_markdown(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for glyph in text.chars() {
+		if matches!(glyph, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~') {
+			escaped.push('\\');
+		}
+		escaped.push(glyph);
+	}
+	escaped
+}
+
+fn escape_html(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for glyph in text.chars() {
+		match glyph {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			'\'' => escaped.push_str("&#39;"),
+			glyph => escaped.push(glyph),
+		}
+	}
+	escaped
+}
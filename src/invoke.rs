@@ -1,15 +1,17 @@
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 use std::{
-	env::{args, vars, Args, Vars},
+	env::{args, var, vars, Args},
 	hint::unreachable_unchecked,
 	num::ParseIntError,
 	str::FromStr,
 };
 
-use lazy_regex::regex;
+use lazy_regex::{Regex, regex, regex::escape};
 use thiserror::Error;
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-const DEFAULT_PORT: u16 = 49434;
+pub const DEFAULT_PORT: u16 = 49434;
+/// The sub-commands, that each do one thing, and exit.
+const COMMANDS: [&str; 6] = ["verify-log", "backup", "restore", "export", "import", "moderate"];
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 #[derive(Debug, Error)]
 pub enum FromCallError {
@@ -22,6 +24,8 @@ pub enum FromCallError {
 
 	#[error("{0}")]
 	Parse(#[from] ParseIntError),
+	#[error("The value `{0}` couldn't be parsed.")]
+	Invalid(Box<str>),
 }
 
 #[derive(Debug)]
@@ -31,6 +35,25 @@ pub enum FromCallError {
 pub enum StartupOption {
 	Server,
 	Client,
+	/// Recompute the hash-chain of a server's history on disk, and exit.
+	VerifyLog,
+	/// Have a running server write a backup, and exit; as in
+	#[doc = concat!("`", env!("CARGO_PKG_NAME"), " server backup <file>`.")]
+	///
+	/// The file is written by the server, so that a relative path is taken to
+	/// be relative to the working directory of the caller, before it is sent.
+	Backup,
+	/// Put a backup into a server's data-directory, and exit; as in
+	#[doc = concat!("`", env!("CARGO_PKG_NAME"), " server restore <file>`.")]
+	///
+	/// The server must not be running, as its files are replaced.
+	Restore,
+	/// Have a running server write a transcript of its history, and exit.
+	Export,
+	/// Have a running server take a transcript into its history, and exit.
+	Import,
+	/// Have a running server moderate its users, and exit.
+	Moderate,
 }
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 pub fn port() -> Result<u16, FromCallError> {
@@ -71,7 +94,7 @@ pub fn port() -> Result<u16, FromCallError> {
 		},
 	};
 	match m.parse() {
-		Ok(val) => return Ok(val),
+		Ok(val) => Ok(val),
 		Err(_) => {
 			let rx = regex!(r#"_{1,2}P(ORT)?[-_]?(N(UM(BER)?)?)?"#i);
 
@@ -87,6 +110,105 @@ pub fn port() -> Result<u16, FromCallError> {
 	}
 }
 
+/// Where the sub-command is among the arguments, if one is given; only ever
+/// as the first of them, or the first after `server`.
+fn command_at(args: &[String]) -> Option<usize> {
+	let index = match args
+		.first()?
+		.as_str()
+	{
+		"server" => 1,
+		_ => 0,
+	};
+	COMMANDS
+		.contains(&args
+			.get(index)?
+			.as_str())
+		.then_some(index)
+}
+
+/// The arguments after the given sub-command, if it is the one given.
+fn after(command: &str) -> Result<Vec<String>, FromCallError> {
+	let args: Vec<String> = args()
+		.skip(1)
+		.collect();
+	let Some(index) = command_at(&args).filter(|index| args[*index] == command) else {
+		Err(FromCallError::NotFound)?
+	};
+	Ok(args[index + 1 ..].to_vec())
+}
+
+/// The argument after the given sub-command.
+pub fn operand(command: &str) -> Result<String, FromCallError> {
+	after(command)?
+		.into_iter()
+		.next()
+		.ok_or(FromCallError::NotSpecified)
+}
+
+/// The arguments after the given sub-command, up to the first flag.
+pub fn operands(command: &str) -> Result<Vec<String>, FromCallError> {
+	let operands: Vec<String> = after(command)?
+		.into_iter()
+		.take_while(|s| !s.starts_with(['-', '+']))
+		.collect();
+	if operands.is_empty() {
+		Err(FromCallError::NotSpecified)?
+	}
+	Ok(operands)
+}
+
+/// Look up the value of a setting.
+///
+/// The arguments are searched for `--<name> <value>` or `--<name>=<value>`,
+/// before falling back to the environment-variable `__<NAME>`.
+pub fn setting<T>(name: &str) -> Result<T, FromCallError>
+where
+	T: FromStr,
+{
+	let rx = Regex::new(&format!(r"^(-{{1,2}}|\+){}([=:](?<value>.*))?$", escape(name)))
+		.expect("escaped names always form a valid pattern");
+
+	let mut it = args().skip(1);
+	let value = match it
+		.by_ref()
+		.find_map(|s| {
+			let cap = rx.captures(s.as_str())?;
+			Some(cap.name("value")
+				.map(|m| String::from(m.as_str())))
+		}) {
+		Some(Some(value)) => value,
+		Some(None) => {
+			let Some(value) = it.next() else {
+				Err(FromCallError::NotSpecified)?
+			};
+			value
+		},
+		None => {
+			let key = format!(
+				"__{}",
+				name.to_uppercase()
+					.replace('-', "_")
+			);
+			var(key).map_err(|_| FromCallError::NotFound)?
+		},
+	};
+	value.parse()
+		.map_err(|_| FromCallError::Invalid(value.into()))
+}
+
+/// Look up the value of a setting, falling back to a default if it wasn't
+/// specified at all.
+pub fn setting_or<T>(name: &str, default: T) -> Result<T, FromCallError>
+where
+	T: FromStr,
+{
+	match setting(name) {
+		Err(FromCallError::NotFound) => Ok(default),
+		other => other,
+	}
+}
+
 impl StartupOption {
 	#[inline(always)]
 	/// Parse a new instance from the passed in [`Args`] or default to
@@ -110,6 +232,54 @@ impl StartupOption {
 		let Self::Client = self else { return false };
 		true
 	}
+
+	/// Check whether the instance of
+	#[doc = concat!('`', env!("CARGO_PKG_NAME"), '`')]
+	/// should only verify the history of a server.
+	pub fn as_verify_log(&self) -> bool {
+		let Self::VerifyLog = self else { return false };
+		true
+	}
+
+	/// Check whether the instance of
+	#[doc = concat!('`', env!("CARGO_PKG_NAME"), '`')]
+	/// should only have a server back up.
+	pub fn as_backup(&self) -> bool {
+		let Self::Backup = self else { return false };
+		true
+	}
+
+	/// Check whether the instance of
+	#[doc = concat!('`', env!("CARGO_PKG_NAME"), '`')]
+	/// should only restore a backup.
+	pub fn as_restore(&self) -> bool {
+		let Self::Restore = self else { return false };
+		true
+	}
+
+	/// Check whether the instance of
+	#[doc = concat!('`', env!("CARGO_PKG_NAME"), '`')]
+	/// should only have a server export a transcript.
+	pub fn as_export(&self) -> bool {
+		let Self::Export = self else { return false };
+		true
+	}
+
+	/// Check whether the instance of
+	#[doc = concat!('`', env!("CARGO_PKG_NAME"), '`')]
+	/// should only have a server import a transcript.
+	pub fn as_import(&self) -> bool {
+		let Self::Import = self else { return false };
+		true
+	}
+
+	/// Check whether the instance of
+	#[doc = concat!('`', env!("CARGO_PKG_NAME"), '`')]
+	/// should only have a server moderate its users.
+	pub fn as_moderate(&self) -> bool {
+		let Self::Moderate = self else { return false };
+		true
+	}
 }
 
 impl Default for StartupOption {
@@ -122,7 +292,19 @@ impl FromStr for StartupOption {
 
 	#[inline(always)]
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
-		if s.starts_with('s') {
+		if s == "verify-log" {
+			Ok(Self::VerifyLog)
+		} else if s == "backup" {
+			Ok(Self::Backup)
+		} else if s == "restore" {
+			Ok(Self::Restore)
+		} else if s == "export" {
+			Ok(Self::Export)
+		} else if s == "import" {
+			Ok(Self::Import)
+		} else if s == "moderate" {
+			Ok(Self::Moderate)
+		} else if s.starts_with('s') {
 			Ok(Self::Server)
 		} else if s.starts_with('c') {
 			Ok(Self::Client)
@@ -144,11 +326,26 @@ impl TryFrom<Args> for StartupOption {
 		{
 			Err(Self::Error::NoArguments)?
 		}
-		let Some(Some(m)) = it.find_map(|s| {
-			let cap = regex!(r"(-{1,2}|\+)(?<init_as>s(erve(r)?)?|c(lient)?)").captures(s.as_str())?;
-			Some(cap.name("init_as")
-				.map(|m| String::from(m.as_str())))
-		}) else {
+		// NOTE: a sub-command, rather than a flag; so that a value, that happens to be named like one, is never taken for it.
+		let args: Vec<String> = it.collect();
+		if let Some(index) = command_at(&args) {
+			return args[index].parse()
+		}
+		// NOTE: `server` on its own, as the first argument, is what `server backup` and `server restore` are under.
+		if args
+			.first()
+			.is_some_and(|s| s == "server")
+		{
+			return Ok(Self::Server)
+		}
+		let Some(Some(m)) = args
+			.into_iter()
+			.find_map(|s| {
+				let cap = regex!(r"(-{1,2}|\+)(?<init_as>s(erve(r)?)?|c(lient)?)").captures(s.as_str())?;
+				Some(cap.name("init_as")
+					.map(|m| String::from(m.as_str())))
+			})
+		else {
 			Err(Self::Error::NotFound)?
 		};
 		m.parse()
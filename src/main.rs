@@ -1,44 +1,434 @@
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 use std::{
-	io::{BufWriter, Write, stdout},
-	sync::Arc,
+	env::var,
+	io::{ErrorKind, IsTerminal, stdin, stdout},
+	net::{Ipv4Addr, TcpStream},
+	path::{PathBuf, absolute},
 	thread,
 	time::Duration,
 };
 
-use besked::Message;
-use parking_lot::RwLock;
+use besked::{Control, Frame, HistoryQuery, Moderate, Profile, Retention, Transcript};
+use chrono::{DateTime, Utc};
+use crossbeam_channel::{never, select, unbounded};
 
 use crate::{
-	client::find_from,
-	invoke::{StartupOption, port},
-	server::host_on,
+	client::{Client, find_from},
+	export::{Export, Progress},
+	invoke::{DEFAULT_PORT, FromCallError, StartupOption, operand, operands, port, setting, setting_or},
+	server::{
+		Config,
+		backpressure::Backpressure,
+		backup::Backup,
+		control::{self, Token},
+		format, host_with,
+		limits::{Limits, Rates},
+		store::verify,
+	},
+	util::to_hex,
+	visual::Executed,
 };
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 mod client;
+mod export;
 mod invoke;
 mod server;
 mod spaces;
 mod util;
 mod visual;
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-pub type MessageLog = Arc<RwLock<Vec<Message>>>;
-///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 // [202407161311+0200] TODO(by: @OST-Gh):
 // 	move some stuffs from util into its
 // 	own module.
 fn main() -> util::Result<()> {
-	let out = BufWriter::new(stdout().lock());
+	let start = StartupOption::new();
+	if start.as_verify_log() {
+		return verify_log()
+	}
+	if start.as_restore() {
+		return restore()
+	}
 	let self_addr = util::local_v4ip()?;
-	// [202407160951+0200] NOTE(by: @OST-Gh): current test code.
 
-	let port = port()?;
-	let start = StartupOption::default();
-	if dbg![start].as_server() {
-		host_on(self_addr, port)?;
+	let port = match port() {
+		Err(FromCallError::NotFound | FromCallError::NoArguments) => DEFAULT_PORT,
+		other => other?,
+	};
+	if start.as_server() {
+		let config = Config::new();
+		let mut policy = config
+			.get_policy()
+			.clone();
+		policy.set_measure(setting_or("nick-measure", policy.get_measure())?);
+		policy.set_budget(setting_or("nick-budget", policy.get_budget())?);
+		let core = setting_or("core", config.get_core())?;
+		let heartbeat = setting_or(
+			"heartbeat",
+			config
+				.get_heartbeat()
+				.as_secs(),
+		)?;
+		let deadline = setting_or(
+			"close-deadline",
+			config
+				.get_deadline()
+				.as_secs(),
+		)?;
+		let max_frame = setting_or("max-frame", config.get_max_frame())?;
+		let handshake = setting_or(
+			"handshake",
+			config
+				.get_handshake()
+				.as_secs(),
+		)?;
+		let trickle = setting_or(
+			"trickle",
+			config
+				.get_trickle()
+				.as_secs(),
+		)?;
+		let per_address = setting_or("per-address", config.get_per_address())?;
+		let allow_list = list("allow-list")?;
+		let deny_list = list("deny-list")?;
+		let storage = setting_or("store", config.get_storage())?;
+		let data_dir = setting_or(
+			"data-dir",
+			config
+				.get_data_dir()
+				.clone(),
+		)?;
+		let history_page = setting_or("history-page", config.get_history_page())?;
+		let defaults = config.get_retention();
+		let retention = Retention::new()
+			.age(Duration::from_secs(setting_or(
+				"retain-for",
+				defaults
+					.get_age()
+					.as_secs(),
+			)?))
+			.count(setting_or("retain-count", defaults.get_count())?)
+			.bytes(setting_or("retain-bytes", defaults.get_bytes())?);
+		let compact_every = setting_or(
+			"compact-every",
+			config
+				.get_compact_every()
+				.as_secs(),
+		)?;
+		let archive = setting_or("archive", config.get_archive())?;
+		let queue_limit = setting_or("queue-limit", config.get_queue_limit())?;
+		let queue_for = setting_or(
+			"queue-for",
+			config
+				.get_queue_for()
+				.as_secs(),
+		)?;
+		let defaults = config.get_backpressure();
+		let backpressure = Backpressure::new()
+			.capacity(setting_or("outbox", defaults.get_capacity())?)
+			.overflow(setting_or("overflow", defaults.get_overflow())?)
+			.directory(setting_or(
+				"spill-dir",
+				defaults
+					.get_directory()
+					.clone(),
+			)?)
+			.limit(setting_or("spill-limit", defaults.get_limit())?);
+		let defaults = config.get_limits();
+		let limits = Limits::new()
+			.user(Rates::new(
+				setting_or("user-messages", defaults.get_user().get_messages())?,
+				setting_or("user-bytes", defaults.get_user().get_bytes())?,
+			))
+			.address(Rates::new(
+				setting_or("address-messages", defaults.get_address().get_messages())?,
+				setting_or("address-bytes", defaults.get_address().get_bytes())?,
+			))
+			.mute_after(setting_or("mute-after", defaults.get_mute_after())?)
+			.mute_for(Duration::from_secs(setting_or(
+				"mute-for",
+				defaults
+					.get_mute_for()
+					.as_secs(),
+			)?))
+			.cut_after(setting_or("cut-after", defaults.get_cut_after())?);
+		let config = config
+			.policy(policy)
+			.core(core)
+			.heartbeat(Duration::from_secs(heartbeat))
+			.deadline(Duration::from_secs(deadline))
+			.backpressure(backpressure)
+			.limits(limits)
+			.max_frame(max_frame)
+			.handshake(Duration::from_secs(handshake))
+			.trickle(Duration::from_secs(trickle))
+			.per_address(per_address)
+			.allow_list(allow_list)
+			.deny_list(deny_list)
+			.storage(storage)
+			.data_dir(data_dir)
+			.history_page(history_page)
+			.retention(retention)
+			.compact_every(Duration::from_secs(compact_every))
+			.archive(archive)
+			.queue_limit(queue_limit)
+			.queue_for(Duration::from_secs(queue_for));
+		let reason = setting_or("close-reason", String::new())?;
+
+		let server = host_with(self_addr, port, config)?;
+		let closer = server.closer();
+		// NOTE: covers `SIGTERM` too.
+		ctrlc::set_handler(move || closer.close(&reason))?;
+		let metrics = server.metrics();
+		server.wait()?;
+		eprintln!("Overflowing outboxes: {metrics}.");
+	} else if start.as_backup() {
+		// NOTE: written by the server, which may not share the working directory.
+		let path = absolute(operand("backup")?)?;
+		request(self_addr, port, Control::Backup(path
+			.to_string_lossy()
+			.into()))?;
+	} else if start.as_export() {
+		let path = absolute(operand("export")?)?;
+		// NOTE: told by the extension of the file, unless given.
+		let transcript = setting_or(
+			"format",
+			path.extension()
+				.and_then(|extension| {
+					extension
+						.to_str()?
+						.parse()
+						.ok()
+				})
+				.unwrap_or(Transcript::Text),
+		)?;
+		let since = point_in_time("since")?;
+		let until = point_in_time("until")?;
+		request(self_addr, port, Control::Export(
+			path.to_string_lossy()
+				.into(),
+			transcript,
+			since,
+			until,
+		))?;
+	} else if start.as_import() {
+		let path = absolute(operand("import")?)?;
+		request(self_addr, port, Control::Import(path
+			.to_string_lossy()
+			.into()))?;
+	} else if start.as_moderate() {
+		let moderate: Moderate = operands("moderate")?
+			.join(" ")
+			.parse()?;
+		request(self_addr, port, Control::Moderate(moderate))?;
 	} else if start.as_client() {
-		dbg![find_from(self_addr, port)];
+		let stream = match setting::<Ipv4Addr>("host") {
+			Ok(host) => TcpStream::connect((host, port))?,
+			Err(_) => {
+				let Some(stream) = find_from(self_addr, port)
+					.into_iter()
+					.next()
+				else {
+					Err(util::AllErrors::NoServer(port))?
+				};
+				stream
+			},
+		};
+		let nickname = setting::<String>("nick")
+			.or_else(|_| var("USER"))
+			.unwrap_or_else(|_| String::from(env!("CARGO_PKG_NAME")));
+
+		let profile = Profile::new()
+			.display_name(setting_or("display-name", String::new())?)
+			.status(setting_or("status", String::new())?)
+			.pronouns(setting_or("pronouns", String::new())?)
+			.timezone(setting_or("timezone", var("TZ").unwrap_or_default())?)
+			.bio(setting_or("bio", String::new())?);
+
+		let secret = setting_or("secret", String::new())?;
+
+		let mut client = Client::join(stream, nickname, secret, profile)?;
+		let away_after = setting_or::<u64>("away-after", 300)?;
+		if away_after != 0 {
+			client.away_after(Duration::from_secs(away_after));
+		}
+		let heartbeat = setting_or(
+			"heartbeat",
+			Frame::HEARTBEAT.as_secs(),
+		)?;
+		if heartbeat != 0 {
+			client.heartbeat(Duration::from_secs(heartbeat))?;
+		}
+		let backfill = setting_or::<u32>("backfill", 50)?;
+		if backfill != 0 {
+			client.history(HistoryQuery::Last(backfill))?;
+		}
+
+		if stdin().is_terminal() && stdout().is_terminal() {
+			return visual::run(client, setting_or("read-receipts", true)?)
+		}
+
+		let incoming = client
+			.incoming()
+			.clone();
+		// NOTE: read apart, so that the pages of an export are taken where it was asked for.
+		let (sender, mut lines) = unbounded();
+		thread::spawn(move || {
+			for line in stdin().lines() {
+				let Ok(_) = sender.send(line) else { return };
+			}
+		});
+
+		let mut export: Option<Export> = None;
+		let mut ended = false;
+		while client.is_connected() {
+			select! {
+				recv(incoming) -> frame => {
+					let Ok(frame) = frame else { break };
+					if let Some(active) = &mut export {
+						match active.take(&frame) {
+							Progress::Unrelated => (),
+							Progress::Pending => continue,
+							Progress::Done => {
+								if let Some(done) = export.take() {
+									eprintln!("{}", done.finish());
+								}
+								if ended {
+									break
+								}
+								continue
+							},
+						}
+					}
+					match (&frame, visual::describe(&frame)) {
+						(Frame::Error(_) | Frame::Refused(_), Some(text)) => eprintln!("{text}"),
+						(_, Some(text)) => println!("{text}"),
+						(_, None) => (),
+					}
+				},
+				recv(lines) -> line => {
+					// NOTE: the end of the input waits for an export, that is under way.
+					let Ok(line) = line else {
+						if export.is_none() {
+							break
+						}
+						lines = never();
+						ended = true;
+						continue
+					};
+					let line = line?;
+					if export.is_some() && line.starts_with("/export") {
+						eprintln!("{}", visual::EXPORTING);
+						continue
+					}
+					match visual::execute(&mut client, &line)? {
+						Executed::Notice(notice) => eprintln!("{notice}"),
+						Executed::Export(started) => export = Some(started),
+						_ => (),
+					}
+				},
+			}
+		}
+		client.close()?;
+	}
+
+	Ok(())
+}
+
+/// Recompute the hash-chain of the history, that a server keeps on disk, and
+/// report the first broken link, if there is one.
+fn verify_log() -> util::Result<()> {
+	let config = Config::new();
+	let storage = setting_or("store", config.get_storage())?;
+	let data_dir = setting_or(
+		"data-dir",
+		config
+			.get_data_dir()
+			.clone(),
+	)?;
+	let max_frame = setting_or("max-frame", config.get_max_frame())?;
+	let Some(path) = storage.path_in(&data_dir) else { Err(util::AllErrors::Unverifiable)? };
+	if !path.exists() {
+		Err(util::AllErrors::NoLog(path.clone()))?
 	}
+	let store = storage.open(&data_dir, max_frame)?;
+	let verification = verify(&*store, format::legacy(&data_dir)?)?;
+	if verification.latest == 0 {
+		println!("`{}` holds no messages.", path.display());
+		return Ok(())
+	}
+	println!(
+		"`{}` holds #{} to #{}.",
+		path.display(),
+		verification.first,
+		verification.latest
+	);
+	if verification.unchained != 0 {
+		println!("The oldest {} were kept before the history was chained.", verification.unchained);
+	}
+	if let Some(broken) = verification.broken {
+		Err(util::AllErrors::Chain(broken))?
+	}
+	println!("The chain holds, up to #{}: {}", verification.latest, to_hex(&verification.head));
+	Ok(())
+}
+
+/// Have the server, that runs on the configured data-directory, do something
+/// over its control channel, and print what it did.
+fn request(self_addr: Ipv4Addr, port: u16, control: Control) -> util::Result<()> {
+	let data_dir = setting_or(
+		"data-dir",
+		Config::new()
+			.get_data_dir()
+			.clone(),
+	)?;
+	let token = match Token::read(&data_dir) {
+		Ok(token) => token,
+		Err(error) if error.kind() == ErrorKind::NotFound => Err(util::AllErrors::NoControl(data_dir))?,
+		Err(error) => Err(error)?,
+	};
+	let stream = TcpStream::connect((setting_or("host", self_addr)?, port))?;
+	println!("{}", control::request(stream, &token, control)?);
+	Ok(())
+}
+
+/// The point in time, that the given setting names; see
+/// [`visual::point_in_time`].
+fn point_in_time(name: &str) -> util::Result<Option<DateTime<Utc>>> {
+	match setting::<String>(name) {
+		Ok(text) => Ok(Some(visual::point_in_time(&text).map_err(util::AllErrors::Time)?)),
+		Err(FromCallError::NotFound) => Ok(None),
+		Err(error) => Err(error)?,
+	}
+}
+
+/// The file of an access-list, that the given setting names; if it names
+/// any.
+fn list(name: &str) -> util::Result<Option<PathBuf>> {
+	match setting(name) {
+		Ok(path) => Ok(Some(path)),
+		Err(FromCallError::NotFound) => Ok(None),
+		Err(error) => Err(error)?,
+	}
+}
 
+/// Put a backup into the data-directory of a server, that is not running.
+fn restore() -> util::Result<()> {
+	let data_dir = setting_or(
+		"data-dir",
+		Config::new()
+			.get_data_dir()
+			.clone(),
+	)?;
+	let backup = Backup::read(&PathBuf::from(operand("restore")?))?;
+	backup.restore(&data_dir)?;
+	println!(
+		"Restored {} of version {} into `{}`.",
+		backup
+			.names()
+			.collect::<Vec<_>>()
+			.join(", "),
+		backup.get_version(),
+		data_dir.display()
+	);
 	Ok(())
 }
@@ -1,125 +1,2204 @@
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 use std::{
 	collections::HashMap,
-	io::{self, BufReader},
-	net::{Ipv4Addr, TcpListener},
+	fs::create_dir_all,
+	io::{self, BufReader, ErrorKind, Read, Write},
+	net::{IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream},
+	ops::Range,
+	path::{Path, PathBuf},
+	str::FromStr,
 	sync::{
 		Arc,
 		atomic::{AtomicBool, Ordering},
 	},
-	thread::{JoinHandle, spawn},
+	thread::{JoinHandle, park_timeout, sleep, spawn},
+	time::{Duration, Instant},
 };
 
-use besked::{Identifier, Message, errors::MessageError};
-use parking_lot::{RwLock, RwLockReadGuard};
+use besked::{
+	Control, Delivery, Frame, HistoryQuery, Identifier, Message, Moderate, Nickname, NicknamePolicy, Presence, Profile, Retention,
+	SearchQuery, State, Target, Transcript,
+	errors::{MessageError, ProfileError, ServerError, UserError},
+};
+use crossbeam_channel::{Receiver, Sender, TrySendError, bounded};
+use mio::Waker;
+use parking_lot::{Mutex, RwLock};
+use thiserror::Error;
+
+use chrono::{DateTime, Utc};
+
+use crate::{client::TYPING_INTERVAL, export};
 
-use crate::proto::MessageLog;
+use self::{
+	access::Access,
+	backpressure::{Backlog, Backpressure, Metrics, Overflow, Spill},
+	backup::{Backup, BackupError},
+	control::Token,
+	import::Imported,
+	limits::{Limiter, Limits, Verdict},
+	mailbox::{MailboxError, Mailboxes},
+	moderation::{Moderation, Subject},
+	registry::Registry,
+	search::Index,
+	store::{Storage, Store, StoreError, file::Archive, replay},
+};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+pub type Connected = Arc<RwLock<HashMap<Identifier, Handler>>>;
+pub type Peers = Arc<RwLock<HashMap<Identifier, Peer>>>;
+/// Profiles are kept after their user left, so that the authors of older
+/// messages can still be looked up.
+pub type Profiles = Arc<RwLock<HashMap<Identifier, Profile>>>;
+/// The last sequence each user has read; like [`Profiles`], kept after they
+/// left.
+pub type Cursors = Arc<RwLock<HashMap<Identifier, u64>>>;
+/// A frame in its wire-format; encoded once, no matter how many clients it
+/// is posted to.
+pub type Encoded = Arc<[u8]>;
+/// How many connections there are from each address.
+pub type Attached = Arc<Mutex<HashMap<IpAddr, u32>>>;
+/// The threads, that give a client, which was cut off, its grace, before its
+/// socket is closed.
+pub type Graces = Arc<Mutex<Vec<JoinHandle<()>>>>;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How many frames may wait for a client by default, before its outbox
+/// overflows.
+pub const OUTBOX_CAPACITY: usize = 256;
+/// How long a client, that is cut off for being too slow, gets to receive the
+/// reason.
+const GRACE: Duration = Duration::from_secs(1);
+/// How long accepting is held off, after an error, that trying again at once
+/// would only repeat; like running out of file-descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+/// How many messages of the history are sent at once by default.
+pub const HISTORY_PAGE: u32 = 100;
+/// How many of the latest messages a search looks through at most, where the
+/// index cannot narrow it down.
+pub const SEARCH_SCAN: u64 = 10_000;
+/// How many direct messages may wait for a single recipient by default.
+pub const QUEUE_LIMIT: usize = 100;
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 pub struct Server {
 	incoming_handler: JoinHandle<Result<(), MessageError>>,
+	heartbeat_handler: Option<JoinHandle<()>>,
+	compactor: Option<JoinHandle<()>>,
+
+	shared: Shared,
+	connected: Connected,
+
+	closer: Closer,
+	deadline: Duration,
+}
+
+/// How a server is run.
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Config {
+	core: Core,
+	policy: NicknamePolicy,
+	/// How often every peer is pinged; never, if zero.
+	heartbeat: Duration,
+	/// How long the outboxes get to be flushed, once the server closes.
+	deadline: Duration,
+	backpressure: Backpressure,
+	limits: Limits,
+	/// The longest body accepted from a client.
+	max_frame: u32,
 
-	message_log: MessageLog,
+	/// How long a client has to join, after connecting; forever, if zero.
+	handshake: Duration,
+	/// How long a frame may take to come in, once it started; forever, if
+	/// zero.
+	trickle: Duration,
+	/// How many connections there may be from the same address at once; any
+	/// amount, if zero.
+	per_address: u32,
+	/// The file of ranges, that connections are allowed from; any, if none.
+	allow_list: Option<PathBuf>,
+	/// The file of ranges, that connections are denied from.
+	deny_list: Option<PathBuf>,
 
-	connected: Arc<RwLock<HashMap<Identifier, JoinHandle<Result<(), MessageError>>>>>,
+	storage: Storage,
+	/// Where the store keeps its files, if it keeps any.
+	data_dir: PathBuf,
+	/// How many messages of the history are sent at once, at most.
+	history_page: u32,
+	retention: Retention,
+	/// How often whatever expired is dropped; never, if zero.
+	compact_every: Duration,
+	/// Whether expired messages are archived, rather than dropped.
+	archive: bool,
 
+	/// How many direct messages may wait for a single recipient; any amount,
+	/// if zero.
+	queue_limit: usize,
+	/// How long a direct message may wait for its recipient; forever, if
+	/// zero.
+	queue_for: Duration,
+}
+
+/// Asks a server to close; may be handed to any thread.
+#[derive(Clone)]
+pub struct Closer {
 	quit: Arc<AtomicBool>,
+	reason: Arc<Mutex<String>>,
+	alarm: Alarm,
+}
+
+/// How a server, that waits for something to happen, is woken up.
+#[derive(Clone)]
+enum Alarm {
+	/// By connecting to its listener.
+	Listener(SocketAddr),
+	Reactor(Arc<Waker>),
+}
+
+/// A connection, as far as the threaded core is concerned.
+pub struct Handler {
+	reader: JoinHandle<Result<(), MessageError>>,
+	socket: Arc<TcpStream>,
+}
+
+/// Forgets the [`Handler`] of a connection, once its reader ends.
+struct Forget {
+	id: Identifier,
+	connected: Connected,
 }
+
+/// Everything, that the connections of a server share.
+#[derive(Clone)]
+pub struct Shared {
+	store: Store,
+	index: Arc<Index>,
+	history_page: u32,
+	retention: Retention,
+	archive: Option<Arc<Archive>>,
+	registry: Arc<Registry>,
+	mailboxes: Arc<Mailboxes>,
+	moderation: Arc<Moderation>,
+	/// What [`Control`]-frames are taken by; none, unless the server keeps
+	/// its history on disk.
+	///
+	/// [`Control`]: Frame::Control
+	token: Option<Arc<Token>>,
+	/// The latest sequence kept before the history was chained.
+	legacy: u64,
+
+	peers: Peers,
+	profiles: Profiles,
+	cursors: Cursors,
+
+	policy: Arc<NicknamePolicy>,
+	backpressure: Arc<Backpressure>,
+	metrics: Arc<Metrics>,
+	limiter: Arc<Limiter>,
+	max_frame: u32,
+
+	handshake: Duration,
+	trickle: Duration,
+	per_address: u32,
+	attached: Attached,
+	access: Arc<Access>,
+	graces: Graces,
+	/// Held to change the history, the registry, the mailboxes or the
+	/// moderation, and taken whole by a backup; so that it sees all of them
+	/// at one point.
+	barrier: Arc<RwLock<()>>,
+}
+
+/// Counts towards the connections from an address, for as long as it lives.
+pub struct Attachment {
+	address: IpAddr,
+	attached: Attached,
+	/// What the server's own host is told, and why, if it tries to join
+	/// from an address, that the access-lists or the bans refuse; its
+	/// control channel is all it reaches.
+	refusal: Option<(ServerError, Box<str>)>,
+}
+
+/// Reads from a socket, but gives up at a deadline; either set up front, or
+/// once something started to come in.
+struct Timed<'a> {
+	reader: &'a mut BufReader<TcpStream>,
+	/// How long to wait, while there is no deadline.
+	idle: Option<Duration>,
+	/// How long the rest may take, once something came in.
+	trickle: Duration,
+	until: Option<Instant>,
+}
+
+/// Everything the server knows about a client, that has joined.
+pub struct Peer {
+	presence: Presence,
+	address: IpAddr,
+	outbox: Outbox,
+	/// When it was last told to be typing.
+	typed: Option<Instant>,
+}
+
+/// The sending half of a connection.
+///
+/// Frames are queued, and written by whoever owns the connection, so that no
+/// client ever waits on another.
+#[derive(Clone)]
+pub struct Outbox {
+	queue: Sender<Encoded>,
+	/// The receiving end of the queue, to make room in it.
+	backlog: Receiver<Encoded>,
+	spill: Option<Arc<Mutex<Spill>>>,
+	overflow: Overflow,
+	metrics: Arc<Metrics>,
+	/// Whether the client has been cut off already.
+	cut: Arc<AtomicBool>,
+	owner: Owner,
+}
+
+/// Who writes the frames of an [`Outbox`].
+#[derive(Clone)]
+enum Owner {
+	/// A thread of the connection's own, which blocks on the queue; and where
+	/// to leave the thread, that closes the socket, once it is cut off.
+	Thread(Arc<TcpStream>, Graces),
+	/// An event-loop, that has to be woken up.
+	Reactor(reactor::Handle),
+}
+
+/// The ways, in which a server can be run.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+pub enum Core {
+	/// A reader and a writer thread for every connection.
+	#[default]
+	Threads,
+	/// A single event-loop for every connection.
+	Events,
+}
+
+#[derive(Debug, Error)]
+#[error("`{0}` names no server-core; expected `threads` or `events`.")]
+pub struct CoreFromStrError(pub Box<str>);
+
+#[derive(Debug, Error)]
+pub enum HostError {
+	#[error(transparent)]
+	IO(#[from] io::Error),
+	#[error(transparent)]
+	Store(#[from] StoreError),
+	#[error(transparent)]
+	Mailbox(#[from] MailboxError),
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+pub mod access;
+pub mod backpressure;
+pub mod backup;
+pub mod control;
+pub mod format;
+pub mod import;
+pub mod limits;
+pub mod mailbox;
+pub mod moderation;
+pub mod reactor;
+pub mod registry;
+pub mod search;
+pub mod store;
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-pub fn host_on(addr: Ipv4Addr, port: impl Into<u16>) -> io::Result<Server> {
+/// Serve on the given address and port, using the configured core.
+#[inline(always)]
+pub fn host_with(addr: Ipv4Addr, port: impl Into<u16>, config: Config) -> Result<Server, HostError> {
+	match config.core {
+		Core::Threads => host_on(addr, port, config),
+		Core::Events => reactor::host_on(addr, port, config),
+	}
+}
+
+/// Serve on the given address and port, with a reader and a writer thread for
+/// every connection.
+///
+/// Every peer is pinged once per heartbeat, and dropped after
+/// [`Frame::MISSED_HEARTBEATS`] of them went by without a word.
+pub fn host_on(addr: Ipv4Addr, port: impl Into<u16>, config: Config) -> Result<Server, HostError> {
 	let incoming_receiver = TcpListener::bind((addr, port.into()))?;
 
-	let connected = Arc::new(RwLock::new(HashMap::with_capacity(2)));
-	let message_log = MessageLog::new(RwLock::from(Vec::new()));
-	let quit = Arc::new(AtomicBool::new(false));
+	let connected = Connected::new(RwLock::new(HashMap::with_capacity(2)));
+	let shared = Shared::new(&config)?;
+	let closer = Closer::new(Alarm::Listener(incoming_receiver.local_addr()?));
+	let heartbeat = config.heartbeat;
 
-	let message_log_2 = message_log.clone();
+	let shared_2 = shared.clone();
 	let connected_2 = connected.clone();
-	let quit_2 = quit.clone();
+	let closer_2 = closer.clone();
 
 	let incoming_handler = spawn(move || -> Result<(), MessageError> {
 		loop {
-			if quit_2.load(Ordering::Acquire) {
+			let accepted = incoming_receiver.accept();
+			// NOTE: the connection, that woke us up, is of no interest.
+			if closer_2.is_closing() {
 				break
 			}
-
-			let (mut stream, _addr) = incoming_receiver.accept()?;
+			let (mut stream, addr) = match accepted {
+				Ok(accepted) => accepted,
+				Err(error) if is_transient(&error) => continue,
+				Err(error) => {
+					eprintln!("A connection could not be accepted: {error}");
+					sleep(ACCEPT_BACKOFF);
+					continue
+				},
+			};
+			let local = stream
+				.local_addr()
+				.map_or(addr.ip(), |local| local.ip());
+			let attachment = match shared_2.attach(addr.ip(), local) {
+				Ok(attachment) => attachment,
+				Err(error) => {
+					shared_2.refuse(&mut stream, error);
+					continue
+				},
+			};
 			let id = Identifier::default();
 
-			Message::with_identifier(id).send(&mut stream);
+			let _ = connect(stream, id, attachment, shared_2.clone(), heartbeat, &connected_2);
+		}
+		Ok(())
+	});
+
+	let heartbeat_handler = beat(heartbeat, &shared, &closer);
+	let compactor = compact(&config, &shared, &closer);
+
+	Ok(Server {
+		incoming_handler,
+		heartbeat_handler,
+		compactor,
+		shared,
+		connected,
+		closer,
+		deadline: config.deadline,
+	})
+}
+
+/// Whether an error of accepting concerns only the connection, that was to be
+/// accepted; so that the next one can be, right away.
+fn is_transient(error: &io::Error) -> bool {
+	matches!(
+		error.kind(),
+		ErrorKind::Interrupted | ErrorKind::ConnectionAborted | ErrorKind::ConnectionReset | ErrorKind::TimedOut
+	)
+}
+
+/// Ping every peer once per `heartbeat`, unless it is zero.
+fn beat(heartbeat: Duration, shared: &Shared, closer: &Closer) -> Option<JoinHandle<()>> {
+	if heartbeat.is_zero() {
+		return None
+	}
+	let shared = shared.clone();
+	let closer = closer.clone();
+	Some(spawn(move || {
+		loop {
+			// NOTE: unparked by the server, when it closes.
+			park_timeout(heartbeat);
+			if closer.is_closing() {
+				break
+			}
+			shared.broadcast(&Frame::Ping, None);
+		}
+	}))
+}
 
-			let mut stream = BufReader::new(stream);
+/// Drop whatever expired once per configured interval, unless it is zero, or
+/// nothing ever expires.
+fn compact(config: &Config, shared: &Shared, closer: &Closer) -> Option<JoinHandle<()>> {
+	let every = config.compact_every;
+	if every.is_zero()
+		|| config
+			.retention
+			.is_forever()
+	{
+		return None
+	}
+	let shared = shared.clone();
+	let closer = closer.clone();
+	Some(spawn(move || {
+		loop {
+			if let Err(error) = shared.compact() {
+				eprintln!("The history could not be compacted: {error}");
+			}
+			park_timeout(every);
+			if closer.is_closing() {
+				break
+			}
+		}
+	}))
+}
 
-			let message_log_3 = message_log_2.clone();
+/// Give an accepted stream a reader and a writer, and count them among the
+/// connected, until the reader ends.
+///
+/// The reader outlives the writer, which lives as long as the outbox.
+fn connect(
+	stream: TcpStream,
+	id: Identifier,
+	attachment: Attachment,
+	shared: Shared,
+	heartbeat: Duration,
+	connected: &Connected,
+) -> io::Result<()> {
+	let accepted = Instant::now();
+	let timeout = (!heartbeat.is_zero()).then(|| heartbeat * Frame::MISSED_HEARTBEATS);
+	stream.set_read_timeout(timeout)?;
+	stream.set_write_timeout(timeout)?;
 
-			let handler = spawn(move || {
-				let mut message_buffer = Vec::with_capacity(Message::MIN_LENGTH);
-				let mut written = Vec::from([0]);
-				loop {
-					let maybe_lock = message_log_3.try_read();
+	let mut sink = stream.try_clone()?;
+	let socket = Arc::new(stream.try_clone()?);
+	let (outbox, backlog) = shared.open(
+		id,
+		Owner::Thread(
+			socket.clone(),
+			shared
+				.graces
+				.clone(),
+		),
+	);
 
-					let len = |log: &RwLockReadGuard<'_, Vec<Message>>| -> bool {
-						let iter = written.iter();
-						iter.sum::<usize>() > log.len()
-					};
+	let writer = spawn(move || -> Result<(), MessageError> {
+		let outcome = (|| -> Result<(), MessageError> {
+			while let Some(bytes) = backlog.next() {
+				sink.write_all(&bytes)?;
+			}
+			Ok(())
+		})();
+		// NOTE: a client, that cannot be written to, is not listened to either.
+		let _ = sink.shutdown(Shutdown::Both);
+		outcome
+	});
 
-					if maybe_lock
-						.as_ref()
-						.map_or(false, len)
-					{
-						let log = unsafe { maybe_lock.unwrap_unchecked() };
-						let mut log = log.iter();
-						for i in written.iter() {
-							log.nth(*i);
-						}
-						for message in log {
-							let Ok(_) = message.send(stream.get_mut()) else {
-								written.push(0);
-								break
-							};
-							let maybe_n = written.last_mut();
-							*unsafe { maybe_n.unwrap_unchecked() } += 1;
+	// NOTE: held on to, until the handler is in; so that a reader, which ends right away, cannot forget it before.
+	let mut handlers = connected.write();
+	let forget = Forget {
+		id,
+		connected: connected.clone(),
+	};
+	let reader = spawn(move || {
+		// NOTE: taken as a whole, so that the connection counts for as long as it is read.
+		let attachment = attachment;
+		let _forget = forget;
+		let mut stream = BufReader::new(stream);
+		let outcome = (|| -> Result<(), MessageError> {
+			outbox.post(&Frame::Policy(NicknamePolicy::clone(&shared.policy)))?;
+			let mut recv = |until| {
+				let mut timed = Timed {
+					reader: &mut stream,
+					idle: timeout,
+					trickle: shared.trickle,
+					until,
+				};
+				Frame::recv_limited(&mut timed, shared.max_frame).inspect_err(|error| shared.report(error, &outbox))
+			};
+			let handshake = (!shared
+				.handshake
+				.is_zero())
+			.then(|| accepted + shared.handshake);
+			let nickname = shared.admit(id, &attachment, recv(handshake)?, &outbox)?;
+			shared.welcome(id, &nickname, &outbox)?;
+			loop {
+				shared.handle(id, &nickname, recv(None)?, &outbox)?;
+			}
+		})();
+		shared.leave(id);
+		// NOTE: with the last sender gone, the writer drains its queue and ends.
+		drop(outbox);
+		let _ = writer.join();
+		outcome
+	});
+	handlers.insert(id, Handler { reader, socket });
+	Ok(())
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Shared {
+	/// Set up what the connections share, with the history, the registry and
+	/// the mailboxes read back from the data-directory.
+	pub fn new(config: &Config) -> Result<Self, HostError> {
+		let store = config
+			.storage
+			.open(&config.data_dir, config.max_frame)?;
+		let index = Index::new();
+		replay(&*store, |message| index.add(message))?;
+		let archive = if config.archive {
+			create_dir_all(&config.data_dir)?;
+			Some(Arc::new(Archive::open(config
+				.data_dir
+				.join(Archive::NAME))?))
+		} else {
+			None
+		};
+		let within = |name| {
+			if let Storage::Memory = config.storage {
+				None
+			} else {
+				Some(config
+					.data_dir
+					.join(name))
+			}
+		};
+		let registry = Registry::open(within(Registry::NAME))?;
+		let mailboxes = Mailboxes::open(within(Mailboxes::NAME), config.queue_limit, config.queue_for)?;
+		let moderation = Moderation::open(within(Moderation::OPERATORS), within(Moderation::BANS))?;
+		let access = Access::open(
+			config
+				.get_allow_list()
+				.cloned(),
+			config
+				.get_deny_list()
+				.cloned(),
+		)?;
+		let (token, legacy) = if let Storage::Memory = config.storage {
+			(None, 0)
+		} else {
+			(Some(Arc::new(Token::issue(&config.data_dir)?)), format::legacy(&config.data_dir)?)
+		};
+		// NOTE: a page, and its end, should never overflow an outbox on their own.
+		let history_page = config
+			.history_page
+			.clamp(
+				1,
+				(config
+					.backpressure
+					.get_capacity()
+					/ 2)
+				.max(1) as u32,
+			);
+		Ok(Self {
+			store,
+			index: Arc::new(index),
+			history_page,
+			retention: config.retention,
+			archive,
+			registry: Arc::new(registry),
+			legacy,
+			mailboxes: Arc::new(mailboxes),
+			moderation: Arc::new(moderation),
+			token,
+			peers: Peers::default(),
+			profiles: Profiles::default(),
+			cursors: Cursors::default(),
+			policy: Arc::new(config
+				.policy
+				.clone()),
+			backpressure: Arc::new(config
+				.backpressure
+				.clone()),
+			metrics: Arc::default(),
+			limiter: Arc::new(Limiter::new(config.limits)),
+			max_frame: config.max_frame,
+			handshake: config.handshake,
+			trickle: config.trickle,
+			per_address: config.per_address,
+			attached: Attached::default(),
+			access: Arc::new(access),
+			graces: Graces::default(),
+			barrier: Arc::default(),
+		})
+	}
+
+	/// Count a new connection from the given address, that came in at the
+	/// local one, unless the access-lists refuse it, it is banned, or there are
+	/// too many from it already.
+	pub fn attach(&self, address: IpAddr, local: IpAddr) -> Result<Attachment, ServerError> {
+		let refusal = match self.screen(address) {
+			Ok(()) => None,
+			// NOTE: the server's own host keeps its control channel in reach; it may not join, no more than any other.
+			Err(refusal) if address == local || address.is_loopback() => Some(refusal),
+			Err((error, why)) => {
+				eprintln!("A connection from `{address}` was refused, {why}.");
+				Err(error)?
+			},
+		};
+		let mut attached = self
+			.attached
+			.lock();
+		let count = attached
+			.entry(address)
+			.or_default();
+		if self.per_address != 0 && *count >= self.per_address {
+			Err(ServerError::Crowded(self.per_address))?
+		}
+		*count += 1;
+		Ok(Attachment {
+			address,
+			attached: self
+				.attached
+				.clone(),
+			refusal,
+		})
+	}
+
+	/// Whether the access-lists and the bans let a connection from the given
+	/// address in; if not, what it is told, and why, as it is logged.
+	fn screen(&self, address: IpAddr) -> Result<(), (ServerError, Box<str>)> {
+		if let Err(refusal) = self
+			.access
+			.admits(address)
+		{
+			Err((ServerError::Denied, format!("as {refusal}").into()))?
+		}
+		if let Some(reason) = self
+			.moderation
+			.banned(&Subject::Address(address))
+		{
+			Err((ServerError::Banned(reason), "as it is banned".into()))?
+		}
+		Ok(())
+	}
+
+	/// Tell a connection, that could not be attached, why; as far as it can
+	/// be told without waiting.
+	pub fn refuse(&self, stream: &mut impl Write, error: ServerError) { let _ = Frame::Error(error).send(stream); }
+
+	#[inline(always)]
+	pub const fn get_max_frame(&self) -> u32 { self.max_frame }
+	#[inline(always)]
+	pub const fn get_handshake(&self) -> Duration { self.handshake }
+	#[inline(always)]
+	pub const fn get_trickle(&self) -> Duration { self.trickle }
+
+	/// Give a new connection an outbox, whose frames its owner takes from the
+	/// returned backlog.
+	fn open(&self, id: Identifier, owner: Owner) -> (Outbox, Backlog) {
+		let (queue, outgoing) = bounded(
+			self.backpressure
+				.get_capacity(),
+		);
+		let spill = self
+			.backpressure
+			.spill(id);
+		let outbox = Outbox {
+			queue,
+			backlog: outgoing.clone(),
+			spill: spill.clone(),
+			overflow: self
+				.backpressure
+				.get_overflow(),
+			metrics: self
+				.metrics
+				.clone(),
+			cut: Arc::default(),
+			owner,
+		};
+		(outbox, Backlog::new(outgoing, spill))
+	}
+
+	/// Judge the nickname, secret and profile of a client's first frame,
+	/// which has to be a [`Join`]; or act upon a [`Control`], after which the
+	/// connection ends.
+	///
+	/// [`Join`]: Frame::Join
+	/// [`Control`]: Frame::Control
+	pub fn admit(&self, id: Identifier, attachment: &Attachment, first: Frame, outbox: &Outbox) -> Result<Nickname, MessageError> {
+		let address = attachment.get_address();
+		let (requested, profile, secret) = match first {
+			Frame::Join(..) if let Some((error, why)) = &attachment.refusal => {
+				eprintln!("A connection from `{address}` was refused to join, {why}.");
+				outbox.post(&Frame::Error(error.clone()))?;
+				Err(error.clone())?
+			},
+			Frame::Join(requested, profile, secret) => (requested, profile, secret),
+			Frame::Control(token, control) => {
+				outbox.post(&self.control(&token, control, address))?;
+				Err(MessageError::ConnectionClosed)?
+			},
+			other => {
+				outbox.post(&Frame::Error(ServerError::Unexpected))?;
+				Err(MessageError::Unexpected(other.kind()))?
+			},
+		};
+		if let Err(error) = profile.validate() {
+			outbox.post(&Frame::Error(ServerError::Profile(error
+				.to_string()
+				.into())))?;
+			Err(MessageError::User(UserError::Profile(error)))?
+		}
+		// NOTE: deriving the hash of a secret takes its time; so it is checked before the peers are locked.
+		if let Ok(nickname) = self
+			.policy
+			.apply(&requested)
+			&& !self.changing(|| {
+				self.registry
+					.admits(&nickname, &secret)
+			}) {
+			outbox.post(&Frame::Error(ServerError::Secret))?;
+			Err(ServerError::Secret)?
+		}
+
+		// NOTE:
+		// 	Checking and claiming happen under the same lock, so that two clients
+		// 	cannot claim confusable nicknames at the same time.
+		let mut peers = self
+			.peers
+			.write();
+		match self
+			.policy
+			.admit(
+				&requested,
+				peers
+					.values()
+					.map(|peer| peer.presence.get_nickname()),
+			) {
+			Ok(nickname)
+				if let Some(reason) = self
+					.moderation
+					.banned(&Subject::nickname(&nickname)) =>
+			{
+				drop(peers);
+				eprintln!("`{nickname}` from `{address}` was refused for being banned.");
+				outbox.post(&Frame::Error(ServerError::Banned(reason.clone())))?;
+				Err(ServerError::Banned(reason))?
+			},
+			Ok(nickname) => {
+				peers.insert(id, Peer {
+					presence: Presence::new(id, nickname.clone(), State::Online),
+					address,
+					outbox: outbox.clone(),
+					typed: None,
+				});
+				self.profiles
+					.write()
+					.insert(id, profile);
+				self.limiter
+					.admit(id, address);
+				Ok(nickname)
+			},
+			Err(error) => {
+				drop(peers);
+				outbox.post(&Frame::Error(ServerError::Nickname(error
+					.to_string()
+					.into())))?;
+				Err(MessageError::User(UserError::Policy(error)))
+			},
+		}
+	}
+
+	/// Greet a freshly admitted client with everything it should know, and
+	/// tell everyone else about it.
+	pub fn welcome(&self, id: Identifier, nickname: &Nickname, outbox: &Outbox) -> Result<(), MessageError> {
+		outbox.post(&Frame::Welcome(id, nickname.clone(), self.max_frame))?;
+		outbox.post(&Frame::Retention(self.retention))?;
+
+		let roster = self
+			.peers
+			.read()
+			.values()
+			.map(|peer| peer.presence.clone())
+			.collect();
+		outbox.post(&Frame::Roster(roster))?;
+		let cursors = self
+			.cursors
+			.read()
+			.iter()
+			.map(|(other, sequence)| Frame::Read(*other, *sequence))
+			.collect::<Vec<_>>();
+		for cursor in cursors {
+			outbox.post(&cursor)?;
+		}
+
+		self.broadcast(&Frame::Presence(Presence::new(id, nickname.clone(), State::Online)), None);
+
+		// NOTE: only a registered nickname has a mailbox, and only its secret gets one in.
+		if self
+			.registry
+			.is_registered(nickname)
+		{
+			let queued = self
+				.changing(|| self
+					.mailboxes
+					.take(nickname))
+				.unwrap_or_else(|error| {
+					eprintln!("The mailbox of `{nickname}` could not be read: {error}");
+					Vec::new()
+				});
+			for message in queued {
+				let author = message
+					.get_nickname()
+					.clone();
+				outbox.post(&Frame::Direct(nickname.clone(), message))?;
+				if let Some(sender) = self.outbox_of(&author) {
+					let _ = sender.post(&Frame::Delivery(nickname.clone(), Delivery::Delivered));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// The outbox of whoever is connected under the given nickname; regardless
+	/// of case.
+	fn outbox_of(&self, nickname: &Nickname) -> Option<Outbox> {
+		let key = registry::key(nickname);
+		self.peers
+			.read()
+			.values()
+			.find(|peer| registry::key(peer.presence.get_nickname()) == key)
+			.map(|peer| {
+				peer.outbox
+					.clone()
+			})
+	}
+
+	/// How long the client is left to be muted by an operator, and why; by its
+	/// nickname, or its address.
+	fn silenced(&self, id: Identifier) -> Option<(Duration, Box<str>)> {
+		let subjects = self
+			.peers
+			.read()
+			.get(&id)
+			.map(|peer| [Subject::nickname(peer.presence.get_nickname()), Subject::Address(peer.address)])?;
+		self.moderation
+			.muted(&subjects)
+	}
+
+	/// Let a client send something as long as it sticks to its limits; tells
+	/// whether it did, and otherwise tells the client why, in the frame that
+	/// `refusal` makes of it.
+	fn throttle(
+		&self,
+		id: Identifier,
+		length: usize,
+		outbox: &Outbox,
+		refusal: fn(ServerError) -> Frame,
+	) -> Result<bool, MessageError> {
+		// NOTE: a mute by an operator is checked first, so that it does not count as flooding.
+		if let Some((left, reason)) = self.silenced(id) {
+			outbox.post(&refusal(ServerError::Silenced(
+				left.as_secs_f64()
+					.ceil() as u32,
+				reason,
+			)))?;
+			return Ok(false)
+		}
+		let error = match self
+			.limiter
+			.check(id, length)
+		{
+			Verdict::Pass => return Ok(true),
+			Verdict::SlowDown(wait) => ServerError::SlowDown(wait.as_millis().max(1) as u32),
+			Verdict::Muted(left) => ServerError::Muted(left
+				.as_secs_f64()
+				.ceil() as u32),
+			Verdict::Cut => {
+				outbox.post(&refusal(ServerError::Flooding))?;
+				Err(ServerError::Flooding)?
+			},
+		};
+		outbox.post(&refusal(error))?;
+		Ok(false)
+	}
+
+	/// Act upon a frame from an admitted client.
+	pub fn handle(&self, id: Identifier, nickname: &Nickname, frame: Frame, outbox: &Outbox) -> Result<(), MessageError> {
+		match frame {
+			// NOTE:
+			// 	The sender is taken from the server's own records, so that no client
+			// 	is able to get around the nickname-policy.
+			Frame::Message(message) => {
+				if !self.throttle(
+					id,
+					message
+						.get_content()
+						.len(),
+					outbox,
+					Frame::Refused,
+				)? {
+					return Ok(())
+				}
+				// NOTE: stamped by the server, whose clock every message is kept by.
+				let appended = self.changing(|| {
+					self.store
+						.append(
+							message
+								.identifier(id)
+								.nickname(nickname.clone())
+								.utc(Utc::now()),
+						)
+				});
+				let message = match appended {
+					Ok(message) => message,
+					Err(error) => {
+						eprintln!("A message of `{id}` could not be kept: {error}");
+						return outbox.post(&Frame::Refused(ServerError::Unsaved))
+					},
+				};
+				self.index
+					.add(&message);
+				outbox.post(&Frame::Ack(message.get_sequence()))?;
+				self.broadcast(&Frame::Message(message), Some(id));
+			},
+			Frame::Read(_, sequence) => {
+				let length = self
+					.store
+					.latest()
+					.unwrap_or_default();
+				let moved = {
+					let mut cursors = self
+						.cursors
+						.write();
+					let cursor = cursors
+						.entry(id)
+						.or_default();
+					let moved = *cursor < sequence && sequence <= length;
+					if moved {
+						*cursor = sequence;
+					}
+					moved
+				};
+				if moved {
+					self.broadcast(&Frame::Read(id, sequence), None);
+				}
+			},
+			Frame::Profile(_, profile) => {
+				let length = [
+					profile.get_display_name(),
+					profile.get_status(),
+					profile.get_pronouns(),
+					profile.get_timezone(),
+					profile.get_bio(),
+				]
+				.map(str::len)
+				.into_iter()
+				.sum();
+				if !self.throttle(id, length, outbox, Frame::Error)? {
+					return Ok(())
+				}
+				match profile.validate() {
+					Ok(()) => {
+						self.profiles
+							.write()
+							.insert(id, profile.clone());
+						outbox.post(&Frame::Profile(id, profile))?
+					},
+					Err(error) => outbox.post(&Frame::Error(ServerError::Profile(error
+						.to_string()
+						.into())))?,
+				}
+			},
+			Frame::ProfileQuery(other) => {
+				let answer = match self
+					.profiles
+					.read()
+					.get(&other)
+				{
+					Some(profile) => Frame::Profile(other, profile.clone()),
+					None => Frame::Error(ServerError::Unknown(other)),
+				};
+				outbox.post(&answer)?
+			},
+			Frame::Presence(presence) => {
+				if !self.throttle(
+					id,
+					presence
+						.get_status()
+						.len(),
+					outbox,
+					Frame::Error,
+				)? {
+					return Ok(())
+				}
+				let length = presence
+					.get_status()
+					.chars()
+					.count();
+				if length > Profile::MAX_STATUS_LENGTH {
+					let error = ProfileError::TooLong("status", length, Profile::MAX_STATUS_LENGTH);
+					return outbox.post(&Frame::Error(ServerError::Profile(error
+						.to_string()
+						.into())))
+				}
+				let updated = self
+					.peers
+					.write()
+					.get_mut(&id)
+					.map(|peer| {
+						peer.presence
+							.set_state(presence.get_state());
+						peer.presence
+							.set_status(presence.get_status());
+						peer.presence
+							.clone()
+					});
+				if let Some(updated) = updated {
+					self.broadcast(&Frame::Presence(updated), None);
+				}
+			},
+			Frame::Ping => outbox.post(&Frame::Pong)?,
+			Frame::Pong => (),
+			// NOTE: a client tells no more often than its interval; whatever comes sooner is dropped, rather than relayed to everyone.
+			Frame::Typing(..) => {
+				let due = self
+					.peers
+					.write()
+					.get_mut(&id)
+					.is_some_and(|peer| {
+						let now = Instant::now();
+						let due = peer
+							.typed
+							.is_none_or(|typed| now.duration_since(typed) >= TYPING_INTERVAL);
+						if due {
+							peer.typed = Some(now);
 						}
+						due
+					});
+				if due {
+					self.broadcast(&Frame::Typing(id, nickname.clone()), Some(id));
+				}
+			},
+			Frame::History(query) => match self.page(query) {
+				Ok((page, next)) => {
+					let count = page.len() as u32;
+					for message in page {
+						outbox.post(&Frame::Backfill(message))?;
 					}
+					outbox.post(&Frame::Page(count, next))?
+				},
+				Err(error) => {
+					eprintln!("The history could not be read for `{id}`: {error}");
+					outbox.post(&Frame::Error(ServerError::History))?
+				},
+			},
+			Frame::Direct(to, message) => {
+				if !self.throttle(
+					id,
+					message
+						.get_content()
+						.len(),
+					outbox,
+					Frame::Error,
+				)? {
+					return Ok(())
+				}
+				let message = message
+					.identifier(id)
+					.nickname(nickname.clone())
+					.utc(Utc::now());
+				if let Some(recipient) = self.outbox_of(&to) {
+					let _ = recipient.post(&Frame::Direct(to.clone(), message));
+					return outbox.post(&Frame::Delivery(to, Delivery::Delivered))
+				}
+				if !self
+					.registry
+					.is_registered(&to)
+				{
+					return outbox.post(&Frame::Error(ServerError::Unregistered(to
+						.as_str()
+						.into())))
+				}
+				match self.changing(|| {
+					self.mailboxes
+						.push(&to, message)
+				}) {
+					Ok(()) => outbox.post(&Frame::Delivery(to, Delivery::Queued))?,
+					Err(MailboxError::Full) => outbox.post(&Frame::Error(ServerError::Inbox(to
+						.as_str()
+						.into())))?,
+					Err(error) => {
+						eprintln!("A direct message of `{id}` could not be kept: {error}");
+						outbox.post(&Frame::Error(ServerError::Unsaved))?
+					},
+				}
+			},
+			Frame::Register(secret) => {
+				if secret.is_empty() {
+					return outbox.post(&Frame::Error(ServerError::Registry))
+				}
+				match self.changing(|| {
+					self.registry
+						.register(nickname, &secret)
+				}) {
+					Ok(()) => outbox.post(&Frame::Registered(nickname.clone()))?,
+					Err(error) => {
+						eprintln!("`{nickname}` could not be registered: {error}");
+						outbox.post(&Frame::Error(ServerError::Registry))?
+					},
+				}
+			},
+			Frame::Search(query) => self.find(id, &query, outbox)?,
+			Frame::HeadQuery => match self
+				.store
+				.head()
+			{
+				Ok((latest, head)) => outbox.post(&Frame::Head(latest, head))?,
+				Err(error) => {
+					eprintln!("The head of the history could not be read for `{id}`: {error}");
+					outbox.post(&Frame::Error(ServerError::History))?
+				},
+			},
+			// NOTE: only a registered nickname is known to be who it claims to be.
+			Frame::Moderate(moderate) => {
+				let answer = if !self
+					.registry
+					.is_registered(nickname)
+					|| !self
+						.moderation
+						.is_operator(nickname)
+				{
+					eprintln!("`{nickname}` was refused to {}.", moderate.as_word());
+					Frame::Error(ServerError::Operator)
+				} else if let Moderate::Grant(_) | Moderate::Revoke(_) = moderate {
+					Frame::Error(ServerError::Moderation("operators are granted and revoked through the control channel alone.".into()))
+				} else {
+					self.moderate(moderate, &format!("{nickname:#}"))
+				};
+				outbox.post(&answer)?
+			},
+			_ => outbox.post(&Frame::Error(ServerError::Unexpected))?,
+		}
+		Ok(())
+	}
 
-					match Message::recv_buf(&mut stream, &mut message_buffer) {
-						Ok(message) => message_log_3
-							.write()
-							.push(message),
-						Err(MessageError::TooShort(_)) => continue,
-						Err(other) => Err(other)?,
-					};
+	/// Answer a search with its hits, and how many there are in all; as long
+	/// as the client sticks to its limits.
+	fn find(&self, id: Identifier, query: &SearchQuery, outbox: &Outbox) -> Result<(), MessageError> {
+		if !self.throttle(
+			id,
+			query
+				.get_text()
+				.len(),
+			outbox,
+			Frame::Error,
+		)? {
+			return Ok(())
+		}
+		match self.search(query) {
+			Ok((hits, total)) => {
+				let sent = hits.len() as u32;
+				for message in hits {
+					outbox.post(&Frame::Hit(message))?;
+				}
+				outbox.post(&Frame::Found(sent, total))
+			},
+			Err(error) => {
+				eprintln!("The history could not be searched for `{id}`: {error}");
+				outbox.post(&Frame::Error(ServerError::Search))
+			},
+		}
+	}
+
+	/// Do what a [`Control`]-frame asks, if it holds the token; tells how it
+	/// went.
+	///
+	/// [`Control`]: Frame::Control
+	fn control(&self, token: &str, control: Control, address: IpAddr) -> Frame {
+		if !self
+			.token
+			.as_ref()
+			.is_some_and(|own| own.admits(token))
+		{
+			eprintln!("A control from `{address}` was refused.");
+			return Frame::Error(ServerError::Token)
+		}
+		match control {
+			Control::Backup(path) => match self.backup(Path::new(&*path)) {
+				Ok(backup) => Frame::Done(
+					format!(
+						"Backed up {} to `{path}`.",
+						backup
+							.names()
+							.collect::<Vec<_>>()
+							.join(", ")
+					)
+					.into(),
+				),
+				Err(error) => {
+					eprintln!("The backup to `{path}` failed: {error}");
+					Frame::Error(ServerError::Backup(error
+						.to_string()
+						.into()))
+				},
+			},
+			Control::Export(path, transcript, since, until) => {
+				match self.export(Path::new(&*path), transcript, since .. until) {
+					Ok(count) => Frame::Done(format!("Exported {count} messages to `{path}`.").into()),
+					Err(error) => {
+						eprintln!("The export to `{path}` failed: {error}");
+						Frame::Error(ServerError::Export(error
+							.to_string()
+							.into()))
+					},
 				}
-			});
-			connected_2
-				.write()
-				.insert(id, handler);
+			},
+			Control::Import(path) => match import::read(Path::new(&*path))
+				.and_then(|messages| self.changing(|| import::import(&*self.store, &self.index, messages)))
+			{
+				Ok(Imported { imported, skipped }) => {
+					Frame::Done(format!("Imported {imported} messages from `{path}`; {skipped} were kept already.").into())
+				},
+				Err(error) => {
+					eprintln!("The import from `{path}` failed: {error}");
+					Frame::Error(ServerError::Import(error
+						.to_string()
+						.into()))
+				},
+			},
+			Control::Moderate(moderate) => self.moderate(moderate, "the control channel"),
+		}
+	}
+
+	/// Do what a moderation asks, on behalf of whoever is named; tells how it
+	/// went. Every moderation is logged, whether it went through or not.
+	fn moderate(&self, moderate: Moderate, by: &str) -> Frame {
+		eprintln!("A moderation by {by}: {moderate}");
+		let done = match moderate {
+			Moderate::List => Ok(self.list()),
+			Moderate::Kick(target, reason) => match self.cut_off(&target, ServerError::Kicked(reason)) {
+				0 => Err(format!("no one is connected as `{target}`.")),
+				kicked => Ok(format!("Kicked `{target}`; {kicked} connections were cut off.")),
+			},
+			Moderate::Mute(target, duration, reason) => self
+				.subject(&target)
+				.and_then(|subject| {
+					if !self
+						.moderation
+						.mute(subject, duration, &reason)
+					{
+						Err(format!("a mute lasts `{}`s at most.", Moderation::LONGEST_MUTE.as_secs()))?
+					}
+					for outbox in self.aimed_at(&target) {
+						let _ = outbox.post(&Frame::Error(ServerError::Silenced(duration.as_secs() as u32, reason.clone())));
+					}
+					Ok(format!("Muted `{target}` for {}s.", duration.as_secs()))
+				}),
+			Moderate::Unmute(target) => self
+				.subject(&target)
+				.and_then(|subject| {
+					if self
+						.moderation
+						.unmute(&subject)
+					{
+						Ok(format!("Unmuted `{subject}`."))
+					} else {
+						Err(format!("`{subject}` is not muted."))
+					}
+				}),
+			// NOTE: a ban by identifier holds for the nickname behind it; the identifier ends with its connection.
+			Moderate::Ban(target, reason) => self
+				.subject(&target)
+				.and_then(|subject| {
+					self.changing(|| {
+						self.moderation
+							.ban(subject.clone(), &reason)
+					})
+					.map_err(|error| format!("the ban could not be kept: {error}"))?;
+					let kicked = self.cut_off(&target, ServerError::Banned(reason));
+					Ok(format!("Banned `{subject}`; {kicked} connections were cut off."))
+				}),
+			Moderate::Unban(target) => self
+				.subject(&target)
+				.and_then(|subject| {
+					match self.changing(|| {
+						self.moderation
+							.unban(&subject)
+					}) {
+						Ok(true) => Ok(format!("Unbanned `{subject}`.")),
+						Ok(false) => Err(format!("`{subject}` is not banned.")),
+						Err(error) => Err(format!("the ban could not be lifted: {error}")),
+					}
+				}),
+			Moderate::Bans => {
+				let bans = self
+					.moderation
+					.bans();
+				if bans.is_empty() {
+					Ok("No one is banned.".into())
+				} else {
+					Ok(bans
+						.iter()
+						.map(|(subject, reason)| format!("{subject}: {reason}"))
+						.collect::<Vec<_>>()
+						.join("\n"))
+				}
+			},
+			Moderate::Grant(nickname) | Moderate::Revoke(nickname)
+				if !self
+					.registry
+					.is_registered(&nickname) =>
+			{
+				Err(format!("`{nickname}` is not registered."))
+			},
+			Moderate::Grant(nickname) => match self.changing(|| {
+				self.moderation
+					.set_operator(&nickname, true)
+			}) {
+				Ok(true) => Ok(format!("`{nickname}` is an operator now.")),
+				Ok(false) => Err(format!("`{nickname}` is an operator already.")),
+				Err(error) => Err(format!("the operators could not be kept: {error}")),
+			},
+			Moderate::Revoke(nickname) => match self.changing(|| {
+				self.moderation
+					.set_operator(&nickname, false)
+			}) {
+				Ok(true) => Ok(format!("`{nickname}` is no operator anymore.")),
+				Ok(false) => Err(format!("`{nickname}` is no operator.")),
+				Err(error) => Err(format!("the operators could not be kept: {error}")),
+			},
+		};
+		match done {
+			Ok(report) => Frame::Done(report.into()),
+			Err(reason) => {
+				eprintln!("A moderation by {by} failed: {reason}");
+				Frame::Error(ServerError::Moderation(reason.into()))
+			},
+		}
+	}
+
+	/// Whoever is connected, with their identifier, nickname and address; a
+	/// line for each.
+	fn list(&self) -> String {
+		let peers = self
+			.peers
+			.read();
+		let mut peers: Vec<_> = peers
+			.iter()
+			.collect();
+		if peers.is_empty() {
+			return "No one is connected.".into()
+		}
+		peers.sort_by_key(|(id, _)| **id);
+		peers
+			.into_iter()
+			.map(|(id, peer)| {
+				let nickname = peer
+					.presence
+					.get_nickname();
+				let mut line = format!("#{id} {nickname:#} {}", peer.address);
+				if self
+					.moderation
+					.is_operator(nickname)
+				{
+					line.push_str(" (operator)");
+				}
+				if let Some((left, reason)) = self
+					.moderation
+					.muted(&[Subject::nickname(nickname), Subject::Address(peer.address)])
+				{
+					line.push_str(&format!(" (muted for {}s: {reason})", left.as_secs()));
+				}
+				line
+			})
+			.collect::<Vec<_>>()
+			.join("\n")
+	}
+
+	/// Whom a ban or a mute of the target holds for; an identifier stands for
+	/// the nickname, that is connected under it.
+	fn subject(&self, target: &Target) -> Result<Subject, String> {
+		match target {
+			Target::Nickname(nickname) => Ok(Subject::nickname(nickname)),
+			Target::Address(address) => Ok(Subject::Address(*address)),
+			Target::Identifier(id) => self
+				.peers
+				.read()
+				.get(id)
+				.map(|peer| Subject::nickname(peer.presence.get_nickname()))
+				.ok_or_else(|| format!("no one is connected as `{target}`.")),
+		}
+	}
+
+	/// The outboxes of whoever the target is aimed at.
+	fn aimed_at(&self, target: &Target) -> Vec<Outbox> {
+		self.peers
+			.read()
+			.iter()
+			.filter(|(id, peer)| match target {
+				Target::Nickname(nickname) => registry::key(peer.presence.get_nickname()) == registry::key(nickname),
+				Target::Identifier(identifier) => *id == identifier,
+				Target::Address(address) => peer.address == *address,
+			})
+			.map(|(_, peer)| {
+				peer.outbox
+					.clone()
+			})
+			.collect()
+	}
+
+	/// Cut off whoever the target is aimed at, for the given reason; tells how
+	/// many were.
+	fn cut_off(&self, target: &Target, reason: ServerError) -> usize {
+		self.aimed_at(target)
+			.iter()
+			.filter(|outbox| outbox.cut_off(&reason))
+			.count()
+	}
+
+	/// Write a snapshot of the history, the registry and the mailboxes to the
+	/// given path.
+	///
+	/// All of them are taken while nothing changes any, so that they agree
+	/// with one another; only the writing waits until after.
+	pub fn backup(&self, path: &Path) -> Result<Backup, BackupError> {
+		let mut backup = Backup::new();
+		let barrier = self
+			.barrier
+			.write();
+		let (name, history) = self
+			.store
+			.snapshot()?;
+		backup.add(name, history);
+		if self.legacy != 0 {
+			backup.add(format::LEGACY, format!("{}\n", self.legacy).into_bytes());
+		}
+		backup.add(Registry::NAME, self
+			.registry
+			.snapshot());
+		backup.add(Mailboxes::NAME, self
+			.mailboxes
+			.snapshot()?);
+		backup.add(Moderation::OPERATORS, self
+			.moderation
+			.operators_snapshot());
+		backup.add(Moderation::BANS, self
+			.moderation
+			.bans_snapshot());
+		drop(barrier);
+		backup.write(path)?;
+		Ok(backup)
+	}
+
+	/// Write a transcript of the messages, that were written within the given
+	/// span, to the given path; either end may be left open. Tells how many
+	/// were written.
+	pub fn export(
+		&self,
+		path: &Path,
+		transcript: Transcript,
+		span: Range<Option<DateTime<Utc>>>,
+	) -> Result<usize, StoreError> {
+		let messages = self
+			.store
+			.range_by_time(
+				span.start
+					.unwrap_or(DateTime::<Utc>::MIN_UTC)
+					.. span
+						.end
+						.unwrap_or(DateTime::<Utc>::MAX_UTC),
+			)?;
+		export::write(path, transcript, &messages)?;
+		Ok(messages.len())
+	}
+
+	/// Make a change to whatever a backup holds; never while one is taken.
+	fn changing<T>(&self, change: impl FnOnce() -> T) -> T {
+		let _barrier = self
+			.barrier
+			.read();
+		change()
+	}
+
+	/// Drop whatever the retention lets expire by now, archiving it first, if
+	/// so configured; tells how many messages expired.
+	pub fn compact(&self) -> Result<usize, StoreError> {
+		let _barrier = self
+			.barrier
+			.read();
+		let expired = self
+			.store
+			.expired(&self.retention, Utc::now())?;
+		let Some(last) = expired.last() else { return Ok(0) };
+		if let Some(archive) = &self.archive {
+			archive.append(&expired)?;
+		}
+		self.store
+			.drop_before(last.get_sequence() + 1)?;
+		self.index
+			.forget_before(last.get_sequence() + 1);
+		Ok(expired.len())
+	}
+
+	/// The first page of the history, that a query asks for, and the sequence
+	/// after which the next one starts; zero, if this is the last.
+	fn page(&self, query: HistoryQuery) -> Result<(Vec<Message>, u64), StoreError> {
+		let size = self.history_page as u64;
+		let latest = self
+			.store
+			.latest()?;
+		let page = match query {
+			HistoryQuery::Last(amount) => {
+				let after = latest.saturating_sub(amount as u64);
+				self.store
+					.range(after + 1 .. after + 1 + size.min(amount as u64))?
+			},
+			HistoryQuery::After(sequence) => self
+				.store
+				.range(sequence.saturating_add(1) .. sequence.saturating_add(1 + size))?,
+			HistoryQuery::Since(time) => {
+				let mut page = self
+					.store
+					.range_by_time(time .. DateTime::<Utc>::MAX_UTC)?;
+				page.truncate(size as usize);
+				page
+			},
+		};
+		let next = page
+			.last()
+			.map(Message::get_sequence)
+			.filter(|last| *last < latest)
+			.unwrap_or(0);
+		Ok((page, next))
+	}
+
+	/// The latest page of hits, that a query finds, and how many there are in
+	/// all; a query, that the index cannot narrow down, only looks through
+	/// the latest [`SEARCH_SCAN`] messages, so that it never costs more than
+	/// that.
+	fn search(&self, query: &SearchQuery) -> Result<(Vec<Message>, u32), StoreError> {
+		let candidates = match self
+			.index
+			.candidates(query)
+		{
+			Some(candidates) => candidates,
+			None => {
+				let latest = self
+					.store
+					.latest()?;
+				(self
+					.store
+					.first()?
+					.max(latest.saturating_sub(SEARCH_SCAN - 1)) ..= latest)
+					.collect()
+			},
+		};
+		let mut hits = Vec::new();
+		let mut total = 0;
+		// NOTE: candidates, that follow one another, are read in one go, latest first.
+		let mut rest = &candidates[..];
+		while let Some(&last) = rest.last() {
+			let run = rest
+				.iter()
+				.rev()
+				.zip(0 ..)
+				.take_while(|(sequence, back)| **sequence == last - back)
+				.count();
+			let first = rest[rest.len() - run];
+			rest = &rest[.. rest.len() - run];
+			for message in self
+				.store
+				.range(first .. last + 1)?
+				.into_iter()
+				.rev()
+				.filter(|message| query.matches(message))
+			{
+				total += 1;
+				if hits.len() < self.history_page as usize {
+					hits.push(message);
+				}
+			}
+		}
+		Ok((hits, total))
+	}
+
+	/// Tell a client about an error in what it sent, if it is any of its
+	/// business.
+	pub fn report(&self, error: &MessageError, outbox: &Outbox) {
+		if let MessageError::TooLong(..) = error {
+			let _ = outbox.post(&Frame::Error(ServerError::TooLong(self.max_frame)));
+		}
+	}
+
+	/// Forget about every client at once, without telling anyone, and hand
+	/// back their outboxes.
+	///
+	/// Used when closing, where leaving is no news.
+	pub fn disband(&self) -> Vec<Outbox> {
+		self.peers
+			.write()
+			.drain()
+			.map(|(_, peer)| peer.outbox)
+			.collect()
+	}
+
+	/// Forget about a client and tell everyone, that it left.
+	pub fn leave(&self, id: Identifier) {
+		self.limiter
+			.forget(id);
+		let left = self
+			.peers
+			.write()
+			.remove(&id);
+		if let Some(peer) = left {
+			let presence = peer
+				.presence
+				.state(State::Offline)
+				.status("");
+			self.broadcast(&Frame::Presence(presence), None);
+		}
+	}
+
+	/// Post a frame to every peer, except for the given one, ignoring those
+	/// that cannot be reached.
+	pub fn broadcast(&self, frame: &Frame, except: Option<Identifier>) {
+		let Ok(bytes) = frame.to_bytes() else { return };
+		let bytes = Encoded::from(bytes);
+		for (_, peer) in self
+			.peers
+			.read()
+			.iter()
+			.filter(|(id, _)| Some(**id) != except)
+		{
+			let _ = peer
+				.outbox
+				.deliver(&bytes);
+		}
+	}
+}
+
+impl Outbox {
+	#[inline(always)]
+	pub fn post(&self, frame: &Frame) -> Result<(), MessageError> { self.deliver(&Encoded::from(frame.to_bytes()?)) }
+
+	/// Queue an encoded frame.
+	///
+	/// What happens, once the queue is full, depends on the [`Overflow`]-policy;
+	/// either way, no client is ever held up by another.
+	pub fn deliver(&self, bytes: &Encoded) -> Result<(), MessageError> {
+		if self
+			.cut
+			.load(Ordering::Acquire)
+		{
+			Err(MessageError::ConnectionInterrupted)?
+		}
+		let mut spill = self
+			.spill
+			.as_ref()
+			.map(|spill| spill.lock());
+		// NOTE: once anything is spilled, everything is, to keep the order.
+		let queued = match &spill {
+			Some(spill) if !spill.is_empty() => Err(TrySendError::Full(bytes.clone())),
+			_ => self
+				.queue
+				.try_send(bytes.clone()),
+		};
+		match (queued, self.overflow) {
+			(Ok(()), _) => (),
+			(Err(TrySendError::Disconnected(_)), _) => Err(MessageError::ConnectionClosed)?,
+			(Err(TrySendError::Full(mut bytes)), Overflow::DropOldest) => loop {
+				// NOTE: the writer, or another poster, may have been quicker.
+				if self
+					.backlog
+					.try_recv()
+					.is_ok()
+				{
+					self.metrics
+						.count(Overflow::DropOldest);
+				}
+				match self
+					.queue
+					.try_send(bytes)
+				{
+					Ok(()) => break,
+					Err(TrySendError::Full(again)) => bytes = again,
+					Err(TrySendError::Disconnected(_)) => return Err(MessageError::ConnectionClosed),
+				}
+			},
+			(Err(TrySendError::Full(bytes)), Overflow::Spill)
+				if spill
+					.as_mut()
+					.is_some_and(|spill| spill.push(&bytes).is_ok()) =>
+			{
+				self.metrics
+					.count(Overflow::Spill);
+			},
+			(Err(TrySendError::Full(_)), _) => {
+				drop(spill);
+				self.disconnect();
+				Err(MessageError::ConnectionInterrupted)?
+			},
+		}
+		if let Owner::Reactor(handle) = &self.owner {
+			handle.flush();
 		}
 		Ok(())
-	});
+	}
+}
 
-	Ok(Server {
-		incoming_handler,
-		message_log,
-		connected,
-		quit,
-	})
+impl Outbox {
+	/// Cut the client off, for being too slow.
+	fn disconnect(&self) {
+		if self.cut_off(&ServerError::TooSlow) {
+			self.metrics
+				.count(Overflow::Disconnect);
+		}
+	}
+
+	/// Cut the client off, with nothing but the reason left to be sent; tells
+	/// whether it was not cut off already.
+	pub fn cut_off(&self, reason: &ServerError) -> bool {
+		if self
+			.cut
+			.swap(true, Ordering::AcqRel)
+		{
+			return false
+		}
+		while self
+			.backlog
+			.try_recv()
+			.is_ok()
+		{}
+		if let Ok(bytes) = Frame::Error(reason.clone()).to_bytes() {
+			let _ = self
+				.queue
+				.try_send(Encoded::from(bytes));
+		}
+		match &self.owner {
+			Owner::Thread(socket, graces) => {
+				let _ = socket.shutdown(Shutdown::Read);
+				let socket = socket.clone();
+				let mut graces = graces.lock();
+				graces.retain(|grace| !grace.is_finished());
+				graces.push(spawn(move || {
+					sleep(GRACE);
+					let _ = socket.shutdown(Shutdown::Both);
+				}));
+			},
+			Owner::Reactor(handle) => handle.cut(),
+		}
+		true
+	}
 }
-///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
-impl Server {
-	/// Create a nonblocking [`TcpListener`] on a given I.P.A. and port.
-	// pub fn host_on(addr: impl ToSocketAddrs) -> Result<Self, io::Error> {}
 
-	pub fn quit(&self) {
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			core: Core::default(),
+			policy: NicknamePolicy::default(),
+			heartbeat: Frame::HEARTBEAT,
+			deadline: Duration::from_secs(5),
+			backpressure: Backpressure::default(),
+			limits: Limits::default(),
+			max_frame: Frame::MAX_LENGTH,
+			handshake: Duration::from_secs(10),
+			trickle: Duration::from_secs(10),
+			per_address: 16,
+			allow_list: None,
+			deny_list: None,
+			storage: Storage::default(),
+			data_dir: PathBuf::from("."),
+			history_page: HISTORY_PAGE,
+			retention: Retention::default(),
+			compact_every: Duration::from_secs(60),
+			archive: false,
+			queue_limit: QUEUE_LIMIT,
+			queue_for: Duration::from_secs(7 * 24 * 60 * 60),
+		}
+	}
+}
+
+impl Config {
+	#[inline(always)]
+	pub fn new() -> Self { Self::default() }
+
+	#[inline(always)]
+	pub const fn get_core(&self) -> Core { self.core }
+	#[inline(always)]
+	pub fn set_core(&mut self, core: Core) { self.core = core; }
+	#[inline(always)]
+	pub fn core(mut self, core: Core) -> Self {
+		self.set_core(core);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_policy(&self) -> &NicknamePolicy { &self.policy }
+	#[inline(always)]
+	pub fn set_policy(&mut self, policy: NicknamePolicy) { self.policy = policy; }
+	#[inline(always)]
+	pub fn policy(mut self, policy: NicknamePolicy) -> Self {
+		self.set_policy(policy);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_heartbeat(&self) -> Duration { self.heartbeat }
+	#[inline(always)]
+	pub fn set_heartbeat(&mut self, heartbeat: Duration) { self.heartbeat = heartbeat; }
+	#[inline(always)]
+	pub fn heartbeat(mut self, heartbeat: Duration) -> Self {
+		self.set_heartbeat(heartbeat);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_deadline(&self) -> Duration { self.deadline }
+	#[inline(always)]
+	pub fn set_deadline(&mut self, deadline: Duration) { self.deadline = deadline; }
+	#[inline(always)]
+	pub fn deadline(mut self, deadline: Duration) -> Self {
+		self.set_deadline(deadline);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_backpressure(&self) -> &Backpressure { &self.backpressure }
+	#[inline(always)]
+	pub fn set_backpressure(&mut self, backpressure: Backpressure) { self.backpressure = backpressure; }
+	#[inline(always)]
+	pub fn backpressure(mut self, backpressure: Backpressure) -> Self {
+		self.set_backpressure(backpressure);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_limits(&self) -> &Limits { &self.limits }
+	#[inline(always)]
+	pub fn set_limits(&mut self, limits: Limits) { self.limits = limits; }
+	#[inline(always)]
+	pub fn limits(mut self, limits: Limits) -> Self {
+		self.set_limits(limits);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_max_frame(&self) -> u32 { self.max_frame }
+	#[inline(always)]
+	pub fn set_max_frame(&mut self, max_frame: u32) { self.max_frame = max_frame; }
+	#[inline(always)]
+	pub fn max_frame(mut self, max_frame: u32) -> Self {
+		self.set_max_frame(max_frame);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_handshake(&self) -> Duration { self.handshake }
+	#[inline(always)]
+	pub fn set_handshake(&mut self, handshake: Duration) { self.handshake = handshake; }
+	#[inline(always)]
+	pub fn handshake(mut self, handshake: Duration) -> Self {
+		self.set_handshake(handshake);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_trickle(&self) -> Duration { self.trickle }
+	#[inline(always)]
+	pub fn set_trickle(&mut self, trickle: Duration) { self.trickle = trickle; }
+	#[inline(always)]
+	pub fn trickle(mut self, trickle: Duration) -> Self {
+		self.set_trickle(trickle);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_per_address(&self) -> u32 { self.per_address }
+	#[inline(always)]
+	pub fn set_per_address(&mut self, per_address: u32) { self.per_address = per_address; }
+	#[inline(always)]
+	pub fn per_address(mut self, per_address: u32) -> Self {
+		self.set_per_address(per_address);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_allow_list(&self) -> Option<&PathBuf> { self.allow_list.as_ref() }
+	#[inline(always)]
+	pub fn set_allow_list(&mut self, allow_list: Option<PathBuf>) { self.allow_list = allow_list; }
+	#[inline(always)]
+	pub fn allow_list(mut self, allow_list: Option<PathBuf>) -> Self {
+		self.set_allow_list(allow_list);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_deny_list(&self) -> Option<&PathBuf> { self.deny_list.as_ref() }
+	#[inline(always)]
+	pub fn set_deny_list(&mut self, deny_list: Option<PathBuf>) { self.deny_list = deny_list; }
+	#[inline(always)]
+	pub fn deny_list(mut self, deny_list: Option<PathBuf>) -> Self {
+		self.set_deny_list(deny_list);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_storage(&self) -> Storage { self.storage }
+	#[inline(always)]
+	pub fn set_storage(&mut self, storage: Storage) { self.storage = storage; }
+	#[inline(always)]
+	pub fn storage(mut self, storage: Storage) -> Self {
+		self.set_storage(storage);
+		self
+	}
+
+	#[inline(always)]
+	pub fn get_data_dir(&self) -> &PathBuf { &self.data_dir }
+	#[inline(always)]
+	pub fn set_data_dir(&mut self, data_dir: impl Into<PathBuf>) { self.data_dir = data_dir.into(); }
+	#[inline(always)]
+	pub fn data_dir(mut self, data_dir: impl Into<PathBuf>) -> Self {
+		self.set_data_dir(data_dir);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_history_page(&self) -> u32 { self.history_page }
+	#[inline(always)]
+	pub fn set_history_page(&mut self, history_page: u32) { self.history_page = history_page; }
+	#[inline(always)]
+	pub fn history_page(mut self, history_page: u32) -> Self {
+		self.set_history_page(history_page);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_retention(&self) -> Retention { self.retention }
+	#[inline(always)]
+	pub fn set_retention(&mut self, retention: Retention) { self.retention = retention; }
+	#[inline(always)]
+	pub fn retention(mut self, retention: Retention) -> Self {
+		self.set_retention(retention);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_compact_every(&self) -> Duration { self.compact_every }
+	#[inline(always)]
+	pub fn set_compact_every(&mut self, every: Duration) { self.compact_every = every; }
+	#[inline(always)]
+	pub fn compact_every(mut self, every: Duration) -> Self {
+		self.set_compact_every(every);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_archive(&self) -> bool { self.archive }
+	#[inline(always)]
+	pub fn set_archive(&mut self, archive: bool) { self.archive = archive; }
+	#[inline(always)]
+	pub fn archive(mut self, archive: bool) -> Self {
+		self.set_archive(archive);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_queue_limit(&self) -> usize { self.queue_limit }
+	#[inline(always)]
+	pub fn set_queue_limit(&mut self, queue_limit: usize) { self.queue_limit = queue_limit; }
+	#[inline(always)]
+	pub fn queue_limit(mut self, queue_limit: usize) -> Self {
+		self.set_queue_limit(queue_limit);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_queue_for(&self) -> Duration { self.queue_for }
+	#[inline(always)]
+	pub fn set_queue_for(&mut self, queue_for: Duration) { self.queue_for = queue_for; }
+	#[inline(always)]
+	pub fn queue_for(mut self, queue_for: Duration) -> Self {
+		self.set_queue_for(queue_for);
+		self
+	}
+}
+
+impl Closer {
+	fn new(alarm: Alarm) -> Self {
+		Self {
+			quit: Arc::new(AtomicBool::new(false)),
+			reason: Arc::default(),
+			alarm,
+		}
+	}
+
+	/// Close the server for the given reason, which may be empty.
+	///
+	/// Only the first reason is told to the clients.
+	pub fn close(&self, reason: impl AsRef<str>) {
+		{
+			let mut current = self
+				.reason
+				.lock();
+			if self.is_closing() {
+				return
+			}
+			current.push_str(reason
+				.as_ref()
+				.trim());
+			self.quit
+				.store(true, Ordering::Release);
+		}
+		match &self.alarm {
+			Alarm::Listener(addr) => {
+				let _ = TcpStream::connect(addr);
+			},
+			Alarm::Reactor(waker) => {
+				let _ = waker.wake();
+			},
+		}
+	}
+
+	#[inline(always)]
+	pub fn is_closing(&self) -> bool {
 		self.quit
-			.store(true, Ordering::Release);
+			.load(Ordering::Acquire)
+	}
+
+	#[inline(always)]
+	fn reason(&self) -> String {
+		self.reason
+			.lock()
+			.clone()
+	}
+}
+
+impl Attachment {
+	#[inline(always)]
+	pub const fn get_address(&self) -> IpAddr { self.address }
+}
+
+impl Drop for Attachment {
+	fn drop(&mut self) {
+		let mut attached = self
+			.attached
+			.lock();
+		if let Some(count) = attached.get_mut(&self.address) {
+			*count -= 1;
+			if *count == 0 {
+				attached.remove(&self.address);
+			}
+		}
+	}
+}
+
+impl Drop for Forget {
+	fn drop(&mut self) {
+		self.connected
+			.write()
+			.remove(&self.id);
+	}
+}
+
+impl Read for Timed<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let timeout = match self.until {
+			Some(until) => {
+				let left = until.saturating_duration_since(Instant::now());
+				if left.is_zero() {
+					Err(io::Error::from(ErrorKind::TimedOut))?
+				}
+				Some(left)
+			},
+			None => self.idle,
+		};
+		self.reader
+			.get_ref()
+			.set_read_timeout(timeout)?;
+		let length = self
+			.reader
+			.read(buf)?;
+		if self
+			.until
+			.is_none()
+			&& length != 0
+			&& !self
+				.trickle
+				.is_zero()
+		{
+			self.until = Some(Instant::now() + self.trickle);
+		}
+		Ok(length)
+	}
+}
+
+impl FromStr for Core {
+	type Err = CoreFromStrError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s
+			.to_lowercase()
+			.as_str()
+		{
+			"threads" | "threaded" => Ok(Self::Threads),
+			"events" | "evented" => Ok(Self::Events),
+			_ => Err(CoreFromStrError(s.into())),
+		}
+	}
+}
+
+impl Server {
+	/// Something to close the server with, from anywhere.
+	#[inline(always)]
+	pub fn closer(&self) -> Closer { self.closer.clone() }
+
+	/// How often the outboxes overflowed so far.
+	#[inline(always)]
+	pub fn metrics(&self) -> Arc<Metrics> {
+		self.shared
+			.metrics
+			.clone()
 	}
 
-	pub fn shutdown(self) -> Result<(), MessageError> {
-		self.quit();
-		unsafe {
+	/// Block until the server has been closed, and every connection is done.
+	pub fn wait(self) -> Result<(), MessageError> {
+		let outcome = unsafe {
 			self.incoming_handler
 				.join()
 				.unwrap_unchecked()
+		};
+		// NOTE: the acceptor may have stopped on its own, on an error.
+		self.closer
+			.close("");
+		for handler in [self.heartbeat_handler, self.compactor]
+			.into_iter()
+			.flatten()
+		{
+			handler
+				.thread()
+				.unpark();
+			let _ = handler.join();
 		}
+
+		let closing = Frame::Closing(self.closer.reason());
+		for outbox in self
+			.shared
+			.disband()
+		{
+			let _ = outbox.post(&closing);
+		}
+		let handlers: Vec<Handler> = self
+			.connected
+			.write()
+			.drain()
+			.map(|(_, handler)| handler)
+			.collect();
+		let mut readers = Vec::with_capacity(handlers.len());
+		let mut sockets = Vec::with_capacity(handlers.len());
+		for Handler { reader, socket } in handlers {
+			// NOTE: a reader, that hears nothing more, ends and takes its writer along.
+			let _ = socket.shutdown(Shutdown::Read);
+			sockets.push(socket);
+			readers.push(reader);
+		}
+
+		let deadline = Instant::now() + self.deadline;
+		while Instant::now() < deadline
+			&& readers
+				.iter()
+				.any(|reader| !reader.is_finished())
+		{
+			sleep(Duration::from_millis(10));
+		}
+		for socket in sockets {
+			let _ = socket.shutdown(Shutdown::Both);
+		}
+		for reader in readers {
+			let _ = reader.join();
+		}
+		let graces: Vec<_> = self
+			.shared
+			.graces
+			.lock()
+			.drain(..)
+			.collect();
+		for grace in graces {
+			let _ = grace.join();
+		}
+		if let Some(token) = &self
+			.shared
+			.token
+		{
+			token.revoke();
+		}
+
+		outcome
 	}
 }
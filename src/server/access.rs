@@ -0,0 +1,264 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	fmt::{self, Display, Formatter},
+	fs::{metadata, read_to_string},
+	io::{self, ErrorKind},
+	net::IpAddr,
+	path::PathBuf,
+	str::FromStr,
+	time::SystemTime,
+};
+
+use parking_lot::{Mutex, MutexGuard};
+use thiserror::Error;
+
+use crate::spaces::{Local, Netspace, Private};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Which addresses a server accepts connections from; by a list of those it
+/// allows, and one of those it denies.
+///
+/// Either is read from a file, and read again, once the file changed; so that
+/// they can be edited, while the server runs.
+///
+/// A connection from the server's own host, that either refuses, is still
+/// let through to the control channel; it just cannot join.
+pub struct Access {
+	allow: Listing,
+	deny: Listing,
+}
+
+/// A range of addresses, as it is written in a list.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq)]
+pub enum Range {
+	/// An address and the length of its prefix; the whole address, if none
+	/// is written.
+	Cidr(IpAddr, u8),
+	/// Every subspace of a net.-space, like `Private`.
+	Private,
+	Local,
+	/// A single subspace, like `Private::P192s16`.
+	PrivateSpace(Private),
+	LocalSpace(Local),
+}
+
+/// Why a connection was refused.
+#[derive(Debug)]
+pub enum Refusal {
+	/// The address is within a range of the deny-list.
+	Denied(Range),
+	/// There is an allow-list, and the address is within none of its ranges.
+	Unlisted,
+}
+
+#[derive(Debug, Error)]
+#[error("`{0}` is neither a CIDR, nor a named net.-space, like `Private::P192s16`.")]
+pub struct RangeFromStrError(pub Box<str>);
+
+/// A list of ranges, as it was last read from its file.
+struct Listing {
+	path: Option<PathBuf>,
+	state: Mutex<Read>,
+}
+
+struct Read {
+	/// When the file was changed, and how long it was, as it was read; none,
+	/// if there was no file.
+	stamp: Option<(SystemTime, u64)>,
+	ranges: Vec<Range>,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Access {
+	/// Take the lists from the files at the given paths; without a path, or
+	/// while there is no file, a list is empty.
+	pub fn open(allow: Option<PathBuf>, deny: Option<PathBuf>) -> io::Result<Self> {
+		let access = Self {
+			allow: Listing::new(allow),
+			deny: Listing::new(deny),
+		};
+		// NOTE: a list, that cannot be read at first, is an error; later on, the last one read is kept.
+		access
+			.allow
+			.refresh()?;
+		access
+			.deny
+			.refresh()?;
+		Ok(access)
+	}
+
+	/// Whether a connection from the address is accepted; the deny-list is
+	/// checked first, and an empty allow-list allows any address.
+	pub fn admits(&self, address: IpAddr) -> Result<(), Refusal> {
+		let address = address.to_canonical();
+		if let Some(range) = self
+			.deny
+			.fresh()
+			.ranges
+			.iter()
+			.find(|range| range.contains(address))
+		{
+			Err(Refusal::Denied(range.clone()))?
+		}
+		let allow = self
+			.allow
+			.fresh();
+		if !allow
+			.ranges
+			.is_empty()
+			&& !allow
+				.ranges
+				.iter()
+				.any(|range| range.contains(address))
+		{
+			Err(Refusal::Unlisted)?
+		}
+		Ok(())
+	}
+}
+
+impl Range {
+	/// Check whether the address lies within the range.
+	pub fn contains(&self, address: IpAddr) -> bool {
+		match (self, address) {
+			(Self::Cidr(IpAddr::V4(network), prefix), IpAddr::V4(address)) => {
+				let mask = u32::MAX
+					.checked_shl(32 - *prefix as u32)
+					.unwrap_or(0);
+				network.to_bits() & mask == address.to_bits() & mask
+			},
+			(Self::Cidr(IpAddr::V6(network), prefix), IpAddr::V6(address)) => {
+				let mask = u128::MAX
+					.checked_shl(128 - *prefix as u32)
+					.unwrap_or(0);
+				network.to_bits() & mask == address.to_bits() & mask
+			},
+			(Self::Private, IpAddr::V4(address)) => Private::contains(&address),
+			(Self::Local, IpAddr::V4(address)) => Local::contains(&address),
+			(Self::PrivateSpace(space), IpAddr::V4(address)) => space.includes(&address),
+			(Self::LocalSpace(space), IpAddr::V4(address)) => space.includes(&address),
+			_ => false,
+		}
+	}
+}
+
+impl Listing {
+	const fn new(path: Option<PathBuf>) -> Self {
+		Self {
+			path,
+			state: Mutex::new(Read {
+				stamp: None,
+				ranges: Vec::new(),
+			}),
+		}
+	}
+
+	/// Read the file again, if it changed since it was read last.
+	fn refresh(&self) -> io::Result<()> {
+		let Some(path) = &self.path else { return Ok(()) };
+		let stamp = match metadata(path) {
+			Ok(metadata) => Some((metadata.modified()?, metadata.len())),
+			Err(error) if error.kind() == ErrorKind::NotFound => None,
+			Err(error) => Err(error)?,
+		};
+		let mut state = self
+			.state
+			.lock();
+		if state.stamp == stamp {
+			return Ok(())
+		}
+		// NOTE: a file, that could not be read, is not tried again, until it changes once more.
+		state.stamp = stamp;
+		state.ranges = if stamp.is_some() { read(&read_to_string(path)?)? } else { Vec::new() };
+		eprintln!("Read {} ranges from `{}`.", state.ranges.len(), path.display());
+		Ok(())
+	}
+
+	/// The list, as it reads by now.
+	fn fresh(&self) -> MutexGuard<'_, Read> {
+		if let Err(error) = self.refresh()
+			&& let Some(path) = &self.path
+		{
+			eprintln!("`{}` could not be read again, and is kept as it was: {error}", path.display());
+		}
+		self.state
+			.lock()
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl FromStr for Range {
+	type Err = RangeFromStrError;
+
+	/// Read `<address>[/<prefix>]`, a net.-space, like `Private`, or one of its
+	/// subspaces, like `Private::P192s16`, or just `P192s16`.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let error = || RangeFromStrError(s.into());
+		if s == "Private" {
+			return Ok(Self::Private)
+		}
+		if s == "Local" {
+			return Ok(Self::Local)
+		}
+		if let Ok(space) = s.parse() {
+			return Ok(Self::PrivateSpace(space))
+		}
+		if let Ok(space) = s.parse() {
+			return Ok(Self::LocalSpace(space))
+		}
+		let (address, prefix) = s
+			.split_once('/')
+			.map_or((s, None), |(address, prefix)| (address, Some(prefix)));
+		let address = address
+			.parse::<IpAddr>()
+			.map_err(|_| error())?
+			.to_canonical();
+		let bits = if address.is_ipv4() { 32 } else { 128 };
+		let prefix = match prefix {
+			Some(prefix) => prefix
+				.parse()
+				.ok()
+				.filter(|prefix| *prefix <= bits)
+				.ok_or_else(error)?,
+			None => bits,
+		};
+		Ok(Self::Cidr(address, prefix))
+	}
+}
+
+impl Display for Range {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Cidr(address, prefix) => write!(f, "{address}/{prefix}"),
+			Self::Private => f.write_str("Private"),
+			Self::Local => f.write_str("Local"),
+			Self::PrivateSpace(space) => Display::fmt(space, f),
+			Self::LocalSpace(space) => Display::fmt(space, f),
+		}
+	}
+}
+
+impl Display for Refusal {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Denied(range) => write!(f, "it is within the denied `{range}`"),
+			Self::Unlisted => f.write_str("it is within none of the allowed ranges"),
+		}
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A range for every line; anything after a `#` is a comment, and blank lines
+/// are skipped.
+fn read(text: &str) -> io::Result<Vec<Range>> {
+	text.lines()
+		.map(|line| {
+			line.split_once('#')
+				.map_or(line, |(line, _)| line)
+				.trim()
+		})
+		.filter(|line| !line.is_empty())
+		.map(|line| {
+			line.parse()
+				.map_err(|error| io::Error::new(ErrorKind::InvalidData, error))
+		})
+		.collect()
+}
@@ -0,0 +1,381 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	env::temp_dir,
+	fmt::{self, Display, Formatter},
+	fs::{DirBuilder, File, OpenOptions, remove_dir, remove_file},
+	io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+	path::PathBuf,
+	process,
+	str::FromStr,
+	sync::{
+		Arc,
+		atomic::{AtomicU64, Ordering},
+	},
+};
+
+use besked::Frame;
+use crossbeam_channel::Receiver;
+use parking_lot::Mutex;
+use thiserror::Error;
+
+use super::{Encoded, OUTBOX_CAPACITY};
+use crate::util::to_hex;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How many bytes a single connection may spill to disk by default.
+pub const SPILL_LIMIT: u64 = 64 * 1024 * 1024;
+/// How often another name is tried for the directory of a spill, that is
+/// taken already.
+const ATTEMPTS: usize = 8;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How much may wait for a client, and what happens once there is more.
+#[derive(Clone)]
+#[derive(Debug)]
+pub struct Backpressure {
+	/// How many frames the outbox of a connection holds.
+	capacity: usize,
+	overflow: Overflow,
+	/// Where connections spill their frames to.
+	directory: PathBuf,
+	/// How many bytes a connection may spill, before it is cut off anyway.
+	limit: u64,
+}
+
+/// What is done with a frame, that does not fit into an outbox anymore.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+#[derive(Eq, PartialEq)]
+pub enum Overflow {
+	/// Make room by throwing away the oldest frame.
+	DropOldest,
+	/// Cut the client off, telling it why.
+	#[default]
+	Disconnect,
+	/// Write it to a file, to be sent once the client catches up.
+	Spill,
+}
+
+/// How often outboxes overflowed, by what was done about it.
+#[derive(Debug)]
+#[derive(Default)]
+pub struct Metrics {
+	dropped: AtomicU64,
+	spilled: AtomicU64,
+	disconnected: AtomicU64,
+}
+
+/// Frames, that did not fit into an outbox, in the order they were posted.
+///
+/// The file is only created once something is spilled, and removed again
+/// with the last outbox. It is kept in a directory of its own, that only the
+/// server may enter, under a name, that cannot be guessed.
+pub struct Spill {
+	/// Where the directory of the file is made.
+	directory: PathBuf,
+	/// What the directory is named after, before a random suffix.
+	name: Box<str>,
+	/// The file, and where it is.
+	file: Option<(PathBuf, File)>,
+	/// Where the next frame is read from.
+	read: u64,
+	/// How far the file has been written.
+	written: u64,
+	limit: u64,
+}
+
+/// The receiving half of an outbox; whatever writes to the client takes its
+/// frames from here.
+pub struct Backlog {
+	queue: Receiver<Encoded>,
+	spill: Option<Arc<Mutex<Spill>>>,
+}
+
+#[derive(Debug, Error)]
+#[error("`{0}` names no overflow-policy; expected `drop-oldest`, `disconnect` or `spill`.")]
+pub struct OverflowFromStrError(pub Box<str>);
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Default for Backpressure {
+	fn default() -> Self {
+		Self {
+			capacity: OUTBOX_CAPACITY,
+			overflow: Overflow::default(),
+			directory: temp_dir(),
+			limit: SPILL_LIMIT,
+		}
+	}
+}
+
+impl Backpressure {
+	#[inline(always)]
+	pub fn new() -> Self { Self::default() }
+
+	#[inline(always)]
+	pub const fn get_capacity(&self) -> usize { self.capacity }
+	#[inline(always)]
+	pub fn set_capacity(&mut self, capacity: usize) { self.capacity = capacity.max(1); }
+	#[inline(always)]
+	pub fn capacity(mut self, capacity: usize) -> Self {
+		self.set_capacity(capacity);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_overflow(&self) -> Overflow { self.overflow }
+	#[inline(always)]
+	pub fn set_overflow(&mut self, overflow: Overflow) { self.overflow = overflow; }
+	#[inline(always)]
+	pub fn overflow(mut self, overflow: Overflow) -> Self {
+		self.set_overflow(overflow);
+		self
+	}
+
+	#[inline(always)]
+	pub fn get_directory(&self) -> &PathBuf { &self.directory }
+	#[inline(always)]
+	pub fn set_directory(&mut self, directory: impl Into<PathBuf>) { self.directory = directory.into(); }
+	#[inline(always)]
+	pub fn directory(mut self, directory: impl Into<PathBuf>) -> Self {
+		self.set_directory(directory);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_limit(&self) -> u64 { self.limit }
+	#[inline(always)]
+	pub fn set_limit(&mut self, limit: u64) { self.limit = limit; }
+	#[inline(always)]
+	pub fn limit(mut self, limit: u64) -> Self {
+		self.set_limit(limit);
+		self
+	}
+
+	/// A spill for the connection of the given name, if this policy spills.
+	pub(super) fn spill(&self, name: impl Display) -> Option<Arc<Mutex<Spill>>> {
+		(self.overflow == Overflow::Spill).then(|| {
+			Arc::new(Mutex::new(Spill {
+				directory: self
+					.directory
+					.clone(),
+				name: format!("{}-{}-{name}", env!("CARGO_PKG_NAME"), process::id()).into(),
+				file: None,
+				read: 0,
+				written: 0,
+				limit: self.limit,
+			}))
+		})
+	}
+}
+
+impl FromStr for Overflow {
+	type Err = OverflowFromStrError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s
+			.to_lowercase()
+			.as_str()
+		{
+			"drop-oldest" | "drop" => Ok(Self::DropOldest),
+			"disconnect" | "cut" => Ok(Self::Disconnect),
+			"spill" => Ok(Self::Spill),
+			_ => Err(OverflowFromStrError(s.into())),
+		}
+	}
+}
+
+impl Metrics {
+	#[inline(always)]
+	pub fn get_dropped(&self) -> u64 {
+		self.dropped
+			.load(Ordering::Relaxed)
+	}
+	#[inline(always)]
+	pub fn get_spilled(&self) -> u64 {
+		self.spilled
+			.load(Ordering::Relaxed)
+	}
+	#[inline(always)]
+	pub fn get_disconnected(&self) -> u64 {
+		self.disconnected
+			.load(Ordering::Relaxed)
+	}
+
+	#[inline(always)]
+	pub(super) fn count(&self, overflow: Overflow) {
+		match overflow {
+			Overflow::DropOldest => &self.dropped,
+			Overflow::Spill => &self.spilled,
+			Overflow::Disconnect => &self.disconnected,
+		}
+		.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+impl Display for Metrics {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"{} frames dropped, {} frames spilled, {} clients disconnected",
+			self.get_dropped(),
+			self.get_spilled(),
+			self.get_disconnected()
+		)
+	}
+}
+
+impl Spill {
+	/// The name of the file in its directory.
+	pub const NAME: &str = "outbox.spill";
+
+	#[inline(always)]
+	pub fn is_empty(&self) -> bool { self.read == self.written }
+
+	/// Append an encoded frame, unless that would exceed the limit.
+	pub fn push(&mut self, bytes: &[u8]) -> io::Result<()> {
+		if self.written + bytes.len() as u64 > self.limit {
+			Err(io::Error::new(ErrorKind::StorageFull, "the spill-limit was reached"))?
+		}
+		let (_, file) = match &mut self.file {
+			Some(file) => file,
+			None => self.file.insert(self.create()?),
+		};
+		file.seek(SeekFrom::Start(self.written))?;
+		file.write_all(bytes)?;
+		self.written += bytes.len() as u64;
+		Ok(())
+	}
+
+	/// Take the oldest frame back out.
+	///
+	/// A file, that cannot be read, is given up on; the frames in it are lost.
+	pub fn pop(&mut self) -> Option<Encoded> {
+		if self.is_empty() {
+			return None
+		}
+		let popped = self.read_next();
+		if popped.is_err() || self.is_empty() {
+			// NOTE: an empty spill starts over at the front of its file.
+			self.read = 0;
+			self.written = 0;
+			if let Some((_, file)) = &self.file {
+				let _ = file.set_len(0);
+			}
+		}
+		popped.ok()
+	}
+
+	/// Make a new directory, and the file in it; neither is ever one, that
+	/// was there before, so that nobody else can have a hold on it.
+	fn create(&self) -> io::Result<(PathBuf, File)> {
+		let mut attempts = 0;
+		let directory = loop {
+			let mut suffix = [0; 8];
+			getrandom::fill(&mut suffix)?;
+			let directory = self
+				.directory
+				.join(format!("{}-{}", self.name, to_hex(&suffix)));
+			let mut builder = DirBuilder::new();
+			#[cfg(unix)]
+			std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+			match builder.create(&directory) {
+				Ok(()) => break directory,
+				Err(error) if error.kind() == ErrorKind::AlreadyExists && attempts < ATTEMPTS => attempts += 1,
+				Err(error) => Err(error)?,
+			}
+		};
+		let path = directory.join(Self::NAME);
+		let mut options = OpenOptions::new();
+		options
+			.read(true)
+			.write(true)
+			.create_new(true);
+		#[cfg(unix)]
+		std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+		match options.open(&path) {
+			Ok(file) => Ok((path, file)),
+			Err(error) => {
+				let _ = remove_dir(&directory);
+				Err(error)
+			},
+		}
+	}
+
+	fn read_next(&mut self) -> io::Result<Encoded> {
+		let Some((_, file)) = &mut self.file else { Err(io::Error::from(ErrorKind::NotFound))? };
+		file.seek(SeekFrom::Start(self.read))?;
+		let mut bytes = vec![0; Frame::HEAD_LENGTH];
+		file.read_exact(&mut bytes)?;
+		let length = u32::from_be_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]) as usize;
+		bytes.resize(Frame::HEAD_LENGTH + length, 0);
+		file.read_exact(&mut bytes[Frame::HEAD_LENGTH ..])?;
+		self.read += bytes.len() as u64;
+		Ok(Encoded::from(bytes))
+	}
+}
+
+impl Drop for Spill {
+	fn drop(&mut self) {
+		if let Some((path, _)) = self
+			.file
+			.take()
+		{
+			let _ = remove_file(&path);
+			if let Some(directory) = path.parent() {
+				let _ = remove_dir(directory);
+			}
+		}
+	}
+}
+
+impl Backlog {
+	#[inline(always)]
+	pub(super) fn new(queue: Receiver<Encoded>, spill: Option<Arc<Mutex<Spill>>>) -> Self { Self { queue, spill } }
+
+	/// Whether nothing is left to be written, for now.
+	pub fn is_empty(&self) -> bool {
+		self.queue
+			.is_empty()
+			&& self
+				.spill
+				.as_ref()
+				.is_none_or(|spill| {
+					spill
+						.lock()
+						.is_empty()
+				})
+	}
+
+	/// The next frame to be written, if there is one already.
+	pub fn try_next(&self) -> Option<Encoded> {
+		if let Ok(bytes) = self
+			.queue
+			.try_recv()
+		{
+			return Some(bytes)
+		}
+		let mut spill = self
+			.spill
+			.as_ref()?
+			.lock();
+		// NOTE:
+		// 	The queue is looked at again under the lock, which posting takes as
+		// 	well; this way, nothing spilled overtakes what was queued in between.
+		match self
+			.queue
+			.try_recv()
+		{
+			Ok(bytes) => Some(bytes),
+			Err(_) => spill.pop(),
+		}
+	}
+
+	/// Wait for the next frame to be written; [`None`] once every outbox is
+	/// gone.
+	pub fn next(&self) -> Option<Encoded> {
+		self.try_next()
+			.or_else(|| {
+				self.queue
+					.recv()
+					.ok()
+			})
+	}
+}
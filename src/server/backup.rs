@@ -0,0 +1,175 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	fs::{File, create_dir_all, read, remove_file, rename},
+	io::{self, ErrorKind, Write},
+	path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use super::{
+	control::Token,
+	format::{self, VERSION},
+	mailbox::MailboxError,
+	store::StoreError,
+};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The first bytes of every backup.
+const MAGIC: &[u8; 8] = b"prata-bk";
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A snapshot of the files of a data-directory, as of a single moment each.
+///
+/// Laid out as [`MAGIC`], and the [`format`]-version of the data-directory
+/// as an `u32`; then, for every file, its name, prefixed by its byte-length
+/// as an `u16`, and its content, prefixed by its byte-length as an `u64`.
+pub struct Backup {
+	version: u32,
+	files: Vec<(Box<str>, Vec<u8>)>,
+}
+
+#[derive(Debug, Error)]
+pub enum BackupError {
+	#[error(transparent)]
+	IO(#[from] io::Error),
+	#[error(transparent)]
+	Store(#[from] StoreError),
+	#[error(transparent)]
+	Mailbox(#[from] MailboxError),
+
+	#[error("`{}` is no backup.", .0.display())]
+	NoBackup(PathBuf),
+	#[error("The backup is of version `{0}`, which is newer than this build knows.")]
+	Newer(u32),
+	#[error("The backup names `{0}`, which is no file of a data-directory.")]
+	Name(Box<str>),
+	#[error("A server is running on `{}`; restore once it is closed, or remove its `{}`, if it is not.", .0.display(), Token::NAME)]
+	Running(PathBuf),
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Backup {
+	/// A backup of the current version.
+	#[inline(always)]
+	pub fn new() -> Self {
+		Self {
+			version: VERSION,
+			files: Vec::new(),
+		}
+	}
+
+	#[inline(always)]
+	pub const fn get_version(&self) -> u32 { self.version }
+
+	/// Take in a file under the given name.
+	pub fn add(&mut self, name: impl Into<Box<str>>, content: Vec<u8>) {
+		self.files
+			.push((name.into(), content));
+	}
+
+	/// The names of the files taken in.
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.files
+			.iter()
+			.map(|(name, _)| &**name)
+	}
+
+	/// Write the backup to a file of its own, which then takes the place of
+	/// whatever was at the given path.
+	pub fn write(&self, path: &Path) -> Result<(), BackupError> {
+		let mut bytes = Vec::from(*MAGIC);
+		bytes.extend(
+			self.version
+				.to_be_bytes(),
+		);
+		for (name, content) in &self.files {
+			bytes.extend((name.len() as u16).to_be_bytes());
+			bytes.extend(name.as_bytes());
+			bytes.extend((content.len() as u64).to_be_bytes());
+			bytes.extend(content);
+		}
+		let saving = path.with_extension("saving");
+		let mut file = File::create(&saving)?;
+		file.write_all(&bytes)?;
+		file.sync_all()?;
+		rename(&saving, path)?;
+		Ok(())
+	}
+
+	/// Read back the backup at the given path.
+	pub fn read(path: &Path) -> Result<Self, BackupError> {
+		let bytes = read(path)?;
+		let no_backup = || BackupError::NoBackup(path.to_path_buf());
+		let Some(mut rest) = bytes.strip_prefix(MAGIC) else { Err(no_backup())? };
+		let version = u32::from_be_bytes(take(&mut rest).ok_or_else(no_backup)?);
+		if version > VERSION {
+			Err(BackupError::Newer(version))?
+		}
+		let mut files = Vec::new();
+		while !rest.is_empty() {
+			let length = u16::from_be_bytes(take(&mut rest).ok_or_else(no_backup)?);
+			let name = take_slice(&mut rest, length as usize)
+				.and_then(|name| str::from_utf8(name).ok())
+				.ok_or_else(no_backup)?;
+			let length = u64::from_be_bytes(take(&mut rest).ok_or_else(no_backup)?);
+			let content = take_slice(&mut rest, length as usize).ok_or_else(no_backup)?;
+			files.push((name.into(), content.to_vec()));
+		}
+		Ok(Self { version, files })
+	}
+
+	/// Put every file back into the given data-directory, which no server may
+	/// be running on, and note its version; the next server to start brings it
+	/// up to date.
+	pub fn restore(&self, directory: &Path) -> Result<(), BackupError> {
+		if directory
+			.join(Token::NAME)
+			.exists()
+		{
+			Err(BackupError::Running(directory.to_path_buf()))?
+		}
+		// NOTE: checked up front, so that nothing is restored, unless everything can be.
+		for name in self.names() {
+			if name.is_empty() || name == format::NAME || name.contains(['/', '\\']) || name.starts_with('.') {
+				Err(BackupError::Name(name.into()))?
+			}
+		}
+		create_dir_all(directory)?;
+		for (name, content) in &self.files {
+			let path = directory.join(&**name);
+			let restoring = path.with_extension("restoring");
+			let mut file = File::create(&restoring)?;
+			file.write_all(content)?;
+			file.sync_all()?;
+			// NOTE: a journal, that outlived its database, would be played back over the one restored.
+			for journal in ["-wal", "-shm"] {
+				match remove_file(directory.join(format!("{name}{journal}"))) {
+					Err(error) if error.kind() != ErrorKind::NotFound => Err(error)?,
+					_ => (),
+				}
+			}
+			rename(&restoring, path)?;
+		}
+		format::stamp(directory, self.version)?;
+		Ok(())
+	}
+}
+
+impl Default for Backup {
+	#[inline(always)]
+	fn default() -> Self { Self::new() }
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Take the given amount of bytes from the front; [`None`], if there are not
+/// as many.
+fn take_slice<'a>(rest: &mut &'a [u8], amount: usize) -> Option<&'a [u8]> {
+	let (taken, left) = rest.split_at_checked(amount)?;
+	*rest = left;
+	Some(taken)
+}
+
+/// Take an array of bytes from the front.
+#[inline(always)]
+fn take<const N: usize>(rest: &mut &[u8]) -> Option<[u8; N]> {
+	take_slice(rest, N)?
+		.try_into()
+		.ok()
+}
@@ -0,0 +1,86 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	fs::{OpenOptions, read_to_string, remove_file},
+	io::{self, BufReader, Write},
+	net::TcpStream,
+	path::{Path, PathBuf},
+};
+
+use besked::{Control, Frame, errors::MessageError};
+
+use crate::util::to_hex;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// What a server takes [`Control`]-frames by; kept in a file of its
+/// data-directory, so that only whoever can read that, can control it.
+///
+/// [`Control`]: Frame::Control
+pub struct Token {
+	path: PathBuf,
+	secret: Box<str>,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Token {
+	/// The name of the file in the data-directory.
+	pub const NAME: &str = "control.token";
+
+	/// Make up a new token, and write it to the given data-directory, for as
+	/// long as the server runs.
+	pub fn issue(directory: &Path) -> io::Result<Self> {
+		let mut secret = [0; 32];
+		getrandom::fill(&mut secret)?;
+		let secret = to_hex(&secret);
+		let path = directory.join(Self::NAME);
+		let mut options = OpenOptions::new();
+		options
+			.write(true)
+			.create(true)
+			.truncate(true);
+		#[cfg(unix)]
+		std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+		let mut file = options.open(&path)?;
+		// NOTE: the mode only holds for a file, that is created; one left behind is narrowed, before the secret goes in.
+		#[cfg(unix)]
+		file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+		file.write_all(secret.as_bytes())?;
+		file.sync_all()?;
+		Ok(Self {
+			path,
+			secret: secret.into(),
+		})
+	}
+
+	/// The token of the server, that runs on the given data-directory.
+	pub fn read(directory: &Path) -> io::Result<Box<str>> {
+		Ok(read_to_string(directory.join(Self::NAME))?
+			.trim()
+			.into())
+	}
+
+	/// Whether the given token is this one.
+	pub fn admits(&self, secret: &str) -> bool {
+		// NOTE: compared in full, like the secrets of the registry.
+		secret.len() == self.secret.len()
+			&& secret
+				.bytes()
+				.zip(self.secret.bytes())
+				.fold(0, |difference, (a, b)| difference | (a ^ b))
+				== 0
+	}
+
+	/// Remove the file, once the server closes.
+	pub fn revoke(&self) { let _ = remove_file(&self.path); }
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Have the server on the other end of the given stream do something, and
+/// tell what it did.
+pub fn request(stream: TcpStream, token: &str, control: Control) -> Result<Box<str>, MessageError> {
+	Frame::Control(token.into(), control).send(&mut &stream)?;
+	let mut reader = BufReader::new(&stream);
+	loop {
+		match Frame::recv_limited(&mut reader, u32::MAX)? {
+			Frame::Done(report) => return Ok(report),
+			Frame::Error(error) => Err(error)?,
+			_ => (),
+		}
+	}
+}
@@ -0,0 +1,145 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	fmt::Display,
+	fs::{File, read_to_string, rename},
+	io::{self, ErrorKind, Write},
+	path::Path,
+};
+
+use super::{
+	mailbox::Mailboxes,
+	registry::Registry,
+	store::{
+		MessageStore, StoreError,
+		file::{Archive, FileStore},
+		sqlite::SqliteStore,
+	},
+};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The version of the layout of a data-directory, that this build keeps.
+///
+/// 1. The history is hash-chained.
+/// 2. Messages of the history may be marked as imported.
+pub const VERSION: u32 = 2;
+/// The name of the file in the data-directory, that tells its version.
+pub const NAME: &str = "format";
+/// The name of the file in the data-directory, that tells the latest sequence
+/// kept before the history was chained; there is none, if it always was.
+pub const LEGACY: &str = "legacy";
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Brings a data-directory from one version to the next; for a server, that
+/// reads frames up to the given length.
+type Migration = fn(&Path, u32) -> Result<(), StoreError>;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// What brings a data-directory of the version at its index to the next.
+const MIGRATIONS: [Migration; VERSION as usize] = [chain, mark];
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The version of the given data-directory.
+///
+/// One, that holds anything, but doesn't tell, predates versions; an empty
+/// one is as new as this build.
+pub fn version(directory: &Path) -> Result<u32, StoreError> {
+	match read_to_string(directory.join(NAME)) {
+		Ok(text) => text
+			.trim()
+			.parse()
+			.map_err(|_| io::Error::new(ErrorKind::InvalidData, format!("`{}` tells no version.", text.trim())).into()),
+		Err(error) if error.kind() == ErrorKind::NotFound => {
+			let kept = [FileStore::NAME, SqliteStore::NAME, Registry::NAME, Mailboxes::NAME, Archive::NAME]
+				.into_iter()
+				.any(|name| {
+					directory
+						.join(name)
+						.exists()
+				});
+			Ok(if kept { 0 } else { VERSION })
+		},
+		Err(error) => Err(error)?,
+	}
+}
+
+/// Bring the given data-directory up to the version of this build, one
+/// version at a time; tells the version it had.
+pub fn migrate(directory: &Path, max_frame: u32) -> Result<u32, StoreError> {
+	let found = version(directory)?;
+	if found > VERSION {
+		Err(StoreError::Newer(found))?
+	}
+	for (from, migration) in MIGRATIONS
+		.iter()
+		.enumerate()
+		.skip(found as usize)
+	{
+		migration(directory, max_frame)?;
+		// NOTE: noted after every step, so that one, that failed, is where the next attempt starts.
+		stamp(directory, from as u32 + 1)?;
+	}
+	if !directory
+		.join(NAME)
+		.exists()
+	{
+		stamp(directory, VERSION)?;
+	}
+	Ok(found)
+}
+
+/// Note the version of the given data-directory.
+#[inline(always)]
+pub fn stamp(directory: &Path, version: u32) -> io::Result<()> { note(directory, NAME, version) }
+
+/// The latest sequence, that the history of the given data-directory kept
+/// before it was chained; up to which entries may go without a link.
+pub fn legacy(directory: &Path) -> Result<u64, StoreError> {
+	match read_to_string(directory.join(LEGACY)) {
+		Ok(text) => text
+			.trim()
+			.parse()
+			.map_err(|_| io::Error::new(ErrorKind::InvalidData, format!("`{}` tells no sequence.", text.trim())).into()),
+		Err(error) if error.kind() == ErrorKind::NotFound => Ok(0),
+		Err(error) => Err(error)?,
+	}
+}
+
+/// Write a single value to a file of its own in the given directory, which
+/// then takes the place of the previous one.
+fn note(directory: &Path, name: &str, value: impl Display) -> io::Result<()> {
+	let path = directory.join(name);
+	let saving = path.with_extension("saving");
+	let mut file = File::create(&saving)?;
+	writeln!(file, "{value}")?;
+	file.sync_all()?;
+	rename(&saving, path)
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// From 0 to 1: give the rows of an SQLite-history a column for their links;
+/// frame-files take [`Chained`]-frames after plain ones as they are.
+///
+/// Either way, the latest sequence kept so far is noted; no entry after it
+/// may go without a link.
+///
+/// [`Chained`]: besked::Frame::Chained
+fn chain(directory: &Path, max_frame: u32) -> Result<(), StoreError> {
+	let sqlite = directory.join(SqliteStore::NAME);
+	let file = directory.join(FileStore::NAME);
+	let kept = if sqlite.exists() {
+		SqliteStore::chain(sqlite)?
+	} else if file.exists() {
+		FileStore::open(file, max_frame)?.latest()?
+	} else {
+		0
+	};
+	if kept != 0 {
+		note(directory, LEGACY, kept)?;
+	}
+	Ok(())
+}
+
+/// From 1 to 2: give the rows of an SQLite-history a column for the mark;
+/// frame-files keep imported messages under a kind of their own.
+fn mark(directory: &Path, _: u32) -> Result<(), StoreError> {
+	let path = directory.join(SqliteStore::NAME);
+	if path.exists() {
+		SqliteStore::mark(path)?;
+	}
+	Ok(())
+}
@@ -0,0 +1,140 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	collections::{HashMap, HashSet},
+	fs::read_to_string,
+	io,
+	path::Path,
+};
+
+use besked::{Identifier, Message, Nickname};
+use chrono::{DateTime, TimeDelta};
+use serde_json::Value;
+use thiserror::Error;
+
+use super::{
+	search::Index,
+	store::{MessageStore, StoreError},
+};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// What tells two messages apart, regardless of the history they were kept
+/// in: when they were written, by whom, and what they say.
+type Key = (i64, Box<str>, Box<str>);
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+#[derive(Debug, Error)]
+pub enum ImportError {
+	#[error(transparent)]
+	IO(#[from] io::Error),
+	#[error(transparent)]
+	Store(#[from] StoreError),
+
+	#[error("Line {0} is no entry of a transcript: {1}")]
+	Entry(usize, Box<str>),
+}
+
+/// How many messages of a transcript were taken into the history, and how
+/// many of them it held already.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct Imported {
+	pub imported: usize,
+	pub skipped: usize,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Read back the messages of a JSON Lines transcript, as [`export`] writes
+/// them; in the order they are listed.
+///
+/// [`export`]: crate::export
+pub fn read(path: &Path) -> Result<Vec<Message>, ImportError> {
+	let mut messages = Vec::new();
+	for (index, line) in read_to_string(path)?
+		.lines()
+		.enumerate()
+	{
+		if line
+			.trim()
+			.is_empty()
+		{
+			continue
+		}
+		let entry = |reason: &str| ImportError::Entry(index + 1, reason.into());
+		let value: Value = serde_json::from_str(line).map_err(|error| entry(&error.to_string()))?;
+		let text = |name: &str| {
+			value[name]
+				.as_str()
+				.ok_or_else(|| entry(&format!("`{name}` is missing, or no string.")))
+		};
+		let written_on = DateTime::parse_from_rfc3339(text("written_on")?).map_err(|error| entry(&error.to_string()))?;
+		let nickname: Nickname = text("nickname")?
+			.parse()
+			.map_err(|error| entry(&format!("{error}")))?;
+		let identifier = value["identifier"]
+			.as_u64()
+			.and_then(|identifier| u32::try_from(identifier).ok())
+			.filter(|identifier| *identifier != 0)
+			.ok_or_else(|| entry("`identifier` is missing, or no identifier."))?;
+		messages.push(
+			Message::empty()
+				.utc(written_on.to_utc())
+				.identifier(Identifier::from(identifier))
+				.nickname(nickname)
+				.content(text("content")?),
+		);
+	}
+	Ok(messages)
+}
+
+/// Append the given messages to the history, marked as imported, and index
+/// them; those, that it holds already, or that came before, are skipped.
+///
+/// The identifiers of their authors belong to another server; so every one is
+/// given a fresh one of its own, rather than one, that may be in use here.
+pub fn import(store: &dyn MessageStore, index: &Index, messages: Vec<Message>) -> Result<Imported, ImportError> {
+	let Some((first, last)) = messages
+		.iter()
+		.map(Message::get_utc)
+		.fold(None, |span, time| match span {
+			Some((first, last)) => Some((time.min(first), time.max(last))),
+			None => Some((time, time)),
+		})
+	else {
+		return Ok(Imported { imported: 0, skipped: 0 })
+	};
+	// NOTE: times are kept to the second; the span ends after the second of the last.
+	let mut kept: HashSet<Key> = store
+		.range_by_time(first .. last + TimeDelta::seconds(1))?
+		.iter()
+		.map(key)
+		.collect();
+	let mut authors = HashMap::new();
+	let mut imported = Imported { imported: 0, skipped: 0 };
+	for message in messages {
+		if !kept.insert(key(&message)) {
+			imported.skipped += 1;
+			continue
+		}
+		let author = *authors
+			.entry(*message.get_identifier())
+			.or_insert_with(Identifier::default);
+		let message = store.append(message
+			.identifier(author)
+			.imported(true))?;
+		index.add(&message);
+		imported.imported += 1;
+	}
+	Ok(imported)
+}
+
+fn key(message: &Message) -> Key {
+	(
+		message
+			.get_utc()
+			.timestamp(),
+		message
+			.get_nickname()
+			.as_str()
+			.into(),
+		message
+			.get_content()
+			.into(),
+	)
+}
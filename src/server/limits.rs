@@ -0,0 +1,378 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	collections::HashMap,
+	fmt::{self, Display, Formatter},
+	net::IpAddr,
+	num::ParseFloatError,
+	str::FromStr,
+	time::{Duration, Instant},
+};
+
+use besked::Identifier;
+use parking_lot::Mutex;
+use thiserror::Error;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How quickly messages may be sent, and how much the server lets pass before
+/// it steps in.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct Limits {
+	/// Per user.
+	user: Rates,
+	/// Per source-address; shared between every connection from it.
+	address: Rates,
+
+	/// How many refused messages get a user muted.
+	mute_after: u32,
+	/// How long a mute lasts; refusals older than this are forgiven.
+	mute_for: Duration,
+	/// How many refused messages get a user cut off.
+	cut_after: u32,
+}
+
+/// The limits on the amount of messages, and on their bytes.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct Rates {
+	messages: Rate,
+	bytes: Rate,
+}
+
+/// A sustained rate per second, on top of which a burst may be spent at once.
+///
+/// Written as `rate/burst`, or `off` for no limit at all.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct Rate {
+	per_second: f64,
+	burst: f64,
+}
+
+/// What the server thinks of a message, that is about to be sent.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub enum Verdict {
+	Pass,
+	/// Refused; the sender may try again after the given time.
+	SlowDown(Duration),
+	/// Refused, for the given time to come.
+	Muted(Duration),
+	/// Refused once too often; the sender is to be cut off.
+	Cut,
+}
+
+/// Keeps the buckets and refusals of every user and address.
+pub struct Limiter {
+	limits: Limits,
+	users: Mutex<HashMap<Identifier, User>>,
+	addresses: Mutex<HashMap<IpAddr, Buckets>>,
+}
+
+/// What a limiter knows about a single user.
+struct User {
+	address: IpAddr,
+	buckets: Buckets,
+	/// How many messages were refused lately.
+	strikes: u32,
+	/// When the last message was refused.
+	struck: Instant,
+	muted: Option<Instant>,
+}
+
+struct Buckets {
+	messages: Bucket,
+	bytes: Bucket,
+}
+
+/// A token-bucket, that refills at its rate, up to its burst.
+struct Bucket {
+	tokens: f64,
+	filled: Instant,
+}
+
+#[derive(Debug, Error)]
+pub enum RateFromStrError {
+	#[error("`{0}` is no rate; expected `rate/burst` or `off`.")]
+	Form(Box<str>),
+	#[error(transparent)]
+	Number(#[from] ParseFloatError),
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Default for Limits {
+	fn default() -> Self {
+		Self {
+			user: Rates {
+				messages: Rate::new(5.0, 10.0),
+				bytes: Rate::new(16.0 * 1024.0, 64.0 * 1024.0),
+			},
+			address: Rates {
+				messages: Rate::new(20.0, 40.0),
+				bytes: Rate::new(64.0 * 1024.0, 256.0 * 1024.0),
+			},
+			mute_after: 5,
+			mute_for: Duration::from_secs(30),
+			cut_after: 10,
+		}
+	}
+}
+
+impl Limits {
+	#[inline(always)]
+	pub fn new() -> Self { Self::default() }
+
+	#[inline(always)]
+	pub const fn get_user(&self) -> Rates { self.user }
+	#[inline(always)]
+	pub fn set_user(&mut self, user: Rates) { self.user = user; }
+	#[inline(always)]
+	pub fn user(mut self, user: Rates) -> Self {
+		self.set_user(user);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_address(&self) -> Rates { self.address }
+	#[inline(always)]
+	pub fn set_address(&mut self, address: Rates) { self.address = address; }
+	#[inline(always)]
+	pub fn address(mut self, address: Rates) -> Self {
+		self.set_address(address);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_mute_after(&self) -> u32 { self.mute_after }
+	#[inline(always)]
+	pub fn set_mute_after(&mut self, strikes: u32) { self.mute_after = strikes; }
+	#[inline(always)]
+	pub fn mute_after(mut self, strikes: u32) -> Self {
+		self.set_mute_after(strikes);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_mute_for(&self) -> Duration { self.mute_for }
+	#[inline(always)]
+	pub fn set_mute_for(&mut self, duration: Duration) { self.mute_for = duration; }
+	#[inline(always)]
+	pub fn mute_for(mut self, duration: Duration) -> Self {
+		self.set_mute_for(duration);
+		self
+	}
+
+	#[inline(always)]
+	pub const fn get_cut_after(&self) -> u32 { self.cut_after }
+	#[inline(always)]
+	pub fn set_cut_after(&mut self, strikes: u32) { self.cut_after = strikes; }
+	#[inline(always)]
+	pub fn cut_after(mut self, strikes: u32) -> Self {
+		self.set_cut_after(strikes);
+		self
+	}
+}
+
+impl Rates {
+	#[inline(always)]
+	pub const fn new(messages: Rate, bytes: Rate) -> Self { Self { messages, bytes } }
+
+	#[inline(always)]
+	pub const fn get_messages(&self) -> Rate { self.messages }
+	#[inline(always)]
+	pub const fn get_bytes(&self) -> Rate { self.bytes }
+}
+
+impl Rate {
+	/// No limit at all.
+	pub const OFF: Self = Self {
+		per_second: f64::INFINITY,
+		burst: f64::INFINITY,
+	};
+
+	#[inline(always)]
+	pub const fn new(per_second: f64, burst: f64) -> Self { Self { per_second, burst } }
+
+	#[inline(always)]
+	pub fn is_off(&self) -> bool { *self == Self::OFF }
+}
+
+impl Display for Rate {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		if self.is_off() {
+			return f.write_str("off")
+		}
+		write!(f, "{}/{}", self.per_second, self.burst)
+	}
+}
+
+impl FromStr for Rate {
+	type Err = RateFromStrError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if s.eq_ignore_ascii_case("off") {
+			return Ok(Self::OFF)
+		}
+		let Some((per_second, burst)) = s.split_once('/') else { Err(RateFromStrError::Form(s.into()))? };
+		let (per_second, burst) = (per_second.parse()?, burst.parse()?);
+		if !(f64::is_normal(per_second) && per_second > 0.0 && burst >= 1.0) {
+			Err(RateFromStrError::Form(s.into()))?
+		}
+		Ok(Self::new(per_second, burst))
+	}
+}
+
+impl Limiter {
+	pub fn new(limits: Limits) -> Self {
+		Self {
+			limits,
+			users: Mutex::default(),
+			addresses: Mutex::default(),
+		}
+	}
+
+	/// Start keeping track of a user, that joined from the given address.
+	pub fn admit(&self, id: Identifier, address: IpAddr) {
+		let now = Instant::now();
+		self.users
+			.lock()
+			.insert(id, User {
+				address,
+				buckets: Buckets::new(&self.limits.user, now),
+				strikes: 0,
+				struck: now,
+				muted: None,
+			});
+	}
+
+	/// Judge a message of `bytes` from the given user, and spend its tokens
+	/// and those of its address, if it passes.
+	pub fn check(&self, id: Identifier, bytes: usize) -> Verdict {
+		let now = Instant::now();
+		let mut users = self
+			.users
+			.lock();
+		let Some(user) = users.get_mut(&id) else { return Verdict::Pass };
+		if now.duration_since(user.struck) >= self.limits.mute_for {
+			user.strikes = 0;
+		}
+		// NOTE: keeping on while muted counts against the user as well.
+		if let Some(until) = user.muted {
+			match until.checked_duration_since(now) {
+				Some(left) => {
+					user.strikes += 1;
+					user.struck = now;
+					if user.strikes >= self.limits.cut_after {
+						return Verdict::Cut
+					}
+					return Verdict::Muted(left)
+				},
+				None => user.muted = None,
+			}
+		}
+
+		let mut addresses = self
+			.addresses
+			.lock();
+		let shared = addresses
+			.entry(user.address)
+			.or_insert_with(|| Buckets::new(&self.limits.address, now));
+		let wait = user
+			.buckets
+			.wait(&self.limits.user, bytes, now)
+			.max(shared.wait(&self.limits.address, bytes, now));
+		if wait.is_zero() {
+			user.buckets
+				.take(bytes);
+			shared.take(bytes);
+			return Verdict::Pass
+		}
+
+		user.strikes += 1;
+		user.struck = now;
+		if user.strikes >= self.limits.cut_after {
+			Verdict::Cut
+		} else if user.strikes >= self.limits.mute_after {
+			user.muted = Some(now + self.limits.mute_for);
+			Verdict::Muted(self.limits.mute_for)
+		} else {
+			Verdict::SlowDown(wait)
+		}
+	}
+
+	/// Forget about a user, that left, and about every address, that has been
+	/// quiet for long enough to be full again.
+	pub fn forget(&self, id: Identifier) {
+		self.users
+			.lock()
+			.remove(&id);
+		let now = Instant::now();
+		self.addresses
+			.lock()
+			.retain(|_, buckets| !buckets.is_full(&self.limits.address, now));
+	}
+}
+
+impl Buckets {
+	fn new(rates: &Rates, now: Instant) -> Self {
+		Self {
+			messages: Bucket::new(&rates.messages, now),
+			bytes: Bucket::new(&rates.bytes, now),
+		}
+	}
+
+	/// How long it takes, until a message of `bytes` fits.
+	fn wait(&mut self, rates: &Rates, bytes: usize, now: Instant) -> Duration {
+		self.messages
+			.wait(&rates.messages, 1.0, now)
+			.max(
+				self.bytes
+					.wait(&rates.bytes, bytes as f64, now),
+			)
+	}
+
+	fn take(&mut self, bytes: usize) {
+		self.messages.tokens -= 1.0;
+		self.bytes.tokens -= bytes as f64;
+	}
+
+	fn is_full(&mut self, rates: &Rates, now: Instant) -> bool {
+		self.messages
+			.wait(&rates.messages, rates.messages.burst, now)
+			.is_zero()
+			&& self
+				.bytes
+				.wait(&rates.bytes, rates.bytes.burst, now)
+				.is_zero()
+	}
+}
+
+impl Bucket {
+	#[inline(always)]
+	fn new(rate: &Rate, now: Instant) -> Self {
+		Self {
+			tokens: rate.burst,
+			filled: now,
+		}
+	}
+
+	/// Refill, and tell how long it takes until `amount` tokens are there.
+	///
+	/// An amount larger than the burst is waited for until the bucket is full.
+	fn wait(&mut self, rate: &Rate, amount: f64, now: Instant) -> Duration {
+		if rate.is_off() {
+			return Duration::ZERO
+		}
+		let elapsed = now
+			.duration_since(self.filled)
+			.as_secs_f64();
+		self.tokens = (self.tokens + elapsed * rate.per_second).min(rate.burst);
+		self.filled = now;
+
+		let missing = amount.min(rate.burst) - self.tokens;
+		if missing <= 0.0 {
+			Duration::ZERO
+		} else {
+			Duration::from_secs_f64(missing / rate.per_second)
+		}
+	}
+}
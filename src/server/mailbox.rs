@@ -0,0 +1,177 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	collections::{HashMap, VecDeque},
+	fs::{self, File, rename},
+	io::{self, BufReader, ErrorKind, Write},
+	path::PathBuf,
+	time::Duration,
+};
+
+use besked::{Frame, Message, Nickname, errors::MessageError};
+use chrono::{DateTime, TimeDelta, Utc};
+use parking_lot::Mutex;
+use thiserror::Error;
+
+use super::registry::key;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Every mailbox by the key of its recipient, with their nickname alongside
+/// each message.
+type Queued = HashMap<Box<str>, VecDeque<(Nickname, Message)>>;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Direct messages, that wait for their recipients to connect; oldest first.
+///
+/// Kept as [`Direct`]-frames in a file of the data-directory, if the server
+/// keeps its history on disk.
+///
+/// [`Direct`]: Frame::Direct
+pub struct Mailboxes {
+	path: Option<PathBuf>,
+	/// How many messages may wait for a single recipient; any amount, if
+	/// zero.
+	limit: usize,
+	/// How long a message may wait; forever, if zero.
+	keep_for: Duration,
+	queued: Mutex<Queued>,
+}
+
+#[derive(Debug, Error)]
+pub enum MailboxError {
+	#[error("The mailbox is full.")]
+	Full,
+	#[error("{0}")]
+	IO(#[from] io::Error),
+	#[error("{0}")]
+	Message(#[from] MessageError),
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Mailboxes {
+	/// The name of the file in the data-directory.
+	pub const NAME: &str = "mailboxes.frames";
+
+	/// Read back the mailboxes at the given path, if there are any; without a
+	/// path, they are gone with the server.
+	pub fn open(path: Option<PathBuf>, limit: usize, keep_for: Duration) -> Result<Self, MailboxError> {
+		let mut queued = Queued::new();
+		if let Some(path) = &path {
+			match File::open(path) {
+				Ok(file) => {
+					let mut reader = BufReader::new(file);
+					loop {
+						match Frame::recv_limited(&mut reader, u32::MAX) {
+							Ok(Frame::Direct(to, message)) => queued
+								.entry(key(&to))
+								.or_default()
+								.push_back((to, message)),
+							Ok(other) => Err(MessageError::Unexpected(other.kind()))?,
+							Err(MessageError::ConnectionClosed) => break,
+							Err(error) => Err(error)?,
+						}
+					}
+				},
+				Err(error) if error.kind() == ErrorKind::NotFound => (),
+				Err(error) => Err(error)?,
+			}
+		}
+		Ok(Self {
+			path,
+			limit,
+			keep_for,
+			queued: Mutex::new(queued),
+		})
+	}
+
+	/// Keep a message for the given recipient, unless their mailbox is full.
+	pub fn push(&self, to: &Nickname, message: Message) -> Result<(), MailboxError> {
+		let mut queued = self
+			.queued
+			.lock();
+		self.expire(&mut queued);
+		let mailbox = queued
+			.entry(key(to))
+			.or_default();
+		if self.limit != 0 && mailbox.len() >= self.limit {
+			Err(MailboxError::Full)?
+		}
+		mailbox.push_back((to.clone(), message));
+		if let Err(error) = self.save(&queued) {
+			if let Some(mailbox) = queued.get_mut(&*key(to)) {
+				mailbox.pop_back();
+			}
+			Err(error)?
+		}
+		Ok(())
+	}
+
+	/// Take every message, that waits for the given recipient.
+	pub fn take(&self, to: &Nickname) -> Result<Vec<Message>, MailboxError> {
+		let mut queued = self
+			.queued
+			.lock();
+		self.expire(&mut queued);
+		let Some(mailbox) = queued.remove(&*key(to)) else { return Ok(Vec::new()) };
+		// NOTE: handed out, even if they could not be forgotten on disk; twice is better than never.
+		if let Err(error) = self.save(&queued) {
+			eprintln!("The mailbox of `{to}` could not be emptied on disk: {error}");
+		}
+		Ok(mailbox
+			.into_iter()
+			.map(|(_, message)| message)
+			.collect())
+	}
+
+	/// Drop every message, that waited for too long.
+	fn expire(&self, queued: &mut Queued) {
+		if self
+			.keep_for
+			.is_zero()
+		{
+			return
+		}
+		let oldest = TimeDelta::from_std(self.keep_for)
+			.ok()
+			.and_then(|keep_for| Utc::now().checked_sub_signed(keep_for))
+			.unwrap_or(DateTime::<Utc>::MIN_UTC);
+		for mailbox in queued.values_mut() {
+			mailbox.retain(|(_, message)| message.get_utc() >= oldest);
+		}
+		queued.retain(|_, mailbox| !mailbox.is_empty());
+	}
+
+	/// Every waiting message, as its file holds them.
+	pub fn snapshot(&self) -> Result<Vec<u8>, MailboxError> {
+		let mut queued = self
+			.queued
+			.lock();
+		self.expire(&mut queued);
+		encode(&queued)
+	}
+
+	/// Write every waiting message to a file of its own, which then takes the
+	/// place of the previous one.
+	fn save(&self, queued: &Queued) -> Result<(), MailboxError> {
+		let Some(path) = &self.path else { return Ok(()) };
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+		let saving = path.with_extension("saving");
+		let mut file = File::create(&saving)?;
+		file.write_all(&encode(queued)?)?;
+		file.sync_all()?;
+		rename(&saving, path)?;
+		Ok(())
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A [`Direct`]-frame for every waiting message.
+///
+/// [`Direct`]: Frame::Direct
+fn encode(queued: &Queued) -> Result<Vec<u8>, MailboxError> {
+	let mut bytes = Vec::new();
+	for (to, message) in queued
+		.values()
+		.flatten()
+	{
+		bytes.extend(Frame::Direct(to.clone(), message.clone()).to_bytes()?);
+	}
+	Ok(bytes)
+}
@@ -0,0 +1,292 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	collections::{HashMap, HashSet},
+	fmt::{self, Display, Formatter},
+	fs::{self, File, rename},
+	io::{self, BufRead, BufReader, ErrorKind, Write},
+	net::IpAddr,
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+
+use besked::Nickname;
+use parking_lot::{Mutex, RwLock};
+
+use super::registry::key;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Who the operators are, who is banned, and who is muted.
+///
+/// Operators and bans are kept in files of the data-directory, if the server
+/// keeps its history on disk; mutes are gone with the server.
+pub struct Moderation {
+	operators_path: Option<PathBuf>,
+	bans_path: Option<PathBuf>,
+	/// The nicknames of the operators; regardless of case.
+	operators: RwLock<HashSet<Box<str>>>,
+	/// Every ban, and its reason.
+	bans: RwLock<HashMap<Subject, Box<str>>>,
+	/// Until when each is muted, and the reason.
+	mutes: Mutex<HashMap<Subject, (Instant, Box<str>)>>,
+}
+
+/// Whom a ban or a mute holds for; an identifier is only good for as long as
+/// its connection, so it is looked up before.
+#[derive(Clone)]
+#[derive(Debug)]
+#[derive(Eq, PartialEq, Hash)]
+pub enum Subject {
+	/// A nickname; regardless of case.
+	Nickname(Box<str>),
+	Address(IpAddr),
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Moderation {
+	/// The name of the file of operators in the data-directory.
+	pub const OPERATORS: &str = "operators";
+	/// The name of the file of bans in the data-directory.
+	pub const BANS: &str = "bans";
+	/// The longest, that a subject can be muted for at once; whatever needs
+	/// longer is a ban.
+	pub const LONGEST_MUTE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+	/// Read back the operators and bans at the given paths, if there are any;
+	/// without a path, they are gone with the server.
+	pub fn open(operators_path: Option<PathBuf>, bans_path: Option<PathBuf>) -> io::Result<Self> {
+		let mut operators = HashSet::new();
+		if let Some(path) = &operators_path {
+			for line in lines(path)? {
+				operators.insert(line.into());
+			}
+		}
+		let mut bans = HashMap::new();
+		if let Some(path) = &bans_path {
+			for line in lines(path)? {
+				let Some((subject, reason)) = line.split_once('\t') else {
+					Err(io::Error::new(ErrorKind::InvalidData, format!("`{line}` is no ban.")))?
+				};
+				let Some(subject) = Subject::read(subject) else {
+					Err(io::Error::new(ErrorKind::InvalidData, format!("`{subject}` is neither a nickname, nor an address.")))?
+				};
+				bans.insert(subject, reason.into());
+			}
+		}
+		Ok(Self {
+			operators_path,
+			bans_path,
+			operators: RwLock::new(operators),
+			bans: RwLock::new(bans),
+			mutes: Mutex::default(),
+		})
+	}
+
+	#[inline(always)]
+	pub fn is_operator(&self, nickname: &Nickname) -> bool {
+		self.operators
+			.read()
+			.contains(&*key(nickname))
+	}
+
+	/// Make the nickname an operator, or take the role away again; tells
+	/// whether anything changed.
+	pub fn set_operator(&self, nickname: &Nickname, operator: bool) -> io::Result<bool> {
+		let mut operators = self
+			.operators
+			.write();
+		let changed = if operator {
+			operators.insert(key(nickname))
+		} else {
+			operators.remove(&*key(nickname))
+		};
+		if changed && let Err(error) = save(self.operators_path.as_deref(), &encode_operators(&operators)) {
+			if operator {
+				operators.remove(&*key(nickname));
+			} else {
+				operators.insert(key(nickname));
+			}
+			Err(error)?
+		}
+		Ok(changed)
+	}
+
+	/// The reason, that the subject is banned for; if it is.
+	pub fn banned(&self, subject: &Subject) -> Option<Box<str>> {
+		self.bans
+			.read()
+			.get(subject)
+			.cloned()
+	}
+
+	/// Ban the subject for the given reason, or change the reason it is
+	/// banned for.
+	pub fn ban(&self, subject: Subject, reason: &str) -> io::Result<()> {
+		let mut bans = self
+			.bans
+			.write();
+		let previous = bans.insert(subject.clone(), reason.into());
+		if let Err(error) = save(self.bans_path.as_deref(), &encode_bans(&bans)) {
+			match previous {
+				Some(previous) => bans.insert(subject, previous),
+				None => bans.remove(&subject),
+			};
+			Err(error)?
+		}
+		Ok(())
+	}
+
+	/// Lift the ban of the subject; tells whether there was one.
+	pub fn unban(&self, subject: &Subject) -> io::Result<bool> {
+		let mut bans = self
+			.bans
+			.write();
+		let Some(previous) = bans.remove(subject) else { return Ok(false) };
+		if let Err(error) = save(self.bans_path.as_deref(), &encode_bans(&bans)) {
+			bans.insert(subject.clone(), previous);
+			Err(error)?
+		}
+		Ok(true)
+	}
+
+	/// Every ban, and its reason; by their subject.
+	pub fn bans(&self) -> Vec<(Subject, Box<str>)> {
+		let mut bans: Vec<_> = self
+			.bans
+			.read()
+			.iter()
+			.map(|(subject, reason)| (subject.clone(), reason.clone()))
+			.collect();
+		bans.sort_by_key(|(subject, _)| subject.to_string());
+		bans
+	}
+
+	/// Mute the subject for the given time, from now on, and the given reason;
+	/// tells whether it was muted, which it is not for longer than the longest
+	/// mute.
+	pub fn mute(&self, subject: Subject, duration: Duration, reason: &str) -> bool {
+		let Some(until) = Instant::now()
+			.checked_add(duration)
+			.filter(|_| duration <= Self::LONGEST_MUTE)
+		else {
+			return false
+		};
+		self.mutes
+			.lock()
+			.insert(subject, (until, reason.into()));
+		true
+	}
+
+	/// Lift the mute of the subject; tells whether there was one.
+	pub fn unmute(&self, subject: &Subject) -> bool {
+		self.mutes
+			.lock()
+			.remove(subject)
+			.is_some_and(|(until, _)| until > Instant::now())
+	}
+
+	/// How long whichever of the subjects is muted the longest is left to be,
+	/// and for what reason; expired mutes are forgotten along the way.
+	pub fn muted<'a>(&self, subjects: impl IntoIterator<Item = &'a Subject>) -> Option<(Duration, Box<str>)> {
+		let now = Instant::now();
+		let mut mutes = self
+			.mutes
+			.lock();
+		mutes.retain(|_, (until, _)| *until > now);
+		subjects
+			.into_iter()
+			.filter_map(|subject| mutes.get(subject))
+			.max_by_key(|(until, _)| *until)
+			.map(|(until, reason)| (*until - now, reason.clone()))
+	}
+
+	/// The operators, as their file holds them.
+	#[inline(always)]
+	pub fn operators_snapshot(&self) -> Vec<u8> {
+		encode_operators(&self
+			.operators
+			.read())
+	}
+
+	/// The bans, as their file holds them.
+	#[inline(always)]
+	pub fn bans_snapshot(&self) -> Vec<u8> {
+		encode_bans(&self
+			.bans
+			.read())
+	}
+}
+
+impl Subject {
+	#[inline(always)]
+	pub fn nickname(nickname: &Nickname) -> Self { Self::Nickname(key(nickname)) }
+
+	/// Read a subject, as it is shown; a nickname after an `@`, so that none
+	/// can pass for an address.
+	fn read(text: &str) -> Option<Self> {
+		match text.strip_prefix('@') {
+			Some(nickname) if !nickname.is_empty() => Some(Self::Nickname(nickname.into())),
+			Some(_) => None,
+			None => text
+				.parse()
+				.ok()
+				.map(Self::Address),
+		}
+	}
+}
+
+impl Display for Subject {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Nickname(nickname) => write!(f, "@{nickname}"),
+			Self::Address(address) => Display::fmt(address, f),
+		}
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The non-empty lines of the file at the given path; none, if there is none.
+fn lines(path: &Path) -> io::Result<Vec<String>> {
+	let file = match File::open(path) {
+		Ok(file) => file,
+		Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(error) => Err(error)?,
+	};
+	let mut lines = Vec::new();
+	for line in BufReader::new(file).lines() {
+		let line = line?;
+		if !line.is_empty() {
+			lines.push(line);
+		}
+	}
+	Ok(lines)
+}
+
+/// Write a file of its own, which then takes the place of the previous one.
+fn save(path: Option<&Path>, content: &[u8]) -> io::Result<()> {
+	let Some(path) = path else { return Ok(()) };
+	if let Some(parent) = path.parent() {
+		fs::create_dir_all(parent)?;
+	}
+	let saving = path.with_extension("saving");
+	let mut file = File::create(&saving)?;
+	file.write_all(content)?;
+	file.sync_all()?;
+	rename(&saving, path)
+}
+
+/// A line for every operator.
+fn encode_operators(operators: &HashSet<Box<str>>) -> Vec<u8> {
+	operators
+		.iter()
+		.map(|nickname| format!("{nickname}\n"))
+		.collect::<String>()
+		.into_bytes()
+}
+
+/// A line for every ban; its subject, as it is shown, and reason, apart by a
+/// tab.
+fn encode_bans(bans: &HashMap<Subject, Box<str>>) -> Vec<u8> {
+	bans.iter()
+		.map(|(subject, reason)| {
+			format!("{subject}\t{}\n", reason.replace(['\t', '\n', '\r'], " "))
+		})
+		.collect::<String>()
+		.into_bytes()
+}
@@ -0,0 +1,690 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	collections::HashMap,
+	io::{self, ErrorKind, Read, Write},
+	net::{Ipv4Addr, SocketAddr},
+	sync::Arc,
+	thread::spawn,
+	time::{Duration, Instant},
+};
+
+use besked::{Frame, Identifier, Nickname, NicknamePolicy, errors::MessageError};
+use crossbeam_channel::{Receiver, Sender, unbounded};
+use mio::{
+	Events, Interest, Poll, Token, Waker,
+	net::{TcpListener, TcpStream},
+};
+
+use super::{
+	ACCEPT_BACKOFF, Alarm, Attachment, Closer, Config, Connected, Encoded, HostError, Outbox, Owner, Server, Shared,
+	backpressure::Backlog, beat, compact, is_transient,
+};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+const LISTENER: Token = Token(0);
+const WAKER: Token = Token(1);
+/// The first token, that is handed out to a connection.
+const FIRST: usize = 2;
+
+/// How many events are handled per poll.
+const EVENTS: usize = 1024;
+/// How much is read from a connection at once.
+const CHUNK: usize = 4096;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// What the worker does for a connection, instead of its event-loop.
+type Job = Box<dyn FnOnce() + Send>;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Why a connection wants the attention of its event-loop.
+enum Wake {
+	/// Its outbox has something to write.
+	Flush(Token),
+	/// Its outbox overflowed.
+	Cut(Token),
+	/// The worker is done with what it asked for.
+	Resume(Token),
+}
+
+/// Lets an [`Outbox`] wake the event-loop, that owns its connection.
+#[derive(Clone)]
+pub struct Handle {
+	token: Token,
+	ready: Sender<Wake>,
+	waker: Arc<Waker>,
+}
+
+/// A single-threaded server-core, that serves every connection from one
+/// readiness event-loop.
+struct Reactor {
+	poll: Poll,
+	listener: TcpListener,
+
+	waker: Arc<Waker>,
+	ready: Sender<Wake>,
+	woken: Receiver<Wake>,
+
+	connections: HashMap<Token, Connection>,
+	next: usize,
+	/// Takes whatever may take long, like a backup or a search, off the
+	/// event-loop.
+	work: Sender<Job>,
+	/// Until when accepting is held off; sooner, once a connection closes.
+	paused: Option<Instant>,
+
+	shared: Shared,
+	heartbeat: Duration,
+	closer: Closer,
+	deadline: Duration,
+}
+
+struct Connection {
+	stream: TcpStream,
+	attachment: Attachment,
+
+	id: Identifier,
+	/// Set, once the client has been admitted.
+	nickname: Option<Nickname>,
+
+	outbox: Outbox,
+	backlog: Backlog,
+	handle: Handle,
+	/// Set, while the worker does what the client asked for; no other frame
+	/// is taken on, and no deadline runs out, until it is done.
+	pending: bool,
+
+	/// Bytes, that don't yet make up a whole frame.
+	inbound: Vec<u8>,
+	/// The frame being written, and how much of it already is.
+	outbound: Option<(Encoded, usize)>,
+
+	/// When the client connected.
+	accepted: Instant,
+	/// When the frame, that is partially read, started to come in.
+	started: Option<Instant>,
+	/// When the client was last heard from.
+	heard: Instant,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Serve on the given address and port from a single event-loop.
+///
+/// Speaks the same protocol as, and behaves like, [`super::host_on`]; only
+/// that it doesn't need two threads for every connection.
+pub fn host_on(addr: Ipv4Addr, port: impl Into<u16>, config: Config) -> Result<Server, HostError> {
+	let mut listener = TcpListener::bind(SocketAddr::from((addr, port.into())))?;
+	let poll = Poll::new()?;
+	poll.registry()
+		.register(&mut listener, LISTENER, Interest::READABLE)?;
+	let waker = Arc::new(Waker::new(poll.registry(), WAKER)?);
+	let (ready, woken) = unbounded();
+	let (work, jobs) = unbounded::<Job>();
+	// NOTE: gone with the event-loop, once it drops its end of the queue.
+	spawn(move || {
+		for job in jobs {
+			job();
+		}
+	});
+
+	let connected = Connected::default();
+	let shared = Shared::new(&config)?;
+	let closer = Closer::new(Alarm::Reactor(waker.clone()));
+	let heartbeat = config.heartbeat;
+
+	let mut reactor = Reactor {
+		poll,
+		listener,
+		waker,
+		ready,
+		woken,
+		connections: HashMap::new(),
+		next: FIRST,
+		work,
+		paused: None,
+		shared: shared.clone(),
+		heartbeat,
+		closer: closer.clone(),
+		deadline: config.deadline,
+	};
+	let incoming_handler = spawn(move || reactor.run());
+	let heartbeat_handler = beat(heartbeat, &shared, &closer);
+	let compactor = compact(&config, &shared, &closer);
+
+	Ok(Server {
+		incoming_handler,
+		heartbeat_handler,
+		compactor,
+		shared,
+		connected,
+		closer,
+		deadline: config.deadline,
+	})
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Handle {
+	#[inline(always)]
+	pub(super) fn flush(&self) { self.wake(Wake::Flush(self.token)) }
+	#[inline(always)]
+	pub(super) fn cut(&self) { self.wake(Wake::Cut(self.token)) }
+	#[inline(always)]
+	fn resume(&self) { self.wake(Wake::Resume(self.token)) }
+
+	fn wake(&self, wake: Wake) {
+		// NOTE: either fails only once the event-loop is gone.
+		let _ = self
+			.ready
+			.send(wake);
+		let _ = self
+			.waker
+			.wake();
+	}
+}
+
+impl Reactor {
+	fn run(&mut self) -> Result<(), MessageError> {
+		let mut events = Events::with_capacity(EVENTS);
+		// NOTE: woken up often enough to notice any deadline passing.
+		let timeout = [
+			self.heartbeat,
+			self.shared
+				.get_handshake(),
+			self.shared
+				.get_trickle(),
+		]
+		.into_iter()
+		.filter(|duration| !duration.is_zero())
+		.min();
+
+		while !self
+			.closer
+			.is_closing()
+		{
+			let now = Instant::now();
+			let timeout = self
+				.paused
+				.map(|until| until.saturating_duration_since(now))
+				.into_iter()
+				.chain(timeout)
+				.min();
+			match self
+				.poll
+				.poll(&mut events, timeout)
+			{
+				Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+				other => other?,
+			}
+
+			for event in events.iter() {
+				match event.token() {
+					LISTENER if self.paused.is_none() => self.accept(),
+					LISTENER => (),
+					WAKER => (),
+					token => {
+						if event.is_readable() || event.is_read_closed() {
+							self.readable(token);
+						}
+						if event.is_writable() {
+							self.flush(token);
+						}
+					},
+				}
+			}
+
+			let woken: Vec<Wake> = self
+				.woken
+				.try_iter()
+				.collect();
+			for wake in woken {
+				match wake {
+					Wake::Flush(token) => self.flush(token),
+					Wake::Cut(token) => self.close(token),
+					Wake::Resume(token) => self.resume(token),
+				}
+			}
+
+			self.expire();
+			// NOTE: whatever came in, while accepting was held off, is waiting still; no event tells of it again.
+			if self
+				.paused
+				.is_some_and(|until| until <= Instant::now())
+			{
+				self.paused = None;
+				self.accept();
+			}
+		}
+
+		self.drain(&mut events);
+		Ok(())
+	}
+
+	/// Tell every connection, that the server closes, and give them until the
+	/// deadline to receive what is left in their outbox.
+	fn drain(&mut self, events: &mut Events) {
+		self.shared
+			.disband();
+		let closing = Frame::Closing(self.closer.reason());
+		for connection in self
+			.connections
+			.values()
+		{
+			let _ = connection
+				.outbox
+				.post(&closing);
+		}
+
+		let deadline = Instant::now() + self.deadline;
+		loop {
+			let tokens: Vec<Token> = self
+				.connections
+				.keys()
+				.copied()
+				.collect();
+			for token in tokens {
+				self.flush(token);
+				if self
+					.connections
+					.get(&token)
+					.is_some_and(Connection::is_flushed)
+				{
+					self.close(token);
+				}
+			}
+
+			let now = Instant::now();
+			if self
+				.connections
+				.is_empty()
+				|| now >= deadline
+			{
+				break
+			}
+			let Ok(_) = self
+				.poll
+				.poll(events, Some(deadline - now))
+			else {
+				break
+			};
+		}
+
+		let left: Vec<Token> = self
+			.connections
+			.keys()
+			.copied()
+			.collect();
+		for token in left {
+			self.close(token);
+		}
+	}
+
+	fn accept(&mut self) {
+		loop {
+			let (mut stream, addr) = match self
+				.listener
+				.accept()
+			{
+				Ok(accepted) => accepted,
+				Err(error) if error.kind() == ErrorKind::WouldBlock => return,
+				Err(error) if is_transient(&error) => continue,
+				// NOTE: like running out of file-descriptors; trying again at once would only spin.
+				Err(error) => {
+					eprintln!("A connection could not be accepted: {error}");
+					self.paused = Some(Instant::now() + ACCEPT_BACKOFF);
+					return
+				},
+			};
+			let token = Token(self.next);
+			self.next += 1;
+
+			let Ok(_) = self
+				.poll
+				.registry()
+				.register(&mut stream, token, Interest::READABLE | Interest::WRITABLE)
+			else {
+				continue
+			};
+
+			let local = stream
+				.local_addr()
+				.map_or(addr.ip(), |local| local.ip());
+			let attachment = match self
+				.shared
+				.attach(addr.ip(), local)
+			{
+				Ok(attachment) => attachment,
+				Err(error) => {
+					self.shared
+						.refuse(&mut stream, error);
+					continue
+				},
+			};
+			let id = Identifier::default();
+			let handle = Handle {
+				token,
+				ready: self
+					.ready
+					.clone(),
+				waker: self
+					.waker
+					.clone(),
+			};
+			let (outbox, backlog) = self
+				.shared
+				.open(id, Owner::Reactor(handle.clone()));
+			let connection = Connection {
+				stream,
+				attachment,
+				id,
+				nickname: None,
+				outbox,
+				backlog,
+				handle,
+				pending: false,
+				inbound: Vec::new(),
+				outbound: None,
+				accepted: Instant::now(),
+				started: None,
+				heard: Instant::now(),
+			};
+			let policy = Frame::Policy(NicknamePolicy::clone(&self.shared.policy));
+			let Ok(_) = connection
+				.outbox
+				.post(&policy)
+			else {
+				continue
+			};
+			self.connections
+				.insert(token, connection);
+		}
+	}
+
+	fn readable(&mut self, token: Token) {
+		let Some(connection) = self
+			.connections
+			.get_mut(&token)
+		else {
+			return
+		};
+		if connection
+			.read(&self.shared, &self.work)
+			.is_err()
+		{
+			self.close(token);
+		}
+	}
+
+	/// Take on the frames, that came in while the worker was busy for the
+	/// connection, and read on.
+	fn resume(&mut self, token: Token) {
+		let Some(connection) = self
+			.connections
+			.get_mut(&token)
+		else {
+			return
+		};
+		connection.pending = false;
+		connection.heard = Instant::now();
+		if connection
+			.handle(&self.shared, &self.work)
+			.inspect_err(|error| {
+				self.shared
+					.report(error, &connection.outbox)
+			})
+			.is_err()
+		{
+			self.close(token);
+			return
+		}
+		self.readable(token);
+	}
+
+	fn flush(&mut self, token: Token) {
+		let Some(connection) = self
+			.connections
+			.get_mut(&token)
+		else {
+			return
+		};
+		if connection
+			.flush()
+			.is_err()
+		{
+			self.close(token);
+		}
+	}
+
+	fn close(&mut self, token: Token) {
+		let Some(mut connection) = self
+			.connections
+			.remove(&token)
+		else {
+			return
+		};
+		// NOTE: whatever is still queued, like the reason for a refusal, is worth a try.
+		let _ = connection.flush();
+		let _ = self
+			.poll
+			.registry()
+			.deregister(&mut connection.stream);
+		self.shared
+			.leave(connection.id);
+		// NOTE: a descriptor was freed; what is held off may be accepted now.
+		if self
+			.paused
+			.is_some()
+		{
+			self.paused = Some(Instant::now());
+		}
+	}
+
+	/// Close every connection, that missed too many heartbeats.
+	fn expire(&mut self) {
+		let silence = self.heartbeat * Frame::MISSED_HEARTBEATS;
+		let handshake = self
+			.shared
+			.get_handshake();
+		let trickle = self
+			.shared
+			.get_trickle();
+		let expired: Vec<Token> = self
+			.connections
+			.iter()
+			.filter(|(_, connection)| connection.is_overdue(silence, handshake, trickle))
+			.map(|(token, _)| *token)
+			.collect();
+		for token in expired {
+			self.close(token);
+		}
+	}
+}
+
+impl Connection {
+	/// Read everything there is, and act upon every whole frame.
+	fn read(&mut self, shared: &Shared, work: &Sender<Job>) -> Result<(), MessageError> {
+		// NOTE: left in the socket, until the worker is done; read once it is.
+		if self.pending {
+			return Ok(())
+		}
+		let mut chunk = [0; CHUNK];
+		loop {
+			match self
+				.stream
+				.read(&mut chunk)
+			{
+				Ok(0) => break,
+				Ok(length) => {
+					let now = Instant::now();
+					self.heard = now;
+					self.inbound
+						.extend_from_slice(&chunk[.. length]);
+					// NOTE: taken apart right away, so that no more than a frame is ever kept.
+					let handled = self
+						.handle(shared, work)
+						.inspect_err(|error| shared.report(error, &self.outbox))?;
+					self.started = match (self.inbound.is_empty(), handled) {
+						(true, _) => None,
+						(false, 0) => self.started.or(Some(now)),
+						// NOTE: what is left over, started with the last chunk.
+						(false, _) => Some(now),
+					};
+				},
+				Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(()),
+				Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+				Err(error) => Err(classify(error))?,
+			}
+		}
+
+		if self
+			.inbound
+			.is_empty()
+		{
+			Err(MessageError::ConnectionClosed)
+		} else {
+			Err(MessageError::ConnectionInterrupted)
+		}
+	}
+
+	/// Act upon every whole frame, that has been read, and tell how many there
+	/// were.
+	fn handle(&mut self, shared: &Shared, work: &Sender<Job>) -> Result<usize, MessageError> {
+		let mut handled = 0;
+		while !self.pending
+			&& let Some(frame) = self.next_frame(shared.get_max_frame())?
+		{
+			match (&self.nickname, frame) {
+				(None, Frame::Control(token, control)) => {
+					let address = self
+						.attachment
+						.get_address();
+					self.aside(shared, work, move |shared, outbox| {
+						outbox.post(&shared.control(&token, control, address))?;
+						Err(MessageError::ConnectionClosed)
+					})?
+				},
+				(None, frame) => {
+					let nickname = shared.admit(self.id, &self.attachment, frame, &self.outbox)?;
+					shared.welcome(self.id, &nickname, &self.outbox)?;
+					self.nickname = Some(nickname);
+				},
+				(Some(_), Frame::Search(query)) => {
+					let id = self.id;
+					self.aside(shared, work, move |shared, outbox| shared.find(id, &query, outbox))?
+				},
+				(Some(nickname), frame) => shared.handle(self.id, nickname, frame, &self.outbox)?,
+			}
+			// NOTE: answered as they come, so that a burst of requests doesn't overflow the outbox.
+			self.flush()?;
+			handled += 1;
+		}
+		Ok(handled)
+	}
+
+	/// Have the worker do what may take long; the connection is resumed once
+	/// it is done, or closed, if it failed.
+	fn aside(
+		&mut self,
+		shared: &Shared,
+		work: &Sender<Job>,
+		job: impl FnOnce(&Shared, &Outbox) -> Result<(), MessageError> + Send + 'static,
+	) -> Result<(), MessageError> {
+		let (shared, outbox, handle) = (shared.clone(), self
+			.outbox
+			.clone(), self
+			.handle
+			.clone());
+		// NOTE: the worker only ever stops with the event-loop.
+		work.send(Box::new(move || match job(&shared, &outbox) {
+			Ok(()) => handle.resume(),
+			Err(_) => handle.cut(),
+		}))
+		.map_err(|_| MessageError::ConnectionClosed)?;
+		self.pending = true;
+		Ok(())
+	}
+
+	/// Take the next whole frame out of what has been read.
+	///
+	/// A body longer than `limit` is refused, as soon as its head is there.
+	fn next_frame(&mut self, limit: u32) -> Result<Option<Frame>, MessageError> {
+		let Some(head) = self
+			.inbound
+			.first_chunk::<{ Frame::HEAD_LENGTH }>()
+		else {
+			return Ok(None)
+		};
+		let [kind, length @ ..] = *head;
+		let length = <u32>::from_be_bytes(length);
+		if length > limit {
+			Err(MessageError::TooLong(length as usize, limit as usize))?
+		}
+		let end = Frame::HEAD_LENGTH + length as usize;
+		if self.inbound.len() < end {
+			return Ok(None)
+		}
+		let frame = Frame::from_body(kind, &self.inbound[Frame::HEAD_LENGTH .. end]);
+		self.inbound
+			.drain(.. end);
+		Ok(Some(frame?))
+	}
+
+	/// Whether the client has been silent for too long, has not joined in
+	/// time, or is taking too long to finish a frame; zero being forever. None
+	/// is, while the worker is busy for it.
+	fn is_overdue(&self, silence: Duration, handshake: Duration, trickle: Duration) -> bool {
+		let past = |since: Instant, limit: Duration| !limit.is_zero() && since.elapsed() > limit;
+		if self.pending {
+			return false
+		}
+		past(self.heard, silence)
+			|| (self
+				.nickname
+				.is_none()
+				&& past(self.accepted, handshake))
+			|| self
+				.started
+				.is_some_and(|started| past(started, trickle))
+	}
+
+	fn is_flushed(&self) -> bool {
+		self.outbound
+			.as_ref()
+			.is_none_or(|(bytes, written)| *written == bytes.len())
+			&& self
+				.backlog
+				.is_empty()
+	}
+
+	/// Write as much of the outbox, as the socket takes.
+	fn flush(&mut self) -> Result<(), MessageError> {
+		loop {
+			let (bytes, written) = match &mut self.outbound {
+				Some((bytes, written)) if *written < bytes.len() => (bytes, written),
+				_ => {
+					let Some(bytes) = self
+						.backlog
+						.try_next()
+					else {
+						self.outbound = None;
+						return Ok(())
+					};
+					self.outbound = Some((bytes, 0));
+					continue
+				},
+			};
+			match self
+				.stream
+				.write(&bytes[*written ..])
+			{
+				Ok(0) => Err(MessageError::ConnectionInterrupted)?,
+				Ok(length) => *written += length,
+				Err(error) if error.kind() == ErrorKind::WouldBlock => return Ok(()),
+				Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+				Err(error) => Err(classify(error))?,
+			}
+		}
+	}
+}
+
+fn classify(error: io::Error) -> MessageError {
+	match error.kind() {
+		ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::BrokenPipe => {
+			MessageError::ConnectionInterrupted
+		},
+		_ => MessageError::IO(error),
+	}
+}
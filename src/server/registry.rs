@@ -0,0 +1,220 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	collections::HashMap,
+	fs::{self, File, rename},
+	io::{self, BufRead, BufReader, ErrorKind, Write},
+	path::PathBuf,
+};
+
+use besked::Nickname;
+use parking_lot::RwLock;
+use pbkdf2::pbkdf2_hmac;
+use sha2::{Digest, Sha256};
+
+use crate::util::to_hex;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The nicknames, that are registered under a secret, which has to be given
+/// to join with them.
+///
+/// Only a hash of each secret is kept, as PBKDF2 derives it from a salt of
+/// its own; in a file of the data-directory, if the server keeps its history
+/// on disk.
+pub struct Registry {
+	path: Option<PathBuf>,
+	secrets: RwLock<HashMap<Box<str>, Hashed>>,
+}
+
+#[derive(Clone, Copy)]
+struct Hashed {
+	/// How many rounds the hash was derived in; none, for a single salted
+	/// SHA-256, as secrets were hashed before, which is derived anew, once
+	/// its secret is given.
+	rounds: u32,
+	salt: [u8; 16],
+	hash: [u8; 32],
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Registry {
+	/// The name of the file in the data-directory.
+	pub const NAME: &str = "registry";
+	/// How many rounds of PBKDF2-HMAC-SHA256 a secret is hashed in.
+	pub const ROUNDS: u32 = 600_000;
+
+	/// Read back the registry at the given path, if there is one; without a
+	/// path, the registry is gone with the server.
+	pub fn open(path: Option<PathBuf>) -> io::Result<Self> {
+		let mut secrets = HashMap::new();
+		if let Some(path) = &path {
+			match File::open(path) {
+				Ok(file) => {
+					for line in BufReader::new(file).lines() {
+						let line = line?;
+						let invalid = || io::Error::new(ErrorKind::InvalidData, format!("`{line}` is no registration."));
+						let fields: Vec<&str> = line
+							.split('\t')
+							.collect();
+						// NOTE: a line without rounds was written, before there were any.
+						let (nickname, rounds, salt, hash) = match fields[..] {
+							[nickname, rounds, salt, hash] => (
+								nickname,
+								rounds
+									.parse()
+									.ok()
+									.filter(|rounds| *rounds != 0)
+									.ok_or_else(invalid)?,
+								salt,
+								hash,
+							),
+							[nickname, salt, hash] => (nickname, 0, salt, hash),
+							_ => Err(invalid())?,
+						};
+						let (Some(salt), Some(hash)) = (from_hex(salt), from_hex(hash)) else { Err(invalid())? };
+						secrets.insert(nickname.into(), Hashed { rounds, salt, hash });
+					}
+				},
+				Err(error) if error.kind() == ErrorKind::NotFound => (),
+				Err(error) => Err(error)?,
+			}
+		}
+		Ok(Self {
+			path,
+			secrets: RwLock::new(secrets),
+		})
+	}
+
+	#[inline(always)]
+	pub fn is_registered(&self, nickname: &Nickname) -> bool {
+		self.secrets
+			.read()
+			.contains_key(&*key(nickname))
+	}
+
+	/// Whether the given secret lets a client join with the given nickname;
+	/// any will do, if it is not registered.
+	///
+	/// A secret, that was hashed in fewer rounds than are taken by now, is
+	/// hashed anew, once it is given.
+	pub fn admits(&self, nickname: &Nickname, secret: &str) -> bool {
+		let Some(hashed) = self
+			.secrets
+			.read()
+			.get(&*key(nickname))
+			.copied()
+		else {
+			return true
+		};
+		// NOTE: compared in full, so that the time taken tells nothing.
+		let admits = hash(hashed.rounds, &hashed.salt, secret)
+			.iter()
+			.zip(hashed.hash)
+			.fold(0, |difference, (a, b)| difference | (a ^ b))
+			== 0;
+		if admits
+			&& hashed.rounds < Self::ROUNDS
+			&& let Err(error) = self.register(nickname, secret)
+		{
+			eprintln!("The secret of `{nickname}` could not be hashed anew: {error}");
+		}
+		admits
+	}
+
+	/// Register a nickname under the given secret, or change the secret it is
+	/// registered under.
+	pub fn register(&self, nickname: &Nickname, secret: &str) -> io::Result<()> {
+		let mut salt = [0; 16];
+		getrandom::fill(&mut salt)?;
+		let hashed = Hashed {
+			rounds: Self::ROUNDS,
+			salt,
+			hash: hash(Self::ROUNDS, &salt, secret),
+		};
+		let mut secrets = self
+			.secrets
+			.write();
+		let previous = secrets.insert(key(nickname), hashed);
+		if let Err(error) = self.save(&secrets) {
+			match previous {
+				Some(previous) => secrets.insert(key(nickname), previous),
+				None => secrets.remove(&*key(nickname)),
+			};
+			Err(error)?
+		}
+		Ok(())
+	}
+
+	/// Every registration, as its file holds them.
+	#[inline(always)]
+	pub fn snapshot(&self) -> Vec<u8> {
+		encode(&self
+			.secrets
+			.read())
+	}
+
+	/// Write every registration to a file of its own, which then takes the
+	/// place of the previous one.
+	fn save(&self, secrets: &HashMap<Box<str>, Hashed>) -> io::Result<()> {
+		let Some(path) = &self.path else { return Ok(()) };
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+		let saving = path.with_extension("saving");
+		let mut file = File::create(&saving)?;
+		file.write_all(&encode(secrets))?;
+		file.sync_all()?;
+		rename(&saving, path)
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// What a nickname is registered by; regardless of case.
+#[inline(always)]
+pub fn key(nickname: &Nickname) -> Box<str> {
+	nickname
+		.as_str()
+		.to_lowercase()
+		.into()
+}
+
+/// A line for every registration; its nickname, rounds, salt and hash, apart
+/// by tabs.
+fn encode(secrets: &HashMap<Box<str>, Hashed>) -> Vec<u8> {
+	secrets
+		.iter()
+		.map(|(nickname, Hashed { rounds, salt, hash })| {
+			if *rounds == 0 {
+				format!("{nickname}\t{}\t{}\n", to_hex(salt), to_hex(hash))
+			} else {
+				format!("{nickname}\t{rounds}\t{}\t{}\n", to_hex(salt), to_hex(hash))
+			}
+		})
+		.collect::<String>()
+		.into_bytes()
+}
+
+/// Derive the hash of a secret in the given rounds of PBKDF2; or, without
+/// any, the single salted SHA-256, that it was hashed with before.
+fn hash(rounds: u32, salt: &[u8], secret: &str) -> [u8; 32] {
+	if rounds == 0 {
+		return Sha256::new()
+			.chain_update(salt)
+			.chain_update(secret)
+			.finalize()
+			.into()
+	}
+	let mut hash = [0; 32];
+	pbkdf2_hmac::<Sha256>(secret.as_bytes(), salt, rounds, &mut hash);
+	hash
+}
+
+fn from_hex<const N: usize>(text: &str) -> Option<[u8; N]> {
+	if text.len() != N * 2 {
+		return None
+	}
+	let mut bytes = [0; N];
+	for (byte, pair) in bytes
+		.iter_mut()
+		.zip(text.as_bytes().chunks(2))
+	{
+		*byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+	}
+	Some(bytes)
+}
@@ -0,0 +1,137 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::collections::{HashMap, HashSet};
+
+use besked::{Message, SearchQuery};
+use parking_lot::RwLock;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// An inverted index over the history of a server, from every trigram of the
+/// contents, and every nickname, to the sequences of the messages holding it.
+///
+/// Everything is lowercased; the index only ever narrows down which messages
+/// may be hits, whereas [`SearchQuery::matches`] tells which are.
+#[derive(Default)]
+pub struct Index {
+	postings: RwLock<Postings>,
+}
+
+#[derive(Default)]
+struct Postings {
+	trigrams: HashMap<Trigram, Vec<u64>>,
+	authors: HashMap<String, Vec<u64>>,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+type Trigram = [char; 3];
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Index {
+	#[inline(always)]
+	pub fn new() -> Self { Self::default() }
+
+	/// Index a message, that was kept.
+	pub fn add(&self, message: &Message) {
+		let sequence = message.get_sequence();
+		let mut postings = self
+			.postings
+			.write();
+		for trigram in trigrams(message.get_content()) {
+			insert(
+				postings
+					.trigrams
+					.entry(trigram)
+					.or_default(),
+				sequence,
+			);
+		}
+		insert(
+			postings
+				.authors
+				.entry(
+					message
+						.get_nickname()
+						.as_str()
+						.to_lowercase(),
+				)
+				.or_default(),
+			sequence,
+		);
+	}
+
+	/// Forget every message before the given sequence.
+	pub fn forget_before(&self, sequence: u64) {
+		let mut postings = self
+			.postings
+			.write();
+		let Postings { trigrams, authors } = &mut *postings;
+		for postings in trigrams
+			.values_mut()
+			.chain(authors.values_mut())
+		{
+			let dropped = postings.partition_point(|kept| *kept < sequence);
+			postings.drain(.. dropped);
+		}
+		trigrams.retain(|_, postings| !postings.is_empty());
+		authors.retain(|_, postings| !postings.is_empty());
+	}
+
+	/// The sequences of every message, that may be a hit, in order; [`None`],
+	/// if the query is too vague to be looked up, so that any may be.
+	pub fn candidates(&self, query: &SearchQuery) -> Option<Vec<u64>> {
+		let postings = self
+			.postings
+			.read();
+		let mut lists = Vec::new();
+		for trigram in trigrams(query.get_text()) {
+			match postings
+				.trigrams
+				.get(&trigram)
+			{
+				Some(list) => lists.push(list),
+				None => return Some(Vec::new()),
+			}
+		}
+		let nickname = query.get_nickname();
+		if !nickname.is_empty() {
+			match postings
+				.authors
+				.get(&nickname.to_lowercase())
+			{
+				Some(list) => lists.push(list),
+				None => return Some(Vec::new()),
+			}
+		}
+		// NOTE: starting from the shortest list keeps the intersection cheap.
+		lists.sort_by_key(|list| list.len());
+		let (shortest, rest) = lists.split_first()?;
+		Some(shortest
+			.iter()
+			.copied()
+			.filter(|sequence| {
+				rest.iter()
+					.all(|list| {
+						list.binary_search(sequence)
+							.is_ok()
+					})
+			})
+			.collect())
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Every distinct trigram of the given text, once lowercased.
+fn trigrams(text: &str) -> HashSet<Trigram> {
+	let characters: Vec<char> = text
+		.to_lowercase()
+		.chars()
+		.collect();
+	characters
+		.windows(3)
+		.map(|window| [window[0], window[1], window[2]])
+		.collect()
+}
+
+/// Keep the given sequence in its place in a list.
+fn insert(postings: &mut Vec<u64>, sequence: u64) {
+	// NOTE: two messages may be indexed in another order than they were kept in.
+	let at = postings.partition_point(|other| *other < sequence);
+	if postings.get(at) != Some(&sequence) {
+		postings.insert(at, sequence);
+	}
+}
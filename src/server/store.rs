@@ -0,0 +1,497 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	fs::create_dir_all,
+	io,
+	ops::Range,
+	path::{Path, PathBuf},
+	str::FromStr,
+	sync::Arc,
+};
+
+use besked::{Message, Retention, errors::MessageError};
+use chrono::{DateTime, TimeDelta, Utc};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use self::{file::FileStore, memory::MemoryStore, sqlite::SqliteStore};
+use super::format;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A store, as shared between every connection of a server.
+pub type Store = Arc<dyn MessageStore>;
+/// The hash of an entry of the history, which the entry after it is chained
+/// to.
+pub type Link = [u8; 32];
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How many messages are read back in at once, when a store is replayed.
+const REPLAY_CHUNK: u64 = 1024;
+/// What the very first entry is chained to.
+pub const GENESIS: Link = [0; 32];
+/// What the hash of an imported entry ends with.
+const IMPORTED: &[u8] = b"imported";
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Where the history of a server is kept.
+///
+/// Sequences start at one, and are handed out by the store itself, without
+/// any gaps; only the oldest messages are ever dropped.
+///
+/// Every entry holds the [`link`] of the one before it, so that no message
+/// can be changed, or taken out, without breaking the chain.
+pub trait MessageStore: Send + Sync {
+	/// Give a message the next sequence, chain it to the latest, and keep it.
+	fn append(&self, message: Message) -> Result<Message, StoreError>;
+	/// The messages, whose sequences lie within the given range; in order.
+	fn range(&self, sequences: Range<u64>) -> Result<Vec<Message>, StoreError>;
+	/// Like [`range`], but with the link each entry holds; [`None`] for those,
+	/// that were kept before the history was chained.
+	///
+	/// [`range`]: Self::range
+	fn chained(&self, sequences: Range<u64>) -> Result<Vec<(Option<Link>, Message)>, StoreError>;
+	/// The messages, that were written within the given span of time; in the
+	/// order of their sequences.
+	fn range_by_time(&self, span: Range<DateTime<Utc>>) -> Result<Vec<Message>, StoreError>;
+	/// How many messages are kept.
+	fn count(&self) -> Result<u64, StoreError>;
+	/// The sequence of the latest message; zero, if there never was one.
+	fn latest(&self) -> Result<u64, StoreError>;
+	/// The sequence of the oldest message kept; one past the latest, if none
+	/// is.
+	fn first(&self) -> Result<u64, StoreError> {
+		let count = self.count()?;
+		let latest = self.latest()?;
+		(latest + 1)
+			.checked_sub(count)
+			.ok_or(StoreError::Inconsistent(count, latest))
+	}
+	/// The sequence of the latest message, and the link of its entry; which
+	/// the whole history is chained into.
+	fn head(&self) -> Result<(u64, Link), StoreError> {
+		let latest = self.latest()?;
+		Ok((
+			latest,
+			self.chained(latest .. latest + 1)?
+				.first()
+				.map_or(GENESIS, |(previous, message)| link(previous.as_ref(), message)),
+		))
+	}
+
+	/// The messages, that the given retention lets expire by now; oldest
+	/// first.
+	fn expired(&self, retention: &Retention, now: DateTime<Utc>) -> Result<Vec<Message>, StoreError>;
+	/// Forget every message before the given sequence, but never the latest,
+	/// and give back the space they took.
+	fn drop_before(&self, sequence: u64) -> Result<(), StoreError>;
+
+	/// The whole history, taken at once, as the name and the content of the
+	/// file it would be kept in within a data-directory.
+	fn snapshot(&self) -> Result<(&'static str, Vec<u8>), StoreError>;
+}
+
+/// The kinds of store, that a server can keep its history in.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+#[derive(Default)]
+pub enum Storage {
+	/// Gone, once the server is.
+	#[default]
+	Memory,
+	/// An append-only file of message-frames.
+	File,
+	/// An SQLite-database.
+	Sqlite,
+}
+
+/// What [`verify`] found out about the chain of a store.
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct Verification {
+	/// The sequence of the oldest message kept.
+	pub first: u64,
+	/// The sequence of the latest message.
+	pub latest: u64,
+	/// How many of the oldest messages were kept before the history was
+	/// chained.
+	pub unchained: u64,
+	/// The link of the latest entry.
+	pub head: Link,
+	/// The sequence of the first entry, whose link does not match the entry
+	/// before it.
+	pub broken: Option<u64>,
+}
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+	#[error(transparent)]
+	IO(#[from] io::Error),
+	#[error(transparent)]
+	Message(#[from] MessageError),
+	#[error(transparent)]
+	Sqlite(#[from] rusqlite::Error),
+
+	#[error("The stored message of sequence `{0}` is corrupt.")]
+	Corrupt(u64),
+	#[error("The data-directory is of version `{0}`, which is newer than this build knows.")]
+	Newer(u32),
+	#[error("The store holds `{0}` messages, but its latest sequence is `{1}`.")]
+	Inconsistent(u64, u64),
+}
+
+#[derive(Debug, Error)]
+#[error("`{0}` names no store; expected `memory`, `file` or `sqlite`.")]
+pub struct StorageFromStrError(pub Box<str>);
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+pub mod file;
+pub mod memory;
+pub mod sqlite;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Read back every message a store kept, so that the identifiers of their
+/// authors are not handed out again, and hand each to the given closure;
+/// tells how many there were.
+pub fn replay(store: &dyn MessageStore, mut each: impl FnMut(&Message)) -> Result<u64, StoreError> {
+	let count = store.count()?;
+	let latest = store.latest()?;
+	let mut next = store.first()?;
+	while next <= latest {
+		let end = (next + REPLAY_CHUNK).min(latest + 1);
+		for message in store.range(next .. end)? {
+			message
+				.get_identifier()
+				.reserve();
+			each(&message);
+		}
+		next = end;
+	}
+	Ok(count)
+}
+
+/// The hash of an entry, which holds the given message, and is chained to
+/// the entry before it by the given link; [`GENESIS`], if there is none.
+///
+/// Whether the message was imported is part of it, so that no mark can be
+/// put on, or taken off, afterwards.
+pub fn link(previous: Option<&Link>, message: &Message) -> Link {
+	let nickname = message
+		.get_nickname()
+		.as_str();
+	let hash = Sha256::new()
+		.chain_update(previous.unwrap_or(&GENESIS))
+		.chain_update(
+			message
+				.get_sequence()
+				.to_be_bytes(),
+		)
+		.chain_update(
+			message
+				.get_utc()
+				.timestamp()
+				.to_be_bytes(),
+		)
+		.chain_update(u32::from(*message.get_identifier()).to_be_bytes())
+		.chain_update([nickname.len() as u8])
+		.chain_update(nickname)
+		.chain_update(message.get_content());
+	// NOTE: only added for those, that are marked; the links of every other entry stay as they were.
+	let hash = if message.get_imported() { hash.chain_update(IMPORTED) } else { hash };
+	hash.finalize()
+		.into()
+}
+
+/// Recompute the chain of a store, from its oldest entry to the latest, and
+/// tell where it first breaks.
+///
+/// Only entries up to the `legacy` sequence, which were kept before the
+/// history was chained, may go without a link; and only as long as none
+/// before them holds one.
+pub fn verify(store: &dyn MessageStore, legacy: u64) -> Result<Verification, StoreError> {
+	let latest = store.latest()?;
+	let first = store.first()?;
+	let mut verification = Verification {
+		first,
+		latest,
+		unchained: 0,
+		head: GENESIS,
+		broken: None,
+	};
+	let mut chained = false;
+	let mut next = first;
+	while next <= latest {
+		let end = (next + REPLAY_CHUNK).min(latest + 1);
+		for (previous, message) in store.chained(next .. end)? {
+			let sequence = message.get_sequence();
+			let holds = match previous {
+				// NOTE: what the oldest entry kept was chained to, was compacted away; it is taken on trust.
+				Some(previous) => sequence == first || previous == verification.head,
+				None if chained || sequence > legacy => false,
+				None => {
+					verification.unchained += 1;
+					true
+				},
+			};
+			if !holds {
+				verification.broken = Some(sequence);
+				return Ok(verification)
+			}
+			chained |= previous.is_some();
+			verification.head = link(previous.as_ref(), &message);
+		}
+		next = end;
+	}
+	Ok(verification)
+}
+
+/// Where a retention starts keeping `length` messages, oldest first, given
+/// when each was written, and its measure; [`None`] for when, if it was
+/// imported.
+///
+/// The history is taken to be in the order of time, but for imported
+/// messages, which are out of it; so those are as old as the message after
+/// them, or kept, while none is. The latest message is always kept.
+pub fn keep_from(
+	retention: &Retention,
+	now: DateTime<Utc>,
+	length: usize,
+	entry: impl Fn(usize) -> (Option<DateTime<Utc>>, u64),
+) -> usize {
+	if length == 0 {
+		return 0
+	}
+	let mut from = 0;
+	if retention.get_count() != 0 {
+		from = from.max(length.saturating_sub(retention.get_count() as usize));
+	}
+	if !retention
+		.get_age()
+		.is_zero()
+	{
+		let oldest = TimeDelta::from_std(retention.get_age())
+			.ok()
+			.and_then(|age| now.checked_sub_signed(age))
+			.unwrap_or(DateTime::<Utc>::MIN_UTC);
+		let mut index = length;
+		while index > from {
+			if entry(index - 1)
+				.0
+				.is_some_and(|written| written < oldest)
+			{
+				break
+			}
+			index -= 1;
+		}
+		from = from.max(index);
+	}
+	if retention.get_bytes() != 0 {
+		let mut total = 0;
+		let mut index = length;
+		while index > from {
+			total += entry(index - 1).1;
+			if total > retention.get_bytes() {
+				break
+			}
+			index -= 1;
+		}
+		from = from.max(index);
+	}
+	from.min(length - 1)
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Storage {
+	/// Open a store of this kind in the given directory, creating it if need
+	/// be; for a server, that reads frames up to `max_frame` long.
+	pub fn open(self, directory: &Path, max_frame: u32) -> Result<Store, StoreError> {
+		let store: Store = match self {
+			Self::Memory => Arc::new(MemoryStore::new()),
+			Self::File => {
+				create_dir_all(directory)?;
+				format::migrate(directory, max_frame)?;
+				Arc::new(FileStore::open(directory.join(FileStore::NAME), max_frame)?)
+			},
+			Self::Sqlite => {
+				create_dir_all(directory)?;
+				format::migrate(directory, max_frame)?;
+				Arc::new(SqliteStore::open(directory.join(SqliteStore::NAME))?)
+			},
+		};
+		Ok(store)
+	}
+
+	/// Where a store of this kind keeps its history in the given directory;
+	/// [`None`], if it is kept in memory.
+	pub fn path_in(self, directory: &Path) -> Option<PathBuf> {
+		match self {
+			Self::Memory => None,
+			Self::File => Some(directory.join(FileStore::NAME)),
+			Self::Sqlite => Some(directory.join(SqliteStore::NAME)),
+		}
+	}
+}
+
+impl FromStr for Storage {
+	type Err = StorageFromStrError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s
+			.to_lowercase()
+			.as_str()
+		{
+			"memory" | "mem" => Ok(Self::Memory),
+			"file" | "frames" => Ok(Self::File),
+			"sqlite" | "sql" => Ok(Self::Sqlite),
+			_ => Err(StorageFromStrError(s.into())),
+		}
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(test)]
+mod tests {
+	use std::{
+		env::temp_dir,
+		fs::{read, remove_dir_all, write},
+		process,
+	};
+
+	use besked::{Frame, Identifier};
+	use rusqlite::{Connection, params};
+
+	use super::*;
+
+	/// What every test keeps, in order.
+	const CONTENTS: [&str; 3] = ["first", "second", "third"];
+	/// Who writes every message.
+	const NICKNAME: &str = "keeper";
+	const IDENTIFIER: u32 = 7;
+
+	/// A fresh directory of its own for the given test.
+	fn directory(test: &str) -> PathBuf {
+		let directory = temp_dir().join(format!("prata-verify-{}-{test}", process::id()));
+		let _ = remove_dir_all(&directory);
+		create_dir_all(&directory).expect("the directory can be created");
+		directory
+	}
+
+	fn message(content: &str) -> Message {
+		Message::empty()
+			.utc(Utc::now())
+			.identifier(Identifier::from(IDENTIFIER))
+			.nickname(
+				NICKNAME
+					.parse()
+					.unwrap(),
+			)
+			.content(content)
+	}
+
+	fn append_all(store: &dyn MessageStore, contents: &[&str]) {
+		for content in contents {
+			store
+				.append(message(content))
+				.expect("a message can be appended");
+		}
+	}
+
+	/// Verify the given store, which holds every one of [`CONTENTS`], intact.
+	fn assert_intact(store: &dyn MessageStore) {
+		let verification = verify(store, 0).expect("the store can be verified");
+		assert_eq!((verification.first, verification.latest), (1, CONTENTS.len() as u64));
+		assert_eq!(verification.unchained, 0);
+		assert_eq!(verification.broken, None);
+		assert_eq!(verification.head, store.head().expect("the store has a head").1);
+	}
+
+	#[test]
+	fn verifies_memory() {
+		let store = MemoryStore::new();
+		append_all(&store, &CONTENTS);
+		assert_intact(&store);
+
+		store.messages.write()[1]
+			.1
+			.set_content("tampered");
+		// NOTE: an entry is only found out by the link of the one after it.
+		assert_eq!(verify(&store, 0).unwrap().broken, Some(3));
+	}
+
+	#[test]
+	fn verifies_file() {
+		let directory = directory("file");
+		let path = directory.join(FileStore::NAME);
+		append_all(&FileStore::open(&path, Frame::MAX_LENGTH).unwrap(), &CONTENTS);
+		assert_intact(&FileStore::open(&path, Frame::MAX_LENGTH).unwrap());
+
+		let mut bytes = read(&path).unwrap();
+		let at = bytes
+			.windows(CONTENTS[1].len())
+			.position(|window| window == CONTENTS[1].as_bytes())
+			.expect("the content is kept as is");
+		bytes[at] ^= 1;
+		write(&path, bytes).unwrap();
+		let store = FileStore::open(&path, Frame::MAX_LENGTH).unwrap();
+		assert_eq!(verify(&store, 0).unwrap().broken, Some(3));
+		remove_dir_all(directory).unwrap();
+	}
+
+	#[test]
+	fn verifies_sqlite() {
+		let directory = directory("sqlite");
+		let path = directory.join(SqliteStore::NAME);
+		let store = SqliteStore::open(&path).unwrap();
+		append_all(&store, &CONTENTS);
+		assert_intact(&store);
+
+		Connection::open(&path)
+			.unwrap()
+			.execute("UPDATE messages SET content = ?1 WHERE sequence = 2", params!["tampered"])
+			.unwrap();
+		assert_eq!(verify(&store, 0).unwrap().broken, Some(3));
+		drop(store);
+		remove_dir_all(directory).unwrap();
+	}
+
+	#[test]
+	fn accepts_legacy_file() {
+		let directory = directory("legacy-file");
+		let path = directory.join(FileStore::NAME);
+		let mut bytes = Vec::new();
+		for (sequence, content) in (1 ..).zip(&CONTENTS[.. 2]) {
+			bytes.extend(
+				Frame::Message(message(content).sequence(sequence))
+					.to_bytes()
+					.unwrap(),
+			);
+		}
+		write(&path, bytes).unwrap();
+		let store = FileStore::open(&path, Frame::MAX_LENGTH).unwrap();
+		append_all(&store, &CONTENTS[2 ..]);
+
+		let verification = verify(&store, 2).unwrap();
+		assert_eq!((verification.unchained, verification.broken), (2, None));
+		assert_eq!(verify(&store, 1).unwrap().broken, Some(2));
+		remove_dir_all(directory).unwrap();
+	}
+
+	#[test]
+	fn accepts_legacy_sqlite() {
+		let directory = directory("legacy-sqlite");
+		let path = directory.join(SqliteStore::NAME);
+		let store = SqliteStore::open(&path).unwrap();
+		let connection = Connection::open(&path).unwrap();
+		for (sequence, content) in (1 ..).zip(&CONTENTS[.. 2]) {
+			connection
+				.execute(
+					"INSERT INTO messages (sequence, written_on, identifier, nickname, content) VALUES (?1, 0, ?2, ?3, ?4)",
+					params![sequence, IDENTIFIER, NICKNAME, content],
+				)
+				.unwrap();
+		}
+		append_all(&store, &CONTENTS[2 ..]);
+
+		let verification = verify(&store, 2).unwrap();
+		assert_eq!((verification.unchained, verification.broken), (2, None));
+		assert_eq!(verify(&store, 1).unwrap().broken, Some(2));
+		// NOTE: once an entry holds a link, none after it may go without.
+		connection
+			.execute("UPDATE messages SET previous = ?1 WHERE sequence = 1", params![&GENESIS[..]])
+			.unwrap();
+		assert_eq!(verify(&store, 2).unwrap().broken, Some(2));
+		drop(store);
+		remove_dir_all(directory).unwrap();
+	}
+}
@@ -0,0 +1,378 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	fs::{File, OpenOptions, rename},
+	io::{self, BufReader, Read, Seek, SeekFrom, Write},
+	ops::Range,
+	path::{Path, PathBuf},
+};
+
+use besked::{Frame, Message, Nickname, Retention, errors::MessageError};
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+
+use super::{GENESIS, Link, MessageStore, StoreError, keep_from, link};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Keeps every message as a [`Chained`]-frame, appended to a single file.
+///
+/// Only where each frame starts, and when it was written, is held in memory;
+/// the messages themselves are read from the file, when asked for. Files,
+/// that were written before the history was chained, start with plain
+/// [`Message`]-frames.
+///
+/// [`Chained`]: Frame::Chained
+/// [`Message`]: Frame::Message
+pub struct FileStore {
+	log: Mutex<Log>,
+}
+
+/// Messages, that expired, as [`Message`]-frames appended to a file of their
+/// own; never read back by the server.
+///
+/// [`Message`]: Frame::Message
+pub struct Archive {
+	file: Mutex<File>,
+}
+
+struct Log {
+	path: PathBuf,
+	file: File,
+	/// The sequence of the first entry.
+	first: u64,
+	/// One entry per message, by sequence.
+	entries: Vec<Entry>,
+	/// Where the next frame goes.
+	length: u64,
+	/// The link of the latest entry.
+	head: Link,
+	/// The longest body of a frame, that is written or read.
+	limit: u32,
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+	offset: u64,
+	written_on: DateTime<Utc>,
+	/// As measured by [`Retention::measure`].
+	measure: u64,
+	imported: bool,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl FileStore {
+	/// The name of the file in the data-directory.
+	pub const NAME: &str = "messages.frames";
+	/// How much longer the body of a kept frame may be than that of the
+	/// message, as it was read: the link to the entry before, and a nickname,
+	/// that the server puts in place of the sender's.
+	const OVERHEAD: u32 = 32 + Nickname::MAX_LENGTH as u32;
+
+	/// The longest body of a frame, that is kept, by a server, that reads
+	/// frames up to `max_frame` long; never less than by default.
+	#[inline(always)]
+	pub fn limit_for(max_frame: u32) -> u32 {
+		max_frame
+			.max(Frame::MAX_LENGTH)
+			.saturating_add(Self::OVERHEAD)
+	}
+
+	/// Open the file at the given path, creating it if need be, and read back
+	/// where each of its frames starts; for a server, that reads frames up to
+	/// `max_frame` long.
+	pub fn open(path: impl AsRef<Path>, max_frame: u32) -> Result<Self, StoreError> {
+		let limit = Self::limit_for(max_frame);
+		let path = path
+			.as_ref()
+			.to_path_buf();
+		let file = OpenOptions::new()
+			.read(true)
+			.append(true)
+			.create(true)
+			.open(&path)?;
+
+		let mut first = None;
+		let mut entries = Vec::new();
+		let mut head = GENESIS;
+		let mut reader = BufReader::new(&file);
+		let length = loop {
+			let offset = reader.stream_position()?;
+			let (previous, message) = match Frame::recv_limited(&mut reader, limit) {
+				Ok(Frame::Message(message)) => (None, message),
+				Ok(Frame::Chained(previous, message)) => (Some(previous), message),
+				Ok(_) => Err(StoreError::Corrupt(first.unwrap_or(1) + entries.len() as u64))?,
+				Err(MessageError::ConnectionClosed) => break offset,
+				// NOTE: a frame, that was cut short, is what a crash while appending leaves behind.
+				Err(MessageError::ConnectionInterrupted) => {
+					file.set_len(offset)?;
+					break offset
+				},
+				Err(error) => Err(error)?,
+			};
+			// NOTE: the first frame tells where the history starts, after it was compacted.
+			if message.get_sequence() != *first.get_or_insert(message.get_sequence()) + entries.len() as u64 {
+				Err(StoreError::Corrupt(first.unwrap_or(1) + entries.len() as u64))?
+			}
+			head = link(previous.as_ref(), &message);
+			entries.push(Entry::of(&message, offset));
+		};
+
+		Ok(Self {
+			log: Mutex::new(Log {
+				path,
+				file,
+				first: first.unwrap_or(1),
+				entries,
+				length,
+				head,
+				limit,
+			}),
+		})
+	}
+}
+
+impl MessageStore for FileStore {
+	fn append(&self, message: Message) -> Result<Message, StoreError> {
+		let mut log = self
+			.log
+			.lock();
+		let message = message.sequence(log.end());
+		let frame = Frame::Chained(log.head, message);
+		let bytes = frame.to_bytes()?;
+		// NOTE: whatever would not be read back is not written in the first place.
+		let length = bytes.len() - Frame::HEAD_LENGTH;
+		if length > log.limit as usize {
+			Err(MessageError::TooLong(length, log.limit as usize))?
+		}
+		if let Err(error) = log
+			.file
+			.write_all(&bytes)
+		{
+			// NOTE: a frame, that was only written in part, is cut off again, so that the next one starts where it should.
+			let _ = log
+				.file
+				.set_len(log.length);
+			Err(error)?
+		}
+
+		let Frame::Chained(previous, message) = frame else { unreachable!() };
+		let entry = Entry::of(&message, log.length);
+		log.entries
+			.push(entry);
+		log.length += bytes.len() as u64;
+		log.head = link(Some(&previous), &message);
+		Ok(message)
+	}
+
+	fn range(&self, sequences: Range<u64>) -> Result<Vec<Message>, StoreError> {
+		Ok(self
+			.chained(sequences)?
+			.into_iter()
+			.map(|(_, message)| message)
+			.collect())
+	}
+
+	fn chained(&self, sequences: Range<u64>) -> Result<Vec<(Option<Link>, Message)>, StoreError> {
+		let mut log = self
+			.log
+			.lock();
+		let (first, end) = (log.first, log.end());
+		let start = sequences
+			.start
+			.clamp(first, end);
+		let stop = sequences
+			.end
+			.clamp(start, end);
+		log.read(start .. stop)
+	}
+
+	fn range_by_time(&self, span: Range<DateTime<Utc>>) -> Result<Vec<Message>, StoreError> {
+		let mut log = self
+			.log
+			.lock();
+		let (first, end) = (log.first, log.end());
+		let mut messages = Vec::new();
+		let mut sequence = first;
+		// NOTE: whatever lies in the span is read in runs, to seek as little as possible.
+		while sequence < end {
+			let within = |sequence: u64| span.contains(&log.entries[(sequence - first) as usize].written_on);
+			if !within(sequence) {
+				sequence += 1;
+				continue
+			}
+			let start = sequence;
+			while sequence < end && within(sequence) {
+				sequence += 1;
+			}
+			messages.extend(
+				log.read(start .. sequence)?
+					.into_iter()
+					.map(|(_, message)| message),
+			);
+		}
+		Ok(messages)
+	}
+
+	fn count(&self) -> Result<u64, StoreError> {
+		Ok(self
+			.log
+			.lock()
+			.entries
+			.len() as u64)
+	}
+
+	fn latest(&self) -> Result<u64, StoreError> {
+		Ok(self
+			.log
+			.lock()
+			.end()
+			- 1)
+	}
+
+	fn expired(&self, retention: &Retention, now: DateTime<Utc>) -> Result<Vec<Message>, StoreError> {
+		let mut log = self
+			.log
+			.lock();
+		let from = keep_from(retention, now, log.entries.len(), |index| {
+			let entry = log.entries[index];
+			((!entry.imported).then_some(entry.written_on), entry.measure)
+		});
+		let first = log.first;
+		Ok(log
+			.read(first .. first + from as u64)?
+			.into_iter()
+			.map(|(_, message)| message)
+			.collect())
+	}
+
+	fn head(&self) -> Result<(u64, Link), StoreError> {
+		let log = self
+			.log
+			.lock();
+		Ok((log.end() - 1, log.head))
+	}
+
+	fn drop_before(&self, sequence: u64) -> Result<(), StoreError> {
+		let mut log = self
+			.log
+			.lock();
+		let dropped = (sequence.saturating_sub(log.first) as usize).min(log.entries.len().saturating_sub(1));
+		if dropped == 0 {
+			return Ok(())
+		}
+		log.compact(dropped)?;
+		Ok(())
+	}
+
+	fn snapshot(&self) -> Result<(&'static str, Vec<u8>), StoreError> {
+		let log = self
+			.log
+			.lock();
+		let mut bytes = Vec::with_capacity(log.length as usize);
+		let mut file = &log.file;
+		file.seek(SeekFrom::Start(0))?;
+		file.take(log.length)
+			.read_to_end(&mut bytes)?;
+		Ok((Self::NAME, bytes))
+	}
+}
+
+impl Archive {
+	/// The name of the file in the data-directory.
+	pub const NAME: &str = "archive.frames";
+
+	pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+		Ok(Self {
+			file: Mutex::new(
+				OpenOptions::new()
+					.append(true)
+					.create(true)
+					.open(path)?,
+			),
+		})
+	}
+
+	/// Append the given messages, under the sequences they had.
+	pub fn append(&self, messages: &[Message]) -> Result<(), StoreError> {
+		let mut bytes = Vec::new();
+		for message in messages {
+			bytes.extend(Frame::Message(message.clone()).to_bytes()?);
+		}
+		let mut file = self
+			.file
+			.lock();
+		file.write_all(&bytes)?;
+		file.sync_data()?;
+		Ok(())
+	}
+}
+
+impl Log {
+	/// The sequence, that the next message gets.
+	#[inline(always)]
+	fn end(&self) -> u64 { self.first + self.entries.len() as u64 }
+
+	/// Read the messages of a range of sequences, that are all known to be
+	/// there, with the links their entries hold.
+	fn read(&mut self, sequences: Range<u64>) -> Result<Vec<(Option<Link>, Message)>, StoreError> {
+		let Some(first) = self
+			.entries
+			.get((sequences.start - self.first) as usize)
+		else {
+			return Ok(Vec::new())
+		};
+		let mut reader = BufReader::new(&self.file);
+		reader.seek(SeekFrom::Start(first.offset))?;
+		sequences
+			.map(|sequence| match Frame::recv_limited(&mut reader, self.limit)? {
+				Frame::Message(message) if message.get_sequence() == sequence => Ok((None, message)),
+				Frame::Chained(previous, message) if message.get_sequence() == sequence => Ok((Some(previous), message)),
+				_ => Err(StoreError::Corrupt(sequence)),
+			})
+			.collect()
+	}
+
+	/// Drop the given amount of entries from the front, by writing the rest to
+	/// a file of its own, which then takes the place of the log.
+	fn compact(&mut self, dropped: usize) -> io::Result<()> {
+		let start = self.entries[dropped].offset;
+		let path = self
+			.path
+			.with_extension("compacting");
+		let mut compacted = OpenOptions::new()
+			.write(true)
+			.create(true)
+			.truncate(true)
+			.open(&path)?;
+		self.file
+			.seek(SeekFrom::Start(start))?;
+		io::copy(&mut self.file, &mut compacted)?;
+		compacted.sync_all()?;
+		// NOTE: renaming is atomic; after a crash, the log is either whole or compacted.
+		rename(&path, &self.path)?;
+
+		self.file = OpenOptions::new()
+			.read(true)
+			.append(true)
+			.open(&self.path)?;
+		self.entries
+			.drain(.. dropped);
+		for entry in &mut self.entries {
+			entry.offset -= start;
+		}
+		self.entries
+			.shrink_to_fit();
+		self.first += dropped as u64;
+		self.length -= start;
+		Ok(())
+	}
+}
+
+impl Entry {
+	#[inline(always)]
+	fn of(message: &Message, offset: u64) -> Self {
+		Self {
+			offset,
+			written_on: message.get_utc(),
+			measure: Retention::measure(message),
+			imported: message.get_imported(),
+		}
+	}
+}
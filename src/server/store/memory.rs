@@ -0,0 +1,140 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::ops::Range;
+
+use besked::{Frame, Message, Retention};
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+
+use super::{GENESIS, Link, MessageStore, StoreError, file::FileStore, keep_from, link};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Keeps every message in memory; the history is gone with the server.
+#[derive(Default)]
+pub struct MemoryStore {
+	/// Every message, after the link of the entry before it.
+	pub(super) messages: RwLock<Vec<(Link, Message)>>,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl MemoryStore {
+	#[inline(always)]
+	pub fn new() -> Self { Self::default() }
+}
+
+/// The sequence of the oldest message kept.
+#[inline(always)]
+fn first(messages: &[(Link, Message)]) -> u64 {
+	messages
+		.first()
+		.map_or(1, |(_, message)| message.get_sequence())
+}
+
+/// The indices of the messages, whose sequences lie within the given range.
+fn within(messages: &[(Link, Message)], sequences: Range<u64>) -> Range<usize> {
+	let first = first(messages);
+	let end = first + messages.len() as u64;
+	let start = sequences
+		.start
+		.clamp(first, end);
+	let stop = sequences
+		.end
+		.clamp(start, end);
+	(start - first) as usize .. (stop - first) as usize
+}
+
+impl MessageStore for MemoryStore {
+	fn append(&self, message: Message) -> Result<Message, StoreError> {
+		let mut messages = self
+			.messages
+			.write();
+		let message = message.sequence(first(&messages) + messages.len() as u64);
+		let previous = messages
+			.last()
+			.map(|(previous, last)| link(Some(previous), last));
+		messages.push((previous.unwrap_or(GENESIS), message.clone()));
+		Ok(message)
+	}
+
+	fn range(&self, sequences: Range<u64>) -> Result<Vec<Message>, StoreError> {
+		let messages = self
+			.messages
+			.read();
+		Ok(messages[within(&messages, sequences)]
+			.iter()
+			.map(|(_, message)| message.clone())
+			.collect())
+	}
+
+	fn chained(&self, sequences: Range<u64>) -> Result<Vec<(Option<Link>, Message)>, StoreError> {
+		let messages = self
+			.messages
+			.read();
+		Ok(messages[within(&messages, sequences)]
+			.iter()
+			.map(|(previous, message)| (Some(*previous), message.clone()))
+			.collect())
+	}
+
+	fn range_by_time(&self, span: Range<DateTime<Utc>>) -> Result<Vec<Message>, StoreError> {
+		Ok(self
+			.messages
+			.read()
+			.iter()
+			.map(|(_, message)| message)
+			.filter(|message| span.contains(&message.get_utc()))
+			.cloned()
+			.collect())
+	}
+
+	fn count(&self) -> Result<u64, StoreError> {
+		Ok(self
+			.messages
+			.read()
+			.len() as u64)
+	}
+
+	fn latest(&self) -> Result<u64, StoreError> {
+		Ok(self
+			.messages
+			.read()
+			.last()
+			.map_or(0, |(_, message)| message.get_sequence()))
+	}
+
+	fn expired(&self, retention: &Retention, now: DateTime<Utc>) -> Result<Vec<Message>, StoreError> {
+		let messages = self
+			.messages
+			.read();
+		let from = keep_from(retention, now, messages.len(), |index| {
+			let (_, message) = &messages[index];
+			(
+				(!message.get_imported()).then(|| message.get_utc()),
+				Retention::measure(message),
+			)
+		});
+		Ok(messages[.. from]
+			.iter()
+			.map(|(_, message)| message.clone())
+			.collect())
+	}
+
+	fn drop_before(&self, sequence: u64) -> Result<(), StoreError> {
+		let mut messages = self
+			.messages
+			.write();
+		let dropped = (sequence.saturating_sub(first(&messages)) as usize).min(messages.len().saturating_sub(1));
+		messages.drain(.. dropped);
+		messages.shrink_to_fit();
+		Ok(())
+	}
+
+	fn snapshot(&self) -> Result<(&'static str, Vec<u8>), StoreError> {
+		let mut bytes = Vec::new();
+		for (previous, message) in self
+			.messages
+			.read()
+			.iter()
+		{
+			bytes.extend(Frame::Chained(*previous, message.clone()).to_bytes()?);
+		}
+		Ok((FileStore::NAME, bytes))
+	}
+}
@@ -0,0 +1,302 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	fs::{read, remove_file},
+	io::ErrorKind,
+	ops::Range,
+	path::{Path, PathBuf},
+};
+
+use besked::{Identifier, Message, Retention};
+use chrono::{DateTime, TimeDelta, Utc};
+use parking_lot::Mutex;
+use rusqlite::{Connection, Row, params};
+
+use super::{GENESIS, Link, MessageStore, StoreError, link};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Keeps every message as a row of an SQLite-database.
+pub struct SqliteStore {
+	path: PathBuf,
+	connection: Mutex<Connection>,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+const SCHEMA: &str = "
+	PRAGMA auto_vacuum = INCREMENTAL;
+	PRAGMA journal_mode = WAL;
+	CREATE TABLE IF NOT EXISTS messages (
+		sequence   INTEGER PRIMARY KEY,
+		written_on INTEGER NOT NULL,
+		identifier INTEGER NOT NULL,
+		nickname   TEXT    NOT NULL,
+		content    TEXT    NOT NULL,
+		previous   BLOB,
+		imported   INTEGER NOT NULL DEFAULT 0
+	);
+	CREATE INDEX IF NOT EXISTS messages_by_time ON messages (written_on);
+";
+const COLUMNS: &str = "sequence, written_on, identifier, nickname, content, previous, imported";
+const WITHIN: &str = "sequence >= ?1 AND sequence < ?2";
+/// What a row counts towards the limit of bytes; as [`Retention::measure`].
+const MEASURE: &str = "length(CAST(nickname AS BLOB)) + length(CAST(content AS BLOB))";
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl SqliteStore {
+	/// The name of the database in the data-directory.
+	pub const NAME: &str = "messages.sqlite";
+
+	/// Open the database at the given path, creating it if need be.
+	pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+		let path = path
+			.as_ref()
+			.to_path_buf();
+		let connection = Connection::open(&path)?;
+		connection.execute_batch(SCHEMA)?;
+		Ok(Self {
+			path,
+			connection: Mutex::new(connection),
+		})
+	}
+
+	/// Give the database at the given path, that was created before the
+	/// history was chained, a column for the links; the messages it already
+	/// kept hold none. Tells the sequence of the latest of them.
+	pub fn chain(path: impl AsRef<Path>) -> Result<u64, StoreError> {
+		let connection = Connection::open(path)?;
+		let chained: bool = connection.query_row(
+			"SELECT COUNT(*) > 0 FROM pragma_table_info('messages') WHERE name = 'previous'",
+			[],
+			|row| row.get(0),
+		)?;
+		if !chained {
+			connection.execute_batch("ALTER TABLE messages ADD COLUMN previous BLOB")?;
+		}
+		let latest: i64 = connection.query_row("SELECT COALESCE(MAX(sequence), 0) FROM messages", [], |row| row.get(0))?;
+		Ok(latest as u64)
+	}
+
+	/// Give the database at the given path, that was created before messages
+	/// could be imported, a column to mark them by; none of those it already
+	/// kept are.
+	pub fn mark(path: impl AsRef<Path>) -> Result<(), StoreError> {
+		let connection = Connection::open(path)?;
+		let marked: bool = connection.query_row(
+			"SELECT COUNT(*) > 0 FROM pragma_table_info('messages') WHERE name = 'imported'",
+			[],
+			|row| row.get(0),
+		)?;
+		if !marked {
+			connection.execute_batch("ALTER TABLE messages ADD COLUMN imported INTEGER NOT NULL DEFAULT 0")?;
+		}
+		Ok(())
+	}
+
+	/// The messages, that the given statement selects with the given bounds.
+	fn select(&self, filter: &str, from: i64, to: i64) -> Result<Vec<Message>, StoreError> {
+		Ok(select(
+			&self
+				.connection
+				.lock(),
+			filter,
+			from,
+			to,
+		)?
+		.into_iter()
+		.map(|(_, message)| message)
+		.collect())
+	}
+}
+
+impl MessageStore for SqliteStore {
+	fn append(&self, message: Message) -> Result<Message, StoreError> {
+		let connection = self
+			.connection
+			.lock();
+		// NOTE: the lock keeps any other append from taking the same sequence.
+		let sequence: i64 =
+			connection.query_row("SELECT COALESCE(MAX(sequence), 0) + 1 FROM messages", [], |row| row.get(0))?;
+		let message = message.sequence(sequence as u64);
+		let previous = select(&connection, WITHIN, sequence - 1, sequence)?
+			.first()
+			.map_or(GENESIS, |(previous, latest)| link(previous.as_ref(), latest));
+		connection.execute(&format!("INSERT INTO messages ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"), params![
+			sequence,
+			message
+				.get_utc()
+				.timestamp(),
+			u32::from(*message.get_identifier()),
+			message
+				.get_nickname()
+				.as_str(),
+			message.get_content(),
+			&previous[..],
+			message.get_imported(),
+		])?;
+		Ok(message)
+	}
+
+	fn range(&self, sequences: Range<u64>) -> Result<Vec<Message>, StoreError> {
+		self.select(
+			WITHIN,
+			sequences
+				.start
+				.min(i64::MAX as u64) as i64,
+			sequences
+				.end
+				.min(i64::MAX as u64) as i64,
+		)
+	}
+
+	fn chained(&self, sequences: Range<u64>) -> Result<Vec<(Option<Link>, Message)>, StoreError> {
+		select(
+			&self
+				.connection
+				.lock(),
+			WITHIN,
+			sequences
+				.start
+				.min(i64::MAX as u64) as i64,
+			sequences
+				.end
+				.min(i64::MAX as u64) as i64,
+		)
+	}
+
+	fn range_by_time(&self, span: Range<DateTime<Utc>>) -> Result<Vec<Message>, StoreError> {
+		self.select(
+			"written_on >= ?1 AND written_on < ?2",
+			span.start
+				.timestamp(),
+			span.end
+				.timestamp(),
+		)
+	}
+
+	fn count(&self) -> Result<u64, StoreError> {
+		let count: i64 = self
+			.connection
+			.lock()
+			.query_row("SELECT COUNT(*) FROM messages", [], |row| row.get(0))?;
+		Ok(count as u64)
+	}
+
+	fn latest(&self) -> Result<u64, StoreError> {
+		let latest: i64 = self
+			.connection
+			.lock()
+			.query_row("SELECT COALESCE(MAX(sequence), 0) FROM messages", [], |row| row.get(0))?;
+		Ok(latest as u64)
+	}
+
+	fn expired(&self, retention: &Retention, now: DateTime<Utc>) -> Result<Vec<Message>, StoreError> {
+		let keep = {
+			let connection = self
+				.connection
+				.lock();
+			let latest: Option<i64> = connection.query_row("SELECT MAX(sequence) FROM messages", [], |row| row.get(0))?;
+			let Some(latest) = latest else { return Ok(Vec::new()) };
+			let mut keep = 0;
+			if retention.get_count() != 0 {
+				keep = keep.max(latest.saturating_sub(retention.get_count() as i64) + 1);
+			}
+			if !retention
+				.get_age()
+				.is_zero()
+			{
+				let oldest = TimeDelta::from_std(retention.get_age())
+					.ok()
+					.and_then(|age| now.checked_sub_signed(age))
+					.map_or(i64::MIN, |oldest| oldest.timestamp());
+				// NOTE: imported messages are out of the order of time; they go with the first message after them, that expires.
+				let expired: Option<i64> = connection.query_row(
+					"SELECT MAX(sequence) FROM messages WHERE imported = 0 AND written_on < ?1",
+					[oldest],
+					|row| row.get(0),
+				)?;
+				keep = keep.max(expired.map_or(0, |expired| expired + 1));
+			}
+			if retention.get_bytes() != 0 {
+				let totals = format!("SELECT sequence, SUM({MEASURE}) OVER (ORDER BY sequence DESC) AS total FROM messages");
+				let first: Option<i64> = connection.query_row(
+					&format!("SELECT MIN(sequence) FROM ({totals}) WHERE total <= ?1"),
+					[retention.get_bytes() as i64],
+					|row| row.get(0),
+				)?;
+				keep = keep.max(first.unwrap_or(latest));
+			}
+			keep.min(latest)
+		};
+		self.range(0 .. keep as u64)
+	}
+
+	fn drop_before(&self, sequence: u64) -> Result<(), StoreError> {
+		let connection = self
+			.connection
+			.lock();
+		connection.execute(
+			"DELETE FROM messages WHERE sequence < ?1 AND sequence < (SELECT MAX(sequence) FROM messages)",
+			[sequence.min(i64::MAX as u64) as i64],
+		)?;
+		connection.execute_batch("PRAGMA incremental_vacuum;")?;
+		Ok(())
+	}
+
+	fn snapshot(&self) -> Result<(&'static str, Vec<u8>), StoreError> {
+		let snapshot = self
+			.path
+			.with_extension("snapshot");
+		match remove_file(&snapshot) {
+			Err(error) if error.kind() != ErrorKind::NotFound => Err(error)?,
+			_ => (),
+		}
+		// NOTE: a copy, that is consistent within a single transaction, even while the log is written.
+		self.connection
+			.lock()
+			.execute("VACUUM INTO ?1", [snapshot
+				.to_string_lossy()
+				.as_ref()])?;
+		let bytes = read(&snapshot);
+		remove_file(&snapshot)?;
+		Ok((Self::NAME, bytes?))
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The messages, that the given statement selects with the given bounds, with
+/// the links their rows hold.
+fn select(connection: &Connection, filter: &str, from: i64, to: i64) -> Result<Vec<(Option<Link>, Message)>, StoreError> {
+	let mut statement =
+		connection.prepare_cached(&format!("SELECT {COLUMNS} FROM messages WHERE {filter} ORDER BY sequence"))?;
+	let rows = statement.query_map(params![from, to], |row| Ok(from_row(row)))?;
+	let mut messages = Vec::new();
+	for row in rows {
+		messages.push(row??);
+	}
+	Ok(messages)
+}
+
+fn from_row(row: &Row<'_>) -> Result<(Option<Link>, Message), StoreError> {
+	let sequence: i64 = row.get(0)?;
+	let written_on: i64 = row.get(1)?;
+	let identifier: u32 = row.get(2)?;
+	let nickname: String = row.get(3)?;
+	let content: String = row.get(4)?;
+	let previous: Option<Vec<u8>> = row.get(5)?;
+	let imported: bool = row.get(6)?;
+
+	let corrupt = || StoreError::Corrupt(sequence as u64);
+	let previous = match previous {
+		Some(previous) => Some(
+			Link::try_from(previous.as_slice())
+				.map_err(|_| corrupt())?,
+		),
+		None => None,
+	};
+	Ok((previous, Message::empty()
+		.sequence(sequence as u64)
+		.utc(DateTime::from_timestamp(written_on, 0).ok_or_else(corrupt)?)
+		.identifier(Identifier::from(identifier))
+		.nickname(
+			nickname
+				.parse()
+				.map_err(|_| corrupt())?,
+		)
+		.content(content)
+		.imported(imported)))
+}
@@ -167,6 +167,10 @@ macro_rules! impl_ipv4ns {
 		)+)+
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 		$(
+			#[derive(::core::fmt::Debug)]
+			#[derive(::core::cmp::Eq, ::core::cmp::PartialEq, ::core::cmp::Ord, ::core::cmp::PartialOrd)]
+			#[derive(::core::clone::Clone, ::core::marker::Copy)]
+			#[derive(::core::hash::Hash)]
 			$(#[$netspace_attr])*
 			pub enum $netspace {
 				$($short($long)),+
@@ -181,6 +185,8 @@ macro_rules! impl_ipv4ns {
 		pub enum $try_from_error {
 			#[error(r#"The IPv4. address "{0}" is not contained within any relative net.-space."#)]
 			NotInRange(::std::net::Ipv4Addr),
+			#[error(r#""{0}" names no net.-space."#)]
+			Unnamed(::std::boxed::Box<::core::primitive::str>),
 		}
 
 		#[derive(::core::fmt::Debug)]
@@ -413,6 +419,15 @@ macro_rules! impl_ipv4ns {
 					pub const fn [<$filter _const>](addr: &::core::net::Ipv4Addr) -> bool {
 						$($long::[<$filter _const>](addr))||+
 					}
+
+					/// Check whether the address lies within this very subspace,
+					/// rather than within any of the net.-space.
+					#[inline(always)]
+					pub const fn includes(&self, addr: &::core::net::Ipv4Addr) -> bool {
+						match self {
+							$(Self::$short(_) => $long::[<$filter _const>](addr)),+
+						}
+					}
 				}
 
 				impl $trait for $netspace {
@@ -449,6 +464,31 @@ macro_rules! impl_ipv4ns {
 				}
 			}
 
+			// NOTE: read by either name of the subspace, with or without the net.-space before it.
+			impl ::core::str::FromStr for $netspace {
+				type Err = $try_from_error;
+
+				fn from_str(s: &::core::primitive::str) -> ::core::result::Result<Self, Self::Err> {
+					let name = s
+						.strip_prefix(::core::concat!(::core::stringify!($netspace), "::"))
+						.unwrap_or(s);
+					$(
+						if name == ::core::stringify!($short) || name == ::core::stringify!($long) {
+							return ::core::result::Result::Ok(Self::$short($long))
+						}
+					)+
+					::core::result::Result::Err(Self::Err::Unnamed(s.into()))
+				}
+			}
+
+			impl ::core::fmt::Display for $netspace {
+				fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+					match self {
+						$(Self::$short(_) => f.write_str(::core::concat!(::core::stringify!($netspace), "::", ::core::stringify!($short)))),+
+					}
+				}
+			}
+
 			impl ::core::iter::IntoIterator for $netspace {
 				type Item = <Self::IntoIter as ::core::iter::Iterator>::Item;
 				type IntoIter = $iterator;
@@ -2,14 +2,20 @@
 use std::{
 	io,
 	net::{IpAddr, Ipv4Addr, Ipv6Addr},
+	path::PathBuf,
 	result,
 };
 
-use besked::errors::MessageError;
+use besked::errors::{MessageError, ModerateFromStrError};
 use local_ip_address::{Error as ResolveError, local_ip};
 use thiserror::Error;
 
-use crate::{invoke::FromCallError, proto::spaces::FromIPv4Error};
+use crate::{
+	client::JoinError,
+	invoke::FromCallError,
+	server::{HostError, backup::BackupError, store::StoreError},
+	spaces::FromIPv4Error,
+};
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 mod macro_def {
 	#[macro_export]
@@ -33,6 +39,36 @@ pub enum AllErrors {
 
 	#[error("{0}")]
 	FromIPv4(#[from] FromIPv4Error),
+
+	#[error("{0}")]
+	Message(#[from] MessageError),
+	#[error("{0}")]
+	Join(#[from] JoinError),
+
+	#[error("{0}")]
+	Signal(#[from] ctrlc::Error),
+
+	#[error("{0}")]
+	Host(#[from] HostError),
+	#[error("{0}")]
+	Store(#[from] StoreError),
+	#[error("{0}")]
+	Backup(#[from] BackupError),
+	#[error("{0}")]
+	Moderate(#[from] ModerateFromStrError),
+
+	#[error("No server could be found on port `{0}`.")]
+	NoServer(u16),
+	#[error("A history, that is kept in memory, cannot be verified.")]
+	Unverifiable,
+	#[error("No history is kept at `{}`.", .0.display())]
+	NoLog(PathBuf),
+	#[error("No server, that keeps its history on disk, runs on `{}`.", .0.display())]
+	NoControl(PathBuf),
+	#[error("The chain is broken at sequence `{0}`; its entry does not hold the link of the one before it.")]
+	Chain(u64),
+	#[error("{0}")]
+	Time(String),
 }
 
 #[derive(Debug, Error)]
@@ -51,3 +87,11 @@ pub fn local_v4ip() -> result::Result<Ipv4Addr, DetermineAddressError> {
 		IpAddr::V6(v6) => Err(DetermineAddressError::NotIPv4(v6))?,
 	}
 }
+
+/// The given bytes as lowercase hexadecimal digits.
+pub fn to_hex(bytes: &[u8]) -> String {
+	bytes
+		.iter()
+		.map(|byte| format!("{byte:02x}"))
+		.collect()
+}
@@ -1 +1,697 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+use std::{
+	collections::{HashMap, VecDeque},
+	mem::take,
+	time::{Duration, Instant},
+};
 
+use besked::{Delivery, Frame, HistoryQuery, Identifier, Moderate, Nickname, SearchQuery, State, Transcript, errors::MessageError};
+use chrono::{DateTime, Local, NaiveDate, TimeDelta, Utc};
+use ratatui::{
+	DefaultTerminal,
+	Frame as Screen,
+	crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+	layout::{Constraint, Layout, Position},
+	text::Line,
+	widgets::{Block, Paragraph},
+};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+	client::{Client, TYPING_INTERVAL},
+	export::{Export, Progress},
+	util,
+};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How long a typing-indicator is shown, without an update.
+pub const TYPING_TIMEOUT: Duration = TYPING_INTERVAL.saturating_mul(2);
+/// How long to wait for input, before looking for new frames.
+const TICK: Duration = Duration::from_millis(100);
+/// What is told, when an export is asked for, while another is under way.
+pub const EXPORTING: &str = "An export is under way already; wait for it to finish.";
+/// The commands, that are sent to the server as moderations; operators are
+/// granted through its control channel alone.
+const MODERATIONS: [&str; 7] = ["who", "kick", "mute", "unmute", "ban", "unban", "bans"];
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// What became of a line of input.
+pub enum Executed {
+	Command,
+	/// The line was sent as a message.
+	Message,
+	/// The line was sent as a direct message to the given nickname, with the
+	/// given content.
+	Direct(Nickname, String),
+	/// Something the user should be told.
+	Notice(String),
+	/// The history was asked for, to be exported.
+	Export(Export),
+}
+
+/// What is known about a message, that was sent by this client.
+#[derive(Clone, Copy)]
+enum Receipt {
+	Sent,
+	/// Acknowledged by the server under the given sequence.
+	Delivered(u64),
+	/// Refused by the server, and never sent on.
+	Refused,
+}
+
+/// A line, as the terminal interface shows it.
+struct Entry {
+	text: String,
+	receipt: Option<Receipt>,
+	/// The sequence of the message, that the line is a part of.
+	sequence: Option<u64>,
+}
+
+/// Everything the terminal interface shows.
+#[derive(Default)]
+struct View {
+	lines: Vec<Entry>,
+	input: String,
+	/// Who is typing, and when they were last known to be.
+	typing: HashMap<Identifier, (Nickname, Instant)>,
+
+	/// Lines of messages, that are yet to be acknowledged; oldest first.
+	pending: VecDeque<usize>,
+	/// The last sequence each user has read.
+	reads: HashMap<Identifier, u64>,
+	/// Whether to tell others, what has been read.
+	receipts: bool,
+	/// The identifier of this client, whose own reads don't count.
+	own: Option<Identifier>,
+
+	/// The first line shown, if scrolled away from the latest.
+	top: Option<usize>,
+	/// How many lines were shown at once, when last drawn.
+	height: usize,
+	/// The sequences of the hits of the last search; in the order listed.
+	hits: Vec<u64>,
+	/// The hit, that is yet to arrive from the history, to be jumped to.
+	jumping: Option<u64>,
+	/// The transcript, whose pages of history are yet to arrive.
+	export: Option<Export>,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Describe a frame from the server in a single line of text, if there is
+/// anything to tell about it.
+pub fn describe(frame: &Frame) -> Option<String> {
+	let text = match frame {
+		Frame::Message(message) => format!(
+			"#{} {:#}: {}",
+			message.get_identifier(),
+			message.get_nickname(),
+			message.get_content()
+		),
+		Frame::Direct(to, message) => format!(
+			"[{}] #{} {:#} → {to:#}: {}",
+			message
+				.get_local()
+				.format("%Y-%m-%d %H:%M"),
+			message.get_identifier(),
+			message.get_nickname(),
+			message.get_content()
+		),
+		Frame::Delivery(to, Delivery::Delivered) => format!("Delivered to {to:#}."),
+		Frame::Delivery(to, Delivery::Queued) => format!("Kept for {to:#}, until they connect."),
+		Frame::Registered(nickname) => format!("{nickname:#} is registered; join with its secret from now on."),
+		Frame::Backfill(message) | Frame::Hit(message) => format!(
+			"[{}] #{} {:#}: {}{}",
+			message
+				.get_local()
+				.format("%Y-%m-%d %H:%M"),
+			message.get_identifier(),
+			message.get_nickname(),
+			message.get_content(),
+			if message.get_imported() { " (imported)" } else { "" }
+		),
+		Frame::Profile(id, profile) => format!(
+			"#{id}: {} ({}) [{}] {}\n\t{}",
+			profile.get_display_name(),
+			profile.get_pronouns(),
+			profile.get_timezone(),
+			profile.get_status(),
+			profile.get_bio()
+		),
+		Frame::Presence(presence) => format!("#{} {presence}", presence.get_identifier()),
+		Frame::Roster(roster) => roster
+			.iter()
+			.map(|presence| format!("#{} {presence}", presence.get_identifier()))
+			.collect::<Vec<_>>()
+			.join("\n"),
+		Frame::Error(error) => error.to_string(),
+		Frame::Refused(error) => format!("A message was refused: {error}"),
+		Frame::Retention(retention) => format!("The server keeps its history {retention}."),
+		Frame::Found(_, 0) => String::from("Nothing was found."),
+		Frame::Found(_, 1) => String::from("Found 1 message."),
+		Frame::Found(sent, total) if sent == total => format!("Found {total} messages."),
+		Frame::Found(sent, total) => format!("Found {total} messages; the latest {sent} are listed."),
+		Frame::Head(0, _) => String::from("The history is empty."),
+		Frame::Head(latest, head) => format!("The history is chained up to #{latest}: {}", util::to_hex(head)),
+		Frame::Done(report) => report.to_string(),
+		Frame::Closing(reason) if reason.is_empty() => String::from("The server is closing."),
+		Frame::Closing(reason) => format!("The server is closing: {reason}"),
+		_ => None?,
+	};
+	Some(text)
+}
+
+/// Act upon a line of input; either a command or a message.
+pub fn execute(client: &mut Client, line: &str) -> Result<Executed, MessageError> {
+	if let Some(arguments) = line.strip_prefix("/search")
+		&& (arguments.is_empty() || arguments.starts_with(' '))
+	{
+		match search_query(arguments) {
+			Ok(query) => client.search(query)?,
+			Err(notice) => return Ok(Executed::Notice(notice)),
+		}
+	} else if let Some(arguments) = line.strip_prefix("/msg ") {
+		let Some((to, content)) = arguments
+			.trim_start()
+			.split_once(' ')
+		else {
+			return Ok(Executed::Notice(String::from("Usage: /msg <nickname> <text>")))
+		};
+		let to = match to
+			.trim_start_matches('@')
+			.parse::<Nickname>()
+		{
+			Ok(to) => to,
+			Err(error) => return Ok(Executed::Notice(format!("{error}"))),
+		};
+		return match client.send_to(to.clone(), content) {
+			Ok(()) => Ok(Executed::Direct(to, String::from(content))),
+			Err(error @ MessageError::TooLong(..)) => Ok(Executed::Notice(format!("Not sent: {error}"))),
+			Err(error) => Err(error),
+		}
+	} else if let Some(arguments) = line.strip_prefix("/export")
+		&& (arguments.is_empty() || arguments.starts_with(' '))
+	{
+		return match export(arguments) {
+			Ok(export) => {
+				client.history(export.query())?;
+				Ok(Executed::Export(export))
+			},
+			Err(notice) => Ok(Executed::Notice(notice)),
+		}
+	} else if let Some(command) = line.strip_prefix('/')
+		&& MODERATIONS.contains(
+			&command
+				.split_whitespace()
+				.next()
+				.unwrap_or_default(),
+		) {
+		match command.parse::<Moderate>() {
+			Ok(moderate) => client.moderate(moderate)?,
+			Err(error) => return Ok(Executed::Notice(error.to_string())),
+		}
+	} else if let Some(secret) = line.strip_prefix("/register ") {
+		client.register(secret)?;
+	} else if line == "/head" {
+		client.head()?;
+	} else if let Some(id) = line.strip_prefix("/profile ") {
+		match id.parse() {
+			Ok(id) => client.query_profile(id)?,
+			Err(error) => return Ok(Executed::Notice(error.to_string())),
+		}
+	} else if let Some(status) = line.strip_prefix("/status ") {
+		let profile = client
+			.get_profile()
+			.clone()
+			.status(status);
+		if let Err(error) = client.set_profile(profile) {
+			return Ok(Executed::Notice(error.to_string()))
+		}
+	} else if let Some(command) = line.strip_prefix('/')
+		&& let (state, status) = command
+			.split_once(' ')
+			.unwrap_or((command, ""))
+		&& let Ok(state) = state.parse::<State>()
+	{
+		client.set_presence(state, status)?;
+	} else {
+		return match client.send(line) {
+			Ok(()) => Ok(Executed::Message),
+			Err(error @ MessageError::TooLong(..)) => Ok(Executed::Notice(format!("Not sent: {error}"))),
+			Err(error) => Err(error),
+		}
+	}
+	Ok(Executed::Command)
+}
+
+/// Read the arguments of `/search`: `-c` to tell case apart, `@nickname`,
+/// `since:` and `until:` with a date or an age, and whatever is left to look
+/// for.
+fn search_query(arguments: &str) -> Result<SearchQuery, String> {
+	let mut query = SearchQuery::new();
+	let mut words = Vec::new();
+	for word in arguments.split_whitespace() {
+		if word == "-c" {
+			query.set_case_sensitive(true);
+		} else if let Some(nickname) = word.strip_prefix('@') {
+			query.set_nickname(nickname);
+		} else if let Some(time) = word.strip_prefix("since:") {
+			query.set_since(Some(point_in_time(time)?));
+		} else if let Some(time) = word.strip_prefix("until:") {
+			query.set_until(Some(point_in_time(time)?));
+		} else {
+			words.push(word);
+		}
+	}
+	query.set_text(words.join(" "));
+	if query == SearchQuery::new() {
+		return Err(String::from("Usage: /search [-c] [@nickname] [since:…] [until:…] [text]"))
+	}
+	Ok(query)
+}
+
+/// Read the arguments of `/export`: the form of the transcript, the file to
+/// write it to, and `since:` and `until:` with a date or an age.
+fn export(arguments: &str) -> Result<Export, String> {
+	const USAGE: &str = "Usage: /export <text|md|html|jsonl> <file> [since:…] [until:…]";
+	let mut words = arguments.split_whitespace();
+	let (Some(transcript), Some(path)) = (words.next(), words.next()) else { return Err(String::from(USAGE)) };
+	let transcript = transcript
+		.parse::<Transcript>()
+		.map_err(|error| error.to_string())?;
+	let (mut since, mut until) = (None, None);
+	for word in words {
+		if let Some(time) = word.strip_prefix("since:") {
+			since = Some(point_in_time(time)?);
+		} else if let Some(time) = word.strip_prefix("until:") {
+			until = Some(point_in_time(time)?);
+		} else {
+			return Err(String::from(USAGE))
+		}
+	}
+	Ok(Export::new(transcript, path.into(), since, until))
+}
+
+/// Read a date, like `2026-10-19`, as its local midnight, or an age, like
+/// `7d`, `12h` or `30m`, as that long ago.
+pub fn point_in_time(text: &str) -> Result<DateTime<Utc>, String> {
+	let unit = match text
+		.chars()
+		.last()
+	{
+		Some('d') => Some(TimeDelta::days as fn(i64) -> TimeDelta),
+		Some('h') => Some(TimeDelta::hours as fn(i64) -> TimeDelta),
+		Some('m') => Some(TimeDelta::minutes as fn(i64) -> TimeDelta),
+		_ => None,
+	};
+	if let Some(unit) = unit
+		&& let Ok(amount) = text[.. text.len() - 1].parse::<u32>()
+	{
+		return Ok(Utc::now() - unit(amount as i64))
+	}
+	NaiveDate::parse_from_str(text, "%Y-%m-%d")
+		.ok()
+		.and_then(|date| {
+			date.and_hms_opt(0, 0, 0)?
+				.and_local_timezone(Local)
+				.earliest()
+		})
+		.map(|midnight| midnight.to_utc())
+		.ok_or_else(|| format!("`{text}` is neither a date, like 2026-10-19, nor an age, like 7d."))
+}
+
+/// Take over the terminal until the user leaves or the server goes away.
+///
+/// With `receipts`, every message shown is marked as read.
+pub fn run(client: Client, receipts: bool) -> util::Result<()> {
+	let mut terminal = ratatui::init();
+	let view = View {
+		receipts,
+		own: Some(client.get_identifier()),
+		..View::default()
+	};
+	let outcome = view.run_on(&mut terminal, client);
+	ratatui::restore();
+	outcome
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl View {
+	fn run_on(mut self, terminal: &mut DefaultTerminal, mut client: Client) -> util::Result<()> {
+		let incoming = client
+			.incoming()
+			.clone();
+		while client.is_connected() {
+			for frame in incoming.try_iter() {
+				// NOTE: the pages of an export are not shown; they were shown once already.
+				if let Some(export) = &mut self.export {
+					match export.take(&frame) {
+						Progress::Unrelated => (),
+						Progress::Pending => continue,
+						Progress::Done => {
+							if let Some(export) = self
+								.export
+								.take()
+							{
+								self.push(&export.finish(), None);
+							}
+							continue
+						},
+					}
+				}
+				if let Frame::Message(message) | Frame::Backfill(message) = &frame
+					&& self.receipts
+					&& *message.get_identifier() != client.get_identifier()
+				{
+					client.mark_read(message.get_sequence())?;
+				}
+				self.receive(frame);
+			}
+			self.typing
+				.retain(|_, (_, since)| since.elapsed() < TYPING_TIMEOUT);
+
+			terminal.draw(|screen| self.draw(screen))?;
+
+			if !event::poll(TICK)? {
+				continue
+			}
+			let Event::Key(key) = event::read()? else { continue };
+			if key.kind != KeyEventKind::Press {
+				continue
+			}
+			match key.code {
+				KeyCode::Esc => break,
+				KeyCode::Char('c' | 'd')
+					if key
+						.modifiers
+						.contains(KeyModifiers::CONTROL) =>
+				{
+					break
+				},
+				KeyCode::Enter => {
+					let line = take(&mut self.input);
+					if line.is_empty() {
+						continue
+					}
+					if let Some(hit) = line.strip_prefix("/jump ") {
+						self.jump(&mut client, hit)?;
+						continue
+					}
+					if self
+						.export
+						.is_some() && line.starts_with("/export")
+					{
+						self.push(EXPORTING, None);
+						continue
+					}
+					match execute(&mut client, &line)? {
+						Executed::Command if line.starts_with("/search") => self
+							.hits
+							.clear(),
+						Executed::Command => (),
+						Executed::Message => {
+							self.pending
+								.push_back(self.lines.len());
+							self.lines
+								.push(Entry {
+									text: format!("#{} {:#}: {line}", client.get_identifier(), client.get_nickname()),
+									receipt: Some(Receipt::Sent),
+									sequence: None,
+								});
+						},
+						Executed::Direct(to, content) => self.push(
+							&format!("#{} {:#} → {to:#}: {content}", client.get_identifier(), client.get_nickname()),
+							None,
+						),
+						Executed::Notice(notice) => self.push(&notice, None),
+						Executed::Export(export) => self.export = Some(export),
+					}
+					continue
+				},
+				KeyCode::PageUp => {
+					let top = self
+						.top
+						.unwrap_or(
+							self.lines
+								.len()
+								.saturating_sub(self.height),
+						);
+					self.top = Some(top.saturating_sub(self.height));
+					continue
+				},
+				KeyCode::PageDown => {
+					// NOTE: scrolling down onto the latest lines follows them again.
+					self.top = self
+						.top
+						.map(|top| top + self.height)
+						.filter(|top| top + self.height < self.lines.len());
+					continue
+				},
+				KeyCode::End => {
+					self.top = None;
+					continue
+				},
+				KeyCode::Backspace => {
+					self.input
+						.pop();
+				},
+				KeyCode::Char(c) => self
+					.input
+					.push(c),
+				_ => continue,
+			}
+			if !self
+				.input
+				.is_empty()
+			{
+				client.typing()?;
+			}
+		}
+		Ok(client.close()?)
+	}
+
+	fn receive(&mut self, frame: Frame) {
+		match &frame {
+			Frame::Typing(id, nickname) => {
+				self.typing
+					.insert(*id, (nickname.clone(), Instant::now()));
+				return
+			},
+			// NOTE: whoever sent something, or left, is done typing.
+			Frame::Message(message) => {
+				self.typing
+					.remove(message.get_identifier());
+			},
+			Frame::Backfill(message) => {
+				if let Some(text) = describe(&frame) {
+					self.insert(message.get_sequence(), &text);
+				}
+				return
+			},
+			Frame::Hit(message) => {
+				self.hits
+					.push(message.get_sequence());
+				if let Some(text) = describe(&frame) {
+					self.push(&format!("{}. {text}", self.hits.len()), None);
+				}
+				return
+			},
+			Frame::Presence(presence) if presence.get_state() == State::Offline => {
+				self.typing
+					.remove(presence.get_identifier());
+			},
+			Frame::Ack(sequence) => {
+				if let Some(line) = self
+					.pending
+					.pop_front()
+				{
+					self.lines[line].receipt = Some(Receipt::Delivered(*sequence));
+					self.lines[line].sequence = Some(*sequence);
+				}
+				return
+			},
+			// NOTE: the reason is shown below, like any other error.
+			Frame::Refused(_) => {
+				if let Some(line) = self
+					.pending
+					.pop_front()
+				{
+					self.lines[line].receipt = Some(Receipt::Refused);
+				}
+			},
+			Frame::Read(id, sequence) => {
+				let read = self
+					.reads
+					.entry(*id)
+					.or_default();
+				*read = (*read).max(*sequence);
+				return
+			},
+			_ => (),
+		}
+		let sequence = match &frame {
+			Frame::Message(message) => Some(message.get_sequence()),
+			_ => None,
+		};
+		if let Some(text) = describe(&frame) {
+			self.push(&text, sequence);
+		}
+	}
+
+	/// Show some text below everything else.
+	fn push(&mut self, text: &str, sequence: Option<u64>) {
+		self.lines
+			.extend(text
+				.lines()
+				.map(|line| Entry {
+					text: String::from(line),
+					receipt: None,
+					sequence,
+				}));
+	}
+
+	/// Show a message from the history among the others, by its sequence,
+	/// unless it is shown already.
+	fn insert(&mut self, sequence: u64, text: &str) {
+		if self
+			.lines
+			.iter()
+			.any(|line| line.sequence == Some(sequence))
+		{
+			return
+		}
+		// NOTE: history goes above whatever came after it, however late it arrives.
+		let at = self
+			.lines
+			.iter()
+			.position(|line| {
+				line.sequence
+					.is_some_and(|other| other > sequence)
+			})
+			.unwrap_or(self.lines.len());
+		let entries: Vec<Entry> = text
+			.lines()
+			.map(|line| Entry {
+				text: String::from(line),
+				receipt: None,
+				sequence: Some(sequence),
+			})
+			.collect();
+		let count = entries.len();
+		self.lines
+			.splice(at .. at, entries);
+		for line in &mut self.pending {
+			if *line >= at {
+				*line += count;
+			}
+		}
+		if let Some(top) = &mut self.top
+			&& *top >= at
+		{
+			*top += count;
+		}
+		if self.jumping == Some(sequence) {
+			self.jumping = None;
+			self.top = Some(at);
+		}
+	}
+
+	/// Scroll to a hit of the last search, asking the server for the history
+	/// from it onward, if it is not shown yet.
+	fn jump(&mut self, client: &mut Client, hit: &str) -> Result<(), MessageError> {
+		let Some(&sequence) = hit
+			.trim()
+			.parse::<usize>()
+			.ok()
+			.and_then(|hit| {
+				self.hits
+					.get(hit.checked_sub(1)?)
+			})
+		else {
+			self.push(&format!("There is no hit `{}` of the last search.", hit.trim()), None);
+			return Ok(())
+		};
+		match self
+			.lines
+			.iter()
+			.position(|line| line.sequence == Some(sequence))
+		{
+			Some(at) => self.top = Some(at),
+			None => {
+				self.jumping = Some(sequence);
+				client.history(HistoryQuery::After(sequence.saturating_sub(1)))?
+			},
+		}
+		Ok(())
+	}
+
+	fn draw(&mut self, screen: &mut Screen) {
+		let [messages, typing, input] = Layout::vertical([
+			Constraint::Min(1),
+			Constraint::Length(1),
+			Constraint::Length(3),
+		])
+		.areas(screen.area());
+
+		let height = messages
+			.height
+			.saturating_sub(2) as usize;
+		self.height = height;
+		// NOTE: read by anyone, save for ourselves.
+		let read = self
+			.reads
+			.iter()
+			.filter(|(id, _)| Some(**id) != self.own)
+			.map(|(_, sequence)| *sequence)
+			.max()
+			.unwrap_or(0);
+		let latest = self
+			.lines
+			.len()
+			.saturating_sub(height);
+		let start = self
+			.top
+			.map_or(latest, |top| top.min(latest));
+		let shown: Vec<Line> = self
+			.lines
+			.iter()
+			.skip(start)
+			.take(height)
+			.map(|Entry { text, receipt, .. }| match receipt {
+				None => Line::raw(text.as_str()),
+				Some(Receipt::Sent) => Line::raw(format!("{text} ·")),
+				Some(Receipt::Delivered(sequence)) if *sequence <= read => Line::raw(format!("{text} ✓✓")),
+				Some(Receipt::Delivered(_)) => Line::raw(format!("{text} ✓")),
+				Some(Receipt::Refused) => Line::raw(format!("{text} ✗")),
+			})
+			.collect();
+		let title = if self
+			.top
+			.is_some()
+		{
+			format!("{} (scrolled back; End to follow)", env!("CARGO_PKG_NAME"))
+		} else {
+			String::from(env!("CARGO_PKG_NAME"))
+		};
+		screen.render_widget(Paragraph::new(shown).block(Block::bordered().title(title)), messages);
+		screen.render_widget(Paragraph::new(self.typing_line()), typing);
+		screen.render_widget(
+			Paragraph::new(self.input.as_str()).block(Block::bordered()),
+			input,
+		);
+		screen.set_cursor_position(Position::new(
+			input.x + 1 + self.input.width() as u16,
+			input.y + 1,
+		));
+	}
+
+	fn typing_line(&self) -> String {
+		let mut names: Vec<String> = self
+			.typing
+			.values()
+			.map(|(nickname, _)| format!("{nickname:#}"))
+			.collect();
+		names.sort();
+		match names.as_slice() {
+			[] => String::new(),
+			[one] => format!("{one} is typing…"),
+			[one, two] => format!("{one} and {two} are typing…"),
+			_ => String::from("Several people are typing…"),
+		}
+	}
+}
@@ -0,0 +1,46 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//! Every message is answered by exactly one acknowledgement or refusal; so,
+//! that a client can tell which of its messages each answer is about.
+use std::time::Duration;
+
+use besked::Frame;
+
+use crate::common::{Peer, Server};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+mod common;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How many messages are sent at once; more than the default burst lets
+/// through, but fewer than get a user cut off.
+const SENT: usize = 18;
+/// How long every answer is waited for.
+const WITHIN: Duration = Duration::from_secs(5);
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+#[test]
+fn threaded_answers() { answers("threads") }
+
+#[test]
+fn events_answers() { answers("events") }
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+fn answers(core: &str) {
+	let server = Server::start(&["--core", core, "--heartbeat", "0"]);
+	let mut peer = Peer::join(&server, "eager");
+	for round in 0 .. SENT {
+		peer.say(&format!("round {round}"));
+	}
+
+	let (mut acked, mut refused) = (0, 0);
+	peer.until(WITHIN, |frame| {
+		match frame {
+			Frame::Ack(_) => acked += 1,
+			Frame::Refused(_) => refused += 1,
+			_ => (),
+		}
+		acked + refused == SENT
+	});
+	assert_eq!(acked + refused, SENT, "{acked} were acknowledged and {refused} refused");
+	assert!(refused > 0, "none of the messages were refused");
+	assert!(
+		!peer.until(Duration::from_millis(500), |frame| matches!(frame, Frame::Ack(_) | Frame::Refused(_))),
+		"a message was answered twice"
+	);
+}
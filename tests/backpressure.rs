@@ -0,0 +1,69 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//! A client, that stops reading, never holds up another; whichever way its
+//! outbox overflows.
+use std::time::Duration;
+
+use besked::Frame;
+
+use crate::common::{Peer, Server, UNLIMITED};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+mod common;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How many messages the normal client sends; far more than the outbox and
+/// the socket of the stalled one hold together.
+const SENT: usize = 1000;
+/// How long each of them is.
+const LENGTH: usize = 8 * 1024;
+/// How many frames an outbox holds.
+const OUTBOX: &str = "32";
+/// How long any message of the normal client may take to be acknowledged.
+const BOUND: Duration = Duration::from_secs(1);
+/// How long the stalled client waits for more, once it reads again.
+const QUIET: Duration = Duration::from_secs(1);
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+#[test]
+fn threaded_drop_oldest() { assert!(stall("threads", "drop-oldest") < SENT) }
+
+#[test]
+fn events_drop_oldest() { assert!(stall("events", "drop-oldest") < SENT) }
+
+#[test]
+fn threaded_disconnect() { assert!(stall("threads", "disconnect") < SENT) }
+
+#[test]
+fn events_disconnect() { assert!(stall("events", "disconnect") < SENT) }
+
+#[test]
+fn threaded_spill() { assert_eq!(stall("threads", "spill"), SENT) }
+
+#[test]
+fn events_spill() { assert_eq!(stall("events", "spill"), SENT) }
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// Have a normal client send, while another one doesn't read; tells how many
+/// of the messages the stalled one got in the end.
+fn stall(core: &str, overflow: &str) -> usize {
+	let server = Server::start(&[&UNLIMITED[..], &["--core", core, "--outbox", OUTBOX, "--overflow", overflow]].concat());
+	let mut stalled = Peer::join(&server, "stalled");
+	let mut normal = Peer::join(&server, "normal");
+
+	let content = "x".repeat(LENGTH);
+	for round in 0 .. SENT {
+		assert!(
+			normal
+				.acked(&content, BOUND)
+				.is_some(),
+			"round {round} was not acknowledged within {BOUND:?}"
+		);
+	}
+
+	let mut received = 0;
+	while stalled.until(QUIET, |frame| matches!(frame, Frame::Message(_))) {
+		received += 1;
+	}
+	if overflow == "disconnect" {
+		assert!(stalled
+			.cut_within(QUIET)
+			.is_some());
+	}
+	received
+}
@@ -0,0 +1,285 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// NOTE: shared by every test, and the benchmark; not each uses all of it.
+#![allow(dead_code)]
+
+use std::{
+	io::{self, ErrorKind, Read, Write},
+	net::{IpAddr, SocketAddr, TcpListener, TcpStream},
+	process::{Child, Command, Stdio},
+	thread::sleep,
+	time::{Duration, Instant},
+};
+
+use besked::{Frame, Identifier, Message, Nickname, Profile, errors::MessageError};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How long a server is given to start listening.
+const STARTUP: Duration = Duration::from_secs(10);
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A server, that runs as a process of its own, for as long as this lives.
+pub struct Server {
+	child: Child,
+	address: SocketAddr,
+}
+
+/// A client, that speaks the protocol frame by frame, and blocks while it
+/// waits for one.
+pub struct Peer {
+	stream: TcpStream,
+	/// What the server welcomed it as; empty, until it joined.
+	id: Identifier,
+	nickname: Nickname,
+}
+
+/// Frames, as they are taken apart from whatever bytes came in.
+#[derive(Default)]
+pub struct Frames {
+	inbound: Vec<u8>,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Server {
+	/// Start a server on a free port of the host, with the given options
+	/// besides; once it listens.
+	#[inline(always)]
+	pub fn start(options: &[&str]) -> Self { Self::start_from(env!("CARGO_BIN_EXE_prata"), options) }
+
+	/// Start a server, like [`start`], only from the given build.
+	///
+	/// [`start`]: Self::start
+	pub fn start_from(program: &str, options: &[&str]) -> Self {
+		let port = TcpListener::bind((host(), 0))
+			.and_then(|listener| listener.local_addr())
+			.expect("no port is free")
+			.port();
+		let address = SocketAddr::new(host(), port);
+		let child = Command::new(program)
+			.args(["--server", "--port", &port.to_string()])
+			.args(options)
+			.stdin(Stdio::null())
+			.stdout(Stdio::null())
+			.stderr(Stdio::null())
+			.spawn()
+			.expect("the server could not be started");
+		let mut server = Self { child, address };
+
+		let until = Instant::now() + STARTUP;
+		while TcpStream::connect(address).is_err() {
+			if Instant::now() > until
+				|| server
+					.child
+					.try_wait()
+					.is_ok_and(|status| status.is_some())
+			{
+				panic!("the server did not start to listen on `{address}`");
+			}
+			sleep(Duration::from_millis(20));
+		}
+		server
+	}
+
+	#[inline(always)]
+	pub const fn get_address(&self) -> SocketAddr { self.address }
+	/// The process, that it runs as.
+	#[inline(always)]
+	pub fn get_process(&self) -> u32 { self.child.id() }
+}
+
+impl Peer {
+	/// Connect to the server, without joining.
+	pub fn connect(server: &Server) -> Self {
+		let stream = TcpStream::connect(server.get_address()).expect("the server could not be reached");
+		stream
+			.set_nodelay(true)
+			.expect("the delay could not be turned off");
+		Self {
+			stream,
+			id: Identifier::empty(),
+			nickname: Nickname::new(),
+		}
+	}
+
+	/// Connect to the server, and join under the given nickname; once it is
+	/// welcomed.
+	pub fn join(server: &Server, nickname: &str) -> Self {
+		let mut peer = Self::connect(server);
+		peer.send(&join(nickname));
+		let mut welcome = None;
+		assert!(
+			peer.until(STARTUP, |frame| {
+				if let Frame::Welcome(id, nickname, _) = frame {
+					welcome = Some((*id, nickname.clone()));
+				}
+				welcome.is_some()
+			}),
+			"`{nickname}` was not welcomed"
+		);
+		(peer.id, peer.nickname) = welcome.unwrap_or_default();
+		peer
+	}
+
+	pub fn send(&mut self, frame: &Frame) {
+		frame
+			.send(&mut self.stream)
+			.expect("a frame could not be sent");
+	}
+
+	/// Write raw bytes, whether they make up a frame or not.
+	pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+		self.stream
+			.write_all(bytes)
+	}
+
+	/// A message with the given content, as it would send it.
+	pub fn message(&self, content: &str) -> Frame {
+		Frame::Message(
+			Message::with_identifier(self.id)
+				.nickname(self.nickname.clone())
+				.content(content),
+		)
+	}
+
+	#[inline(always)]
+	pub fn say(&mut self, content: &str) { self.send(&self.message(content)) }
+
+	/// Read until a frame meets the condition, and tell whether one did before
+	/// the time ran out, or the connection ended.
+	pub fn until(&mut self, within: Duration, mut condition: impl FnMut(&Frame) -> bool) -> bool {
+		let until = Instant::now() + within;
+		loop {
+			let left = until.saturating_duration_since(Instant::now());
+			if left.is_zero() {
+				return false
+			}
+			self.stream
+				.set_read_timeout(Some(left))
+				.expect("the timeout could not be set");
+			match Frame::recv(&mut self.stream) {
+				Ok(frame) if condition(&frame) => return true,
+				Ok(_) => (),
+				Err(_) => return false,
+			}
+		}
+	}
+
+	/// Send a message, and wait until the server acknowledges it; tells how
+	/// long that took.
+	pub fn acked(&mut self, content: &str, within: Duration) -> Option<Duration> {
+		let start = Instant::now();
+		self.say(content);
+		self.until(within, |frame| matches!(frame, Frame::Ack(_)))
+			.then(|| start.elapsed())
+	}
+
+	/// Wait until the server ends the connection, skipping whatever it sends
+	/// before; tells how long that took, if it did in time.
+	pub fn cut_within(&mut self, within: Duration) -> Option<Duration> {
+		let start = Instant::now();
+		let mut chunk = [0; 4096];
+		loop {
+			let left = within.saturating_sub(start.elapsed());
+			if left.is_zero() {
+				return None
+			}
+			self.stream
+				.set_read_timeout(Some(left))
+				.expect("the timeout could not be set");
+			match self
+				.stream
+				.read(&mut chunk)
+			{
+				Ok(0) => return Some(start.elapsed()),
+				Ok(_) => (),
+				Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => return None,
+				Err(error) if error.kind() == ErrorKind::Interrupted => (),
+				Err(_) => return Some(start.elapsed()),
+			}
+		}
+	}
+
+	/// Another handle to the connection, to write to it from elsewhere.
+	pub fn writer(&self) -> TcpStream {
+		self.stream
+			.try_clone()
+			.expect("the connection could not be shared")
+	}
+}
+
+impl Frames {
+	pub fn extend(&mut self, bytes: &[u8]) {
+		self.inbound
+			.extend_from_slice(bytes)
+	}
+
+	/// Take the next whole frame out of what came in.
+	pub fn next_frame(&mut self) -> Result<Option<Frame>, MessageError> {
+		let Some(head) = self
+			.inbound
+			.first_chunk::<{ Frame::HEAD_LENGTH }>()
+		else {
+			return Ok(None)
+		};
+		let [kind, length @ ..] = *head;
+		let end = Frame::HEAD_LENGTH + <u32>::from_be_bytes(length) as usize;
+		if self.inbound.len() < end {
+			return Ok(None)
+		}
+		let frame = Frame::from_body(kind, &self.inbound[Frame::HEAD_LENGTH .. end]);
+		self.inbound
+			.drain(.. end);
+		frame.map(Some)
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Drop for Server {
+	fn drop(&mut self) {
+		let _ = self
+			.child
+			.kill();
+		let _ = self
+			.child
+			.wait();
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// The address, that a server listens on; the host's own.
+pub fn host() -> IpAddr { local_ip_address::local_ip().expect("the host has no address") }
+
+/// The options, that lift every limit of a server, which a test would run
+/// into by itself.
+pub const UNLIMITED: [&str; 12] = [
+	"--user-messages",
+	"off",
+	"--user-bytes",
+	"off",
+	"--address-messages",
+	"off",
+	"--address-bytes",
+	"off",
+	"--heartbeat",
+	"0",
+	"--per-address",
+	"0",
+];
+
+/// A [`Join`] under the given nickname, without a profile or secret.
+///
+/// [`Join`]: Frame::Join
+pub fn join(nickname: &str) -> Frame {
+	Frame::Join(
+		nickname
+			.parse::<Nickname>()
+			.expect("the nickname is invalid"),
+		Profile::new(),
+		"".into(),
+	)
+}
+
+/// The sample at the given fraction of the way from the fastest to the
+/// slowest.
+pub fn percentile(samples: &mut [Duration], fraction: f64) -> Duration {
+	samples.sort();
+	let index = ((samples.len() as f64 - 1.0) * fraction).round() as usize;
+	samples
+		.get(index)
+		.copied()
+		.unwrap_or_default()
+}
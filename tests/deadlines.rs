@@ -0,0 +1,122 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//! A client, that takes too long to finish a frame, is cut off once the
+//! trickle-deadline passes; whoever else is connected doesn't notice.
+use std::{
+	io::Write,
+	thread::{JoinHandle, sleep, spawn},
+	time::{Duration, Instant},
+};
+
+use besked::Frame;
+
+use crate::common::{Peer, Server, UNLIMITED};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+mod common;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// How long the rest of a frame may take, once it started to come in.
+const TRICKLE: Duration = Duration::from_secs(1);
+/// How late past the deadline a cut may come; the event-loop only looks as
+/// often as the deadline is long.
+const LATE: Duration = Duration::from_secs(2);
+/// How long any message of the other client may take to be acknowledged.
+const ECHO: Duration = Duration::from_millis(500);
+/// How many messages the other client sends, while the slow one is cut off.
+const ECHOES: usize = 25;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+#[test]
+fn threaded_trickle() { trickle("threads") }
+
+#[test]
+fn events_trickle() { trickle("events") }
+
+#[test]
+fn threaded_stall() { stall("threads") }
+
+#[test]
+fn events_stall() { stall("events") }
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+fn start(core: &str) -> Server {
+	Server::start(&[&UNLIMITED[..], &["--core", core, "--trickle", &TRICKLE.as_secs().to_string()]].concat())
+}
+
+/// Keep a client busy with messages, each of which has to come back in time;
+/// hands back the slowest.
+fn echoing(mut peer: Peer) -> JoinHandle<Duration> {
+	spawn(move || {
+		(0 .. ECHOES)
+			.map(|round| {
+				let took = peer
+					.acked(&format!("round {round}"), ECHO)
+					.unwrap_or_else(|| panic!("round {round} was not acknowledged within {ECHO:?}"));
+				sleep(Duration::from_millis(100));
+				took
+			})
+			.max()
+			.unwrap_or_default()
+	})
+}
+
+/// Write a message a byte at a time, far slower than the deadline allows.
+fn trickle(core: &str) {
+	let server = start(core);
+	let other = echoing(Peer::join(&server, "other"));
+	let mut slow = Peer::join(&server, "slow");
+
+	let bytes = slow
+		.message("one byte at a time")
+		.to_bytes()
+		.expect("the message could not be encoded");
+	let mut writer = slow.writer();
+	let started = Instant::now();
+	spawn(move || {
+		for byte in bytes {
+			if writer
+				.write_all(&[byte])
+				.is_err()
+			{
+				break
+			}
+			sleep(Duration::from_millis(100));
+		}
+	});
+
+	let cut = slow
+		.cut_within(TRICKLE + LATE)
+		.map(|_| started.elapsed());
+	assert!(
+		cut.is_some_and(|cut| cut >= TRICKLE),
+		"the trickling client was cut off after {cut:?}, not after {TRICKLE:?}"
+	);
+	let slowest = other
+		.join()
+		.expect("the other client failed");
+	assert!(slowest < ECHO, "the other client waited {slowest:?}");
+}
+
+/// Write half of a head, and nothing more.
+fn stall(core: &str) {
+	let server = start(core);
+	let other = echoing(Peer::join(&server, "other"));
+	let mut stalled = Peer::join(&server, "stalled");
+
+	let bytes = stalled
+		.message("never finished")
+		.to_bytes()
+		.expect("the message could not be encoded");
+	let started = Instant::now();
+	stalled
+		.write(&bytes[.. Frame::HEAD_LENGTH / 2])
+		.expect("the head could not be written");
+
+	let cut = stalled
+		.cut_within(TRICKLE + LATE)
+		.map(|_| started.elapsed());
+	assert!(
+		cut.is_some_and(|cut| cut >= TRICKLE),
+		"the stalled client was cut off after {cut:?}, not after {TRICKLE:?}"
+	);
+	let slowest = other
+		.join()
+		.expect("the other client failed");
+	assert!(slowest < ECHO, "the other client waited {slowest:?}");
+}
@@ -0,0 +1,216 @@
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+//! Thousands of clients on the event-loop core at once; every one of them
+//! has to stay connected, and hear every broadcast in time.
+//!
+//! Opt-in, as it opens as many sockets on either end; best run as
+//! `cargo test --release --test soak -- --ignored --nocapture`.
+use std::{
+	io::{ErrorKind, Read, Write},
+	net::TcpStream as StdStream,
+	time::{Duration, Instant},
+};
+
+use besked::{Frame, Identifier, Message, Nickname};
+use mio::{Events, Interest, Poll, Token, net::TcpStream};
+
+use crate::common::{Frames, Server, UNLIMITED, join, percentile};
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+mod common;
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+const CONNECTIONS: usize = 5000;
+const BROADCASTS: usize = 10;
+/// How long every client is given to be welcomed.
+const WELCOMED_WITHIN: Duration = Duration::from_secs(120);
+/// How long a broadcast may take to reach everyone.
+const LATENCY: Duration = Duration::from_secs(5);
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+/// A client, as the soak sees it.
+struct Client {
+	stream: TcpStream,
+	frames: Frames,
+	/// What it was welcomed as; none, until it is.
+	welcome: Option<(Identifier, Nickname)>,
+	live: bool,
+	/// How many messages it heard, since the latest broadcast was sent.
+	heard: usize,
+}
+
+/// Every client, and the poll, that tells which can be read from.
+struct Soak {
+	poll: Poll,
+	events: Events,
+	clients: Vec<Client>,
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+impl Soak {
+	/// Read whatever came in within the given time.
+	fn pump(&mut self, within: Duration) {
+		self.poll
+			.poll(&mut self.events, Some(within))
+			.expect("the poll failed");
+		for event in self.events.iter() {
+			self.clients[event
+				.token()
+				.0]
+				.read();
+		}
+	}
+
+	fn welcomed(&self) -> usize {
+		self.clients
+			.iter()
+			.filter(|client| client.welcome.is_some())
+			.count()
+	}
+
+	fn live(&self) -> usize {
+		self.clients
+			.iter()
+			.filter(|client| client.live)
+			.count()
+	}
+
+	/// How many heard the latest broadcast.
+	fn reached(&self) -> usize {
+		self.clients
+			.iter()
+			.filter(|client| client.heard > 0)
+			.count()
+	}
+}
+
+impl Client {
+	/// Read everything there is, and take it apart.
+	fn read(&mut self) {
+		let mut chunk = [0; 16 * 1024];
+		loop {
+			match self
+				.stream
+				.read(&mut chunk)
+			{
+				Ok(0) => {
+					self.live = false;
+					return
+				},
+				Ok(length) => self
+					.frames
+					.extend(&chunk[.. length]),
+				Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+				Err(error) if error.kind() == ErrorKind::Interrupted => (),
+				Err(_) => {
+					self.live = false;
+					return
+				},
+			}
+		}
+		while let Ok(Some(frame)) = self
+			.frames
+			.next_frame()
+		{
+			match frame {
+				Frame::Welcome(id, nickname, _) => self.welcome = Some((id, nickname)),
+				Frame::Message(_) => self.heard += 1,
+				_ => (),
+			}
+		}
+	}
+
+	/// Write the whole frame, however long the socket takes for it.
+	fn send(&mut self, bytes: &[u8]) {
+		let mut written = 0;
+		while written < bytes.len() {
+			match self
+				.stream
+				.write(&bytes[written ..])
+			{
+				Ok(length) => written += length,
+				Err(error) if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => (),
+				Err(error) => panic!("the broadcast could not be sent: {error}"),
+			}
+		}
+	}
+}
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+#[test]
+#[ignore = "opens 5,000 connections; run with `--ignored`"]
+fn five_thousand() {
+	let server = Server::start(&[&UNLIMITED[..], &["--core", "events"]].concat());
+	let mut soak = Soak {
+		poll: Poll::new().expect("no poll could be made"),
+		events: Events::with_capacity(1024),
+		clients: Vec::with_capacity(CONNECTIONS),
+	};
+
+	let started = Instant::now();
+	for index in 0 .. CONNECTIONS {
+		let mut stream = StdStream::connect(server.get_address()).expect("the server could not be reached");
+		stream
+			.set_nodelay(true)
+			.expect("the delay could not be turned off");
+		join(&format!("soak{index}"))
+			.send(&mut stream)
+			.expect("the join could not be sent");
+		stream
+			.set_nonblocking(true)
+			.expect("the connection could not be made non-blocking");
+		let mut stream = TcpStream::from_std(stream);
+		soak.poll
+			.registry()
+			.register(&mut stream, Token(index), Interest::READABLE)
+			.expect("the connection could not be registered");
+		soak.clients
+			.push(Client {
+				stream,
+				frames: Frames::default(),
+				welcome: None,
+				live: true,
+				heard: 0,
+			});
+		if index % 100 == 99 {
+			soak.pump(Duration::ZERO);
+		}
+	}
+	let until = Instant::now() + WELCOMED_WITHIN;
+	while soak.welcomed() < CONNECTIONS && soak.live() == CONNECTIONS && Instant::now() < until {
+		soak.pump(Duration::from_millis(100));
+	}
+	println!("{} of {CONNECTIONS} were welcomed within {:?}.", soak.welcomed(), started.elapsed());
+	assert_eq!(soak.welcomed(), CONNECTIONS);
+
+	let (id, nickname) = soak.clients[0]
+		.welcome
+		.clone()
+		.unwrap_or_default();
+	let mut latencies = Vec::with_capacity(BROADCASTS);
+	for round in 0 .. BROADCASTS {
+		let message = Frame::Message(
+			Message::with_identifier(id)
+				.nickname(nickname.clone())
+				.content(format!("broadcast {round}")),
+		)
+		.to_bytes()
+		.expect("the broadcast could not be encoded");
+		let sent = Instant::now();
+		soak.clients[0].send(&message);
+		let until = sent + LATENCY;
+		while soak.reached() < soak.live() - 1 && Instant::now() < until {
+			soak.pump(Duration::from_millis(10));
+		}
+		let latency = sent.elapsed();
+		println!("Broadcast {round} reached {} in {latency:?}.", soak.reached());
+		assert_eq!(soak.reached(), CONNECTIONS - 1, "broadcast {round} did not reach everyone");
+		latencies.push(latency);
+		for client in &mut soak.clients {
+			client.heard = 0;
+		}
+	}
+
+	println!(
+		"{} of {CONNECTIONS} are live; a broadcast reached everyone in {:?} at the median, and {:?} at worst.",
+		soak.live(),
+		percentile(&mut latencies, 0.5),
+		percentile(&mut latencies, 1.0),
+	);
+	assert_eq!(soak.live(), CONNECTIONS);
+	assert!(percentile(&mut latencies, 1.0) < LATENCY);
+}